@@ -1,4 +1,5 @@
 use bevy::{audio::{PlaybackMode, Volume}, prelude::*};
+use serde::{Deserialize, Serialize};
 use strum::IntoEnumIterator;
 use strum_macros::{EnumIter, EnumProperty};
 use std::{ops::{Deref, DerefMut}, string::ToString, time::Duration};
@@ -46,7 +47,7 @@ impl AmbientAudio {
 //         Ambient Audio Type
 //==============================================================================
 
-#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, Reflect, EnumIter, strum_macros::Display)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, Reflect, EnumIter, strum_macros::Display, Serialize, Deserialize)]
 pub enum AmbientAudioType {
     Computer,
     Storm
@@ -1,3 +1,5 @@
+use std::time::Duration;
+
 use bevy::{audio::{PlaybackMode, Volume}, prelude::*};
 
 use crate::util::DelayedEventPlugin;
@@ -14,9 +16,10 @@ impl Plugin for SoundEffectPlugin {
             .add_plugins(DelayedEventPlugin::<PlaySoundEvent>::default())
         
             .add_systems(PreUpdate, update_sound_effects)
-            .add_systems(PostUpdate, catch_play_sound_events)
+            .add_systems(PostUpdate, (catch_play_sound_events, catch_play_tone_events))
             
             .add_event::<PlaySoundEvent>()
+            .add_event::<PlayToneEvent>()
             .add_event::<SoundFinishedEvent>()
         ;
         
@@ -50,6 +53,25 @@ impl PlaySoundEvent {
 #[derive(Debug, Event, Clone)]
 pub struct SoundFinishedEvent(Handle<AudioSource>);
 
+//==============================================================================
+//         PlayToneEvent
+//==============================================================================
+
+/// A plain sine tone, for beeps and modem noise that don't need a sound file.
+#[derive(Debug, Event, Clone, Copy)]
+pub struct PlayToneEvent {
+    pub frequency : f32,
+    /// In seconds.
+    pub duration : f32,
+    pub volume : f32,
+}
+
+impl PlayToneEvent {
+    pub fn new(frequency: f32, duration: f32, volume: f32) -> Self {
+        Self { frequency, duration, volume }
+    }
+}
+
 //==============================================================================
 //         PlaySound Systems
 //==============================================================================
@@ -77,6 +99,28 @@ pub fn catch_play_sound_events (
     }
 }
 
+pub fn catch_play_tone_events (
+    mut commands : Commands,
+    mut events : EventReader<PlayToneEvent>,
+    mut pitches : ResMut<Assets<Pitch>>,
+) {
+    for event in events.read() {
+        commands.spawn((
+            PitchBundle {
+                source: pitches.add(Pitch::new(event.frequency, Duration::from_secs_f32(event.duration))),
+                settings: PlaybackSettings {
+                    mode: PlaybackMode::Once,
+                    volume: Volume::new(event.volume),
+                    ..Default::default()
+                },
+                ..default()
+            },
+            SoundEffect,
+            Name::new("Tone")
+        ));
+    }
+}
+
 pub fn update_sound_effects(
     mut commands : Commands,
     sound_effects : Query<(Entity, Option<&AudioSink>, Option<&SpatialAudioSink>), With<SoundEffect>>,
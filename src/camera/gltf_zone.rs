@@ -0,0 +1,212 @@
+use bevy::{gltf::GltfExtras, prelude::*};
+use serde::Deserialize;
+
+use crate::{game::{ActiveCamera, GameState}, scene::SCREEN_NODE_PREFIX};
+
+use super::{zone::{CameraZone, CameraZoneAction, CameraZoneSource, CurrentZone}, zone_hover::CameraZoneHighlight, zone_shape::CameraZoneShape};
+
+/// Any glTF node whose name starts with this is a camera zone, e.g.
+/// `Zone.Room` or `Zone.Desk`. A zone nested directly under another zone in
+/// the node tree is one of its children, so clicking it from the parent moves
+/// the camera there and backing up returns to the parent.
+pub const ZONE_NODE_PREFIX : &str = "Zone";
+
+/// A node whose name starts with this, directly under a zone, is where the
+/// camera sits when it is in that zone, e.g. `Camera.Desk`.
+pub const ZONE_CAMERA_PREFIX : &str = "Camera";
+
+//==============================================================================
+//         Gltf Zone Plugin
+//==============================================================================
+
+/// Builds the camera zones from the room model once it has been spawned, so
+/// they are laid out in the modeling tool instead of in code.
+pub struct GltfZonePlugin;
+
+impl Plugin for GltfZonePlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_systems(OnEnter(GameState::Active), spawn_gltf_zones.run_if(resource_equals(CameraZoneSource::Gltf)))
+        ;
+    }
+}
+
+//==============================================================================
+//         Zone Extras
+//==============================================================================
+
+/// The custom properties on a zone node, exported as glTF `extras`. All of
+/// them are optional.
+///
+/// - `zone_shape` : `"box"` (the default), `"sphere"`, `"capsule"` or `"mesh"`.
+///   A mesh zone is clicked on the node's own mesh, which is hidden.
+/// - `zone_bounds` : the size of a box, `[x, y, z]`. Defaults to a unit cube.
+/// - `zone_radius` : the radius of a sphere or capsule.
+/// - `zone_half_length` : how far the middle of a capsule reaches up and down from the zone.
+/// - `zone_action` : `"move"` (the default), `"computer"`, `"inspect"`, `"sound"`,
+///   `"flag"`, `"custom"` or `"wait"`, or a list of them to run in turn.
+///   `"move"`, `"inspect"` and `"computer"` take the camera to the zone's camera node.
+/// - `zone_screen` : for `"computer"`, the name of the screen node to sit down at.
+/// - `zone_sound` : for `"sound"`, the asset path of the sound to play.
+/// - `zone_flag` : for `"flag"`, the game flag to set, and `zone_flag_value`
+///   to clear it instead with `false`.
+/// - `zone_event` : for `"custom"`, the name of the event to send.
+/// - `zone_wait` : for `"wait"`, how many seconds to wait.
+/// - `zone_highlight` : the name of the node that lights up when the zone is hovered.
+#[derive(Debug, Default, Deserialize)]
+struct ZoneExtras {
+    #[serde(default)]
+    zone_shape : Option<String>,
+    #[serde(default)]
+    zone_bounds : Option<[f32; 3]>,
+    #[serde(default)]
+    zone_radius : Option<f32>,
+    #[serde(default)]
+    zone_half_length : Option<f32>,
+    #[serde(default)]
+    zone_action : Option<ZoneActionNames>,
+    #[serde(default)]
+    zone_screen : Option<String>,
+    #[serde(default)]
+    zone_sound : Option<String>,
+    #[serde(default)]
+    zone_flag : Option<String>,
+    #[serde(default)]
+    zone_flag_value : Option<bool>,
+    #[serde(default)]
+    zone_event : Option<String>,
+    #[serde(default)]
+    zone_wait : Option<f32>,
+    #[serde(default)]
+    zone_highlight : Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum ZoneActionNames {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl ZoneExtras {
+    /// The zone's action, or what is missing for it.
+    fn action(&self, camera : Option<Transform>) -> Result<CameraZoneAction, String> {
+        match &self.zone_action {
+            None => self.named_action("move", camera),
+            Some(ZoneActionNames::One(action)) => self.named_action(action, camera),
+            Some(ZoneActionNames::Many(actions)) => actions.iter()
+                .map(|action| self.named_action(action, camera))
+                .collect::<Result<Vec<_>, _>>()
+                .map(CameraZoneAction::Sequence),
+        }
+    }
+
+    fn named_action(&self, action : &str, camera : Option<Transform>) -> Result<CameraZoneAction, String> {
+        let missing = |property : &str| format!("'{}' needs a {} property", action, property);
+        let no_camera = || format!("'{}' has nowhere for the camera to go, add a {} node under it", action, ZONE_CAMERA_PREFIX);
+
+        match action {
+            "move" => camera.map(CameraZoneAction::Move).ok_or_else(no_camera),
+            "inspect" => camera.map(CameraZoneAction::Inspect).ok_or_else(no_camera),
+            "computer" => {
+                let screen = self.zone_screen.clone().unwrap_or_else(|| SCREEN_NODE_PREFIX.to_string());
+                camera.map(|seat| CameraZoneAction::sit_at_computer(screen, seat)).ok_or_else(no_camera)
+            },
+            "sound" => self.zone_sound.clone().map(CameraZoneAction::PlaySound).ok_or_else(|| missing("zone_sound")),
+            "flag" => self.zone_flag.clone().map(|flag| CameraZoneAction::SetFlag(flag, self.zone_flag_value.unwrap_or(true))).ok_or_else(|| missing("zone_flag")),
+            "custom" => self.zone_event.clone().map(CameraZoneAction::Custom).ok_or_else(|| missing("zone_event")),
+            "wait" => self.zone_wait.map(CameraZoneAction::Wait).ok_or_else(|| missing("zone_wait")),
+            _ => Err(format!("'{}' is not an action", action)),
+        }
+    }
+
+    fn parse(extras : Option<&GltfExtras>, name : &str) -> Self {
+        let Some(extras) = extras else { return Self::default() };
+        match serde_json::from_str(&extras.value) {
+            Ok(extras) => extras,
+            Err(error) => {
+                println!("Camera zone {} has extras that could not be read: {}", name, error);
+                Self::default()
+            }
+        }
+    }
+}
+
+//==============================================================================
+//         Gltf Zone Systems
+//==============================================================================
+
+fn spawn_gltf_zones(
+    mut commands : Commands,
+    mut cameras : Query<(Entity, &mut Transform), (With<ActiveCamera>, Without<CurrentZone>)>,
+    nodes : Query<(Entity, &Name, &GlobalTransform, Option<&GltfExtras>, Option<&Parent>, Option<&Children>)>,
+    meshes : Query<&Handle<Mesh>>,
+) {
+    let is_zone = |entity : Entity| nodes.get(entity).is_ok_and(|(_, name, ..)| name.starts_with(ZONE_NODE_PREFIX));
+
+    let mut roots = Vec::new();
+    for (entity, name, _, extras, parent, children) in nodes.iter() {
+        if !name.starts_with(ZONE_NODE_PREFIX) { continue }
+
+        let extras = ZoneExtras::parse(extras, name);
+
+        // The camera pose is the node's place in the world, not relative to the zone.
+        let camera = children.into_iter().flatten()
+            .filter_map(|child| nodes.get(*child).ok())
+            .find(|(_, child_name, ..)| child_name.starts_with(ZONE_CAMERA_PREFIX))
+            .map(|(_, _, camera_transform, ..)| camera_transform.compute_transform());
+
+        let event = match extras.action(camera) {
+            Ok(event) => event,
+            Err(error) => {
+                println!("Camera zone {} can't be used: {}", name, error);
+                continue;
+            },
+        };
+
+        let shape = match extras.zone_shape.as_deref() {
+            None | Some("box") => match extras.zone_bounds {
+                Some([x, y, z]) => CameraZoneShape::Box(Cuboid::new(x, y, z)),
+                None => CameraZoneShape::default(),
+            },
+            Some("sphere") => CameraZoneShape::Sphere(Sphere::new(extras.zone_radius.unwrap_or(0.5))),
+            Some("capsule") => CameraZoneShape::Capsule(Capsule::new(extras.zone_radius.unwrap_or(0.25), extras.zone_half_length.unwrap_or(0.5) * 2.0)),
+            Some("mesh") => {
+                // glTF puts a node's mesh on a child of its own.
+                let Some((collider, mesh)) = children.into_iter().flatten().find_map(|child| meshes.get(*child).ok().map(|mesh| (*child, mesh.clone()))) else {
+                    println!("Camera zone {} is a mesh zone without a mesh", name);
+                    continue;
+                };
+                commands.entity(collider).insert(Visibility::Hidden);
+                CameraZoneShape::Mesh(mesh)
+            },
+            Some(shape) => {
+                println!("Camera zone {} has an unknown shape '{}'", name, shape);
+                continue;
+            },
+        };
+
+        commands.entity(entity).insert(CameraZone::new(shape, event));
+        if let Some(highlight) = extras.zone_highlight {
+            commands.entity(entity).insert(CameraZoneHighlight(highlight));
+        }
+
+        if !parent.is_some_and(|parent| is_zone(**parent)) {
+            roots.push((entity, camera));
+        }
+    }
+
+    let Some((root, camera)) = roots.first().copied() else {
+        println!("The room has no camera zones, name a node {}.<Name> to add one", ZONE_NODE_PREFIX);
+        return;
+    };
+    if roots.len() > 1 {
+        println!("The room has {} top level camera zones, starting in the first", roots.len());
+    }
+
+    let Ok((camera_entity, mut camera_transform)) = cameras.get_single_mut() else { return };
+    if let Some(camera) = camera {
+        *camera_transform = camera;
+    }
+    commands.entity(camera_entity).insert(CurrentZone(root));
+}
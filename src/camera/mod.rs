@@ -2,10 +2,14 @@ pub mod path;
 pub mod fly;
 pub mod zone;
 pub mod blackout;
+pub mod gltf_zone;
+pub mod zone_graph;
+pub mod zone_hover;
+pub mod zone_shape;
 
 use bevy::prelude::*;
 
-use self::{blackout::BlackoutPlugin, fly::FlyCamPlugin, path::CameraPathPlugin, zone::CameraZonePlugin};
+use self::{blackout::BlackoutPlugin, fly::FlyCamPlugin, gltf_zone::GltfZonePlugin, path::CameraPathPlugin, zone::CameraZonePlugin, zone_graph::CameraZoneGraphPlugin, zone_hover::CameraZoneHoverPlugin};
 
 pub struct CameraPlugin;
 
@@ -15,6 +19,9 @@ impl Plugin for CameraPlugin {
             .add_plugins(FlyCamPlugin)
             .add_plugins(CameraPathPlugin)
             .add_plugins(CameraZonePlugin)
+            .add_plugins(GltfZonePlugin)
+            .add_plugins(CameraZoneGraphPlugin)
+            .add_plugins(CameraZoneHoverPlugin)
             .add_plugins(BlackoutPlugin)
         ;
     }
@@ -1,10 +1,10 @@
-use std::default;
+use std::{collections::VecDeque, default};
 
-use bevy::{math::bounding::{Bounded3d, RayCast3d}, prelude::*, window::PrimaryWindow};
+use bevy::{prelude::*, window::PrimaryWindow};
 
-use crate::{audio::ambient::{AmbientAudioEvent, AmbientAudioType}, camera::path::CameraPathFollower, game::{ActiveCamera, ToggleGameWorldEvent}, scene::RoomCamera, util::{DelayedEvent, DelayedEventPlugin}};
+use crate::{audio::{ambient::{AmbientAudioEvent, AmbientAudioType}, sound::PlaySoundEvent}, camera::path::CameraPathFollower, game::{ActiveCamera, GameFlags, ToggleGameWorldEvent}, scene::{computer_world::{ActiveComputer, Computer}, RoomCamera}, util::{DelayedEvent, DelayedEventPlugin}};
 
-use super::blackout::BlackoutTransition;
+use super::{blackout::BlackoutTransition, zone_shape::{CameraZoneShape, ZonePicker}};
 
 //==============================================================================
 //         CameraZone Plugin
@@ -15,10 +15,17 @@ pub struct CameraZonePlugin;
 impl Plugin for CameraZonePlugin {
     fn build(&self, app: &mut App) {
         app 
+            .add_plugins(DelayedEventPlugin::<CameraZoneActionEvent>::default())
             
-            .add_systems(Update, (detect_camera_zone_click, detect_back_up))
+            .add_systems(Update, (detect_camera_zone_click, detect_back_up, run_camera_zone_actions).chain())
             
+            .init_resource::<CameraZoneSource>()
+
+            .add_event::<CameraZoneActionEvent>()
+            .add_event::<CameraZoneCustomEvent>()
+
             .register_type::<CameraZone>()
+            .register_type::<CameraZoneShape>()
         ;
     }
 }
@@ -27,10 +34,73 @@ impl Plugin for CameraZonePlugin {
 //         CameraZone Type
 //==============================================================================
 
-#[derive(Debug, Event, Reflect)]
+/// What clicking a zone does. New kinds of objects are usually a
+/// [`CameraZoneAction::Sequence`] of these, with a [`CameraZoneAction::Custom`]
+/// for anything only that object does.
+#[derive(Debug, Clone, Reflect)]
 pub enum CameraZoneAction {
+    /// Moves the camera here and enters the zone, so the zones under it can be clicked.
     Move(Transform),
-    Computer
+    /// Makes the computer whose screen is on the glTF node with this name the
+    /// one the keyboard types into. See [`CameraZoneAction::sit_at_computer`].
+    Computer(String),
+    /// Switches the game over to the active computer's screen.
+    EnterComputer,
+    /// Fades the room to black over this many seconds.
+    FadeOut(f32),
+    /// Fades out a kind of ambient sound over this many seconds.
+    FadeOutAmbient(AmbientAudioType, f32),
+    /// Moves the camera here for a closer look without entering the zone.
+    /// Backing up returns to the zone the camera is in.
+    Inspect(Transform),
+    /// Plays the sound file at this asset path.
+    PlaySound(String),
+    /// Sets or clears a flag in [`GameFlags`].
+    SetFlag(String, bool),
+    /// Sends a [`CameraZoneCustomEvent`] with this name.
+    Custom(String),
+    /// Waits this many seconds before going on with a sequence.
+    Wait(f32),
+    /// Runs each action in turn.
+    Sequence(Vec<CameraZoneAction>),
+}
+
+impl CameraZoneAction {
+    /// Sitting down at a computer: the camera moves in front of the screen
+    /// while the room fades out, then the game switches to the computer.
+    pub fn sit_at_computer(screen : impl Into<String>, seat : Transform) -> Self {
+        Self::Sequence(vec![
+            Self::Computer(screen.into()),
+            Self::Inspect(seat),
+            Self::FadeOut(1.0),
+            Self::FadeOutAmbient(AmbientAudioType::Storm, 1.0),
+            Self::Wait(1.2),
+            Self::EnterComputer,
+        ])
+    }
+
+    /// Where the camera ends up after entering the zone, if it enters it at all.
+    pub fn camera_pose(&self) -> Option<Transform> {
+        match self {
+            Self::Move(transform) => Some(*transform),
+            Self::Sequence(actions) => actions.iter().rev().find_map(|action| action.camera_pose()),
+            _ => None,
+        }
+    }
+}
+
+/// Runs `action` as if `zone` had been clicked.
+#[derive(Debug, Clone, Event)]
+pub struct CameraZoneActionEvent {
+    pub zone : Entity,
+    pub action : CameraZoneAction,
+}
+
+/// Sent by [`CameraZoneAction::Custom`], for whatever system handles that name.
+#[derive(Debug, Clone, Event)]
+pub struct CameraZoneCustomEvent {
+    pub zone : Entity,
+    pub name : String,
 }
 
 //==============================================================================
@@ -39,17 +109,17 @@ pub enum CameraZoneAction {
 
 #[derive(Component, Reflect)]
 pub struct CameraZone {
-    pub bounds : Cuboid,
+    pub shape : CameraZoneShape,
     pub event : CameraZoneAction,
 }
 
 impl CameraZone {
-    pub fn new(bounds: Cuboid, event: CameraZoneAction) -> Self {
-        Self { bounds, event }
+    pub fn new(shape : impl Into<CameraZoneShape>, event : CameraZoneAction) -> Self {
+        Self { shape : shape.into(), event }
     }
 
-    pub fn new_movement_zone(bounds : Cuboid, target_transform : Transform) -> Self {
-        Self { bounds, event : CameraZoneAction::Move(target_transform) }
+    pub fn new_movement_zone(shape : impl Into<CameraZoneShape>, target_transform : Transform) -> Self {
+        Self { shape : shape.into(), event : CameraZoneAction::Move(target_transform) }
     }
 }
 
@@ -69,6 +139,23 @@ impl CameraZoneBundle {
     }
 }
 
+//==============================================================================
+//         Zone Source
+//==============================================================================
+
+/// Where the camera zones of the room come from. Insert it before the
+/// plugins are added to pick something other than the room model, or run
+/// the game with `--zones`.
+#[derive(Resource, Debug, Clone, Default, PartialEq, Eq)]
+pub enum CameraZoneSource {
+    /// `Zone` nodes in the room model, see [`super::gltf_zone`].
+    #[default]
+    Gltf,
+    /// A `.zones.ron` file, see [`super::zone_graph`]. Reloads while the game
+    /// runs when built with the `dev` feature.
+    Graph(String),
+}
+
 //==============================================================================
 //         Available Zones
 //==============================================================================
@@ -76,55 +163,99 @@ impl CameraZoneBundle {
 #[derive(Debug, Component)]
 pub struct CurrentZone(pub Entity);
 
+/// On the camera while it is looking closer at something, see [`CameraZoneAction::Inspect`].
+#[derive(Debug, Component)]
+pub struct Inspecting;
+
 //==============================================================================
 //         CameraZone Systems
 //==============================================================================
 
 fn detect_camera_zone_click(
-    mut commands : Commands,
-    mut camera : Query<(Entity, &Camera, &GlobalTransform, &mut CurrentZone), With<ActiveCamera>>,
-    mut fade_event : EventWriter<BlackoutTransition>,
-    mut ambient_event : EventWriter<AmbientAudioEvent>,
-    mut delayed_world_event : EventWriter<DelayedEvent<ToggleGameWorldEvent>>,
+    mut action_events : EventWriter<CameraZoneActionEvent>,
+    camera : Query<(&Camera, &GlobalTransform, &CurrentZone), With<ActiveCamera>>,
     window : Query<&Window, With<PrimaryWindow>>,
-    camera_zones : Query<(Entity, &CameraZone, &Transform, Option<&Children>)>,
+    zone_picker : ZonePicker,
     mouse : Res<ButtonInput<MouseButton>>,
     
 ) {
     if mouse.just_pressed(MouseButton::Left) {
         let Ok(window) = window.get_single() else { return };
-        let Ok((cam_entity, camera, cam_transform, mut current_zone)) = camera.get_single_mut() else { return };
-        if let Some(pos) = window.cursor_position() {
-            if let Some(ray) = camera.viewport_to_world(cam_transform, pos) {
-                let Ok((_, _, _, children)) = camera_zones.get(current_zone.0) else { return };
-                let Some(children) = children else { return };
-                
-                for child in children {
-                    let Ok((zone_entity, camera_zone, transform, _)) = camera_zones.get(*child) else { return };
-                    
-                    // let (_, rotation, translation) = transform.to_scale_rotation_translation();
-                    let ray_cast = RayCast3d::from_ray(ray, 10.0);
-                    let aabb = camera_zone.bounds.aabb_3d(transform.translation, transform.rotation);
-                    
-                    if let Some(_) = ray_cast.aabb_intersection_at(&aabb) {       
-                       match camera_zone.event {
-                           CameraZoneAction::Move(target_transform) => {
-                               commands.entity(cam_entity).insert(CameraPathFollower::to_transform(target_transform, 1.0));
-                               *current_zone = CurrentZone(zone_entity);
-                           },
-                           CameraZoneAction::Computer => {
-                               commands.entity(cam_entity).insert(CameraPathFollower::to_transform(
-                                   Transform::from_xyz(0.191, 1.078, 1.587)
-                                       .with_rotation(Quat::from_euler(EulerRot::XYZ, 3.127, -0.021, 3.141)), 
-                                   1.0
-                               ));
-                               fade_event.send(BlackoutTransition::fade_out(1.0));
-                               ambient_event.send(AmbientAudioEvent::fade_out(AmbientAudioType::Storm, 1.0));
-                               delayed_world_event.send(DelayedEvent::new(ToggleGameWorldEvent, 1.2));
-                           }
-                       }
+        let Ok((camera, cam_transform, current_zone)) = camera.get_single() else { return };
+        // Only the nearest zone under the cursor, when several overlap.
+        let Some(zone_entity) = zone_picker.pick_at_cursor(current_zone.0, window, camera, cam_transform) else { return };
+        let Some(camera_zone) = zone_picker.zone(zone_entity) else { return };
+        
+        action_events.send(CameraZoneActionEvent { zone : zone_entity, action : camera_zone.event.clone() });
+    }
+}
+
+fn run_camera_zone_actions(
+    mut commands : Commands,
+    mut action_events : EventReader<CameraZoneActionEvent>,
+    mut delayed_action_events : EventWriter<DelayedEvent<CameraZoneActionEvent>>,
+    mut custom_events : EventWriter<CameraZoneCustomEvent>,
+    mut sound_events : EventWriter<PlaySoundEvent>,
+    mut fade_event : EventWriter<BlackoutTransition>,
+    mut ambient_event : EventWriter<AmbientAudioEvent>,
+    mut world_event : EventWriter<ToggleGameWorldEvent>,
+    mut active_computer : ResMut<ActiveComputer>,
+    mut flags : ResMut<GameFlags>,
+    mut camera : Query<(Entity, &mut CurrentZone), With<ActiveCamera>>,
+    computers : Query<(Entity, &Computer)>,
+    asset_server : Res<AssetServer>,
+) {
+    for CameraZoneActionEvent { zone, action } in action_events.read() {
+        let mut queue = VecDeque::from([action.clone()]);
+        
+        while let Some(action) = queue.pop_front() {
+            match action {
+                CameraZoneAction::Move(target_transform) => {
+                    let Ok((cam_entity, mut current_zone)) = camera.get_single_mut() else { continue };
+                    commands.entity(cam_entity)
+                        .insert(CameraPathFollower::to_transform(target_transform, 1.0))
+                        .remove::<Inspecting>();
+                    *current_zone = CurrentZone(*zone);
+                },
+                CameraZoneAction::Computer(screen) => {
+                    let Some((computer, _)) = computers.iter().find(|(_, computer)| computer.name == screen) else {
+                        println!("Camera zone wants a computer on {}, but there isn't one", screen);
+                        continue;
+                    };
+                    active_computer.0 = Some(computer);
+                },
+                CameraZoneAction::EnterComputer => {
+                    world_event.send(ToggleGameWorldEvent);
+                },
+                CameraZoneAction::FadeOut(seconds) => {
+                    fade_event.send(BlackoutTransition::fade_out(seconds));
+                },
+                CameraZoneAction::FadeOutAmbient(ambient, seconds) => {
+                    ambient_event.send(AmbientAudioEvent::fade_out(ambient, seconds));
+                },
+                CameraZoneAction::Inspect(target_transform) => {
+                    let Ok((cam_entity, _)) = camera.get_single() else { continue };
+                    commands.entity(cam_entity).insert((CameraPathFollower::to_transform(target_transform, 1.0), Inspecting));
+                },
+                CameraZoneAction::PlaySound(path) => {
+                    sound_events.send(PlaySoundEvent::new(asset_server.load(path), 1.0, None));
+                },
+                CameraZoneAction::SetFlag(flag, value) => flags.set(flag, value),
+                CameraZoneAction::Custom(name) => {
+                    custom_events.send(CameraZoneCustomEvent { zone : *zone, name });
+                },
+                CameraZoneAction::Wait(seconds) => {
+                    // The rest of the sequence comes back as a new event once the wait is over.
+                    if !queue.is_empty() {
+                        let rest = CameraZoneAction::Sequence(queue.drain(..).collect());
+                        delayed_action_events.send(DelayedEvent::new(CameraZoneActionEvent { zone : *zone, action : rest }, seconds));
+                    }
+                },
+                CameraZoneAction::Sequence(actions) => {
+                    for action in actions.into_iter().rev() {
+                        queue.push_front(action);
                     }
-                }
+                },
             }
         }
     }
@@ -132,23 +263,29 @@ fn detect_camera_zone_click(
 
 fn detect_back_up(
     mut commands : Commands,
-    mut camera : Query<(Entity, &mut CurrentZone), With<ActiveCamera>>,
+    mut camera : Query<(Entity, &mut CurrentZone, Has<Inspecting>), With<ActiveCamera>>,
     camera_zones : Query<(Entity, &CameraZone, Option<&Parent>)>,
     key_input : Res<ButtonInput<KeyCode>>,
 ) {
     if key_input.just_pressed(KeyCode::Escape) || key_input.just_pressed(KeyCode::Backspace){
-        let Ok((cam_entity, mut current_zone)) = camera.get_single_mut() else { return };
-        let Ok((_, _, parent)) = camera_zones.get(current_zone.0) else { return };
+        let Ok((cam_entity, mut current_zone, inspecting)) = camera.get_single_mut() else { return };
+        let Ok((_, camera_zone, parent)) = camera_zones.get(current_zone.0) else { return };
+        
+        // A closer look backs up to the zone the camera is still in.
+        if inspecting {
+            commands.entity(cam_entity).remove::<Inspecting>();
+            if let Some(target_transform) = camera_zone.event.camera_pose() {
+                commands.entity(cam_entity).insert(CameraPathFollower::to_transform(target_transform, 1.0));
+            }
+            return;
+        }
         
         if let Some(parent) = parent {
             let Ok((parent_entity, camera_zone, _)) = camera_zones.get(**parent) else { return };
-            match camera_zone.event {
-                CameraZoneAction::Move(target_transform) => {
-                    commands.entity(cam_entity).insert(CameraPathFollower::to_transform(target_transform, 1.0));
-                    *current_zone = CurrentZone(parent_entity);
-                },
-                _ => {}
+            if let Some(target_transform) = camera_zone.event.camera_pose() {
+                commands.entity(cam_entity).insert(CameraPathFollower::to_transform(target_transform, 1.0));
+                *current_zone = CurrentZone(parent_entity);
             }
         }
     }
-}
\ No newline at end of file
+}
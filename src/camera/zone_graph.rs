@@ -0,0 +1,385 @@
+use bevy::{asset::{io::Reader, ron, AssetLoader, AsyncReadExt, LoadContext}, prelude::*, utils::{BoxedFuture, HashMap}};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{audio::ambient::AmbientAudioType, scene::RoomCamera};
+
+use super::{zone::{CameraZone, CameraZoneAction, CameraZoneBundle, CameraZoneSource, CurrentZone}, zone_hover::CameraZoneHighlight, zone_shape::CameraZoneShape};
+
+/// Where a graph is saved when the zones didn't come from one.
+pub const DEFAULT_ZONE_GRAPH : &str = "zones/room.zones.ron";
+
+//==============================================================================
+//         Zone Graph Plugin
+//==============================================================================
+
+/// Spawns the camera zones from a `.zones.ron` file when
+/// [`CameraZoneSource::Graph`] is selected, and spawns them again whenever
+/// the file changes on disk (with the `dev` feature). Send [`SaveCameraZoneGraphEvent`] to write the
+/// zones as they are now, inspector edits included, back to the file.
+pub struct CameraZoneGraphPlugin;
+
+impl Plugin for CameraZoneGraphPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_asset::<CameraZoneGraph>()
+            .init_asset_loader::<CameraZoneGraphLoader>()
+
+            .add_systems(Startup, load_zone_graph)
+            .add_systems(Update, (spawn_zone_graph, save_zone_graph))
+
+            .add_event::<SaveCameraZoneGraphEvent>()
+        ;
+    }
+}
+
+//==============================================================================
+//         Zone Graph Asset
+//==============================================================================
+
+/// A position and Euler angles (XYZ, in radians).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct ZonePose {
+    #[serde(default)]
+    pub translation : [f32; 3],
+    #[serde(default)]
+    pub rotation : [f32; 3],
+}
+
+impl ZonePose {
+    pub fn to_transform(&self) -> Transform {
+        let [x, y, z] = self.rotation;
+        Transform::from_translation(Vec3::from(self.translation))
+            .with_rotation(Quat::from_euler(EulerRot::XYZ, x, y, z))
+    }
+
+    pub fn from_transform(transform : &Transform) -> Self {
+        let (x, y, z) = transform.rotation.to_euler(EulerRot::XYZ);
+        Self { translation : transform.translation.to_array(), rotation : [x, y, z] }
+    }
+}
+
+/// What clicking a zone does, as written in the file.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ZoneGraphAction {
+    /// Moves the camera to this pose, in world space.
+    Move(ZonePose),
+    /// Makes the computer on the screen node with this name the active one.
+    Computer(String),
+    /// Switches over to the active computer's screen.
+    EnterComputer,
+    /// Fades the room to black over this many seconds.
+    FadeOut(f32),
+    /// Fades out a kind of ambient sound over this many seconds.
+    FadeOutAmbient(AmbientAudioType, f32),
+    /// Looks closer from this pose, in world space, without entering the zone.
+    Inspect(ZonePose),
+    /// Plays the sound file at this asset path.
+    PlaySound(String),
+    /// Sets or clears a game flag.
+    SetFlag(String, bool),
+    /// Sends a custom event with this name.
+    Custom(String),
+    /// Waits this many seconds before the rest of a sequence.
+    Wait(f32),
+    /// Runs each action in turn.
+    Sequence(Vec<ZoneGraphAction>),
+}
+
+impl ZoneGraphAction {
+    fn to_action(&self) -> CameraZoneAction {
+        match self {
+            Self::Move(pose) => CameraZoneAction::Move(pose.to_transform()),
+            Self::Computer(screen) => CameraZoneAction::Computer(screen.clone()),
+            Self::EnterComputer => CameraZoneAction::EnterComputer,
+            Self::FadeOut(seconds) => CameraZoneAction::FadeOut(*seconds),
+            Self::FadeOutAmbient(ambient, seconds) => CameraZoneAction::FadeOutAmbient(*ambient, *seconds),
+            Self::Inspect(pose) => CameraZoneAction::Inspect(pose.to_transform()),
+            Self::PlaySound(path) => CameraZoneAction::PlaySound(path.clone()),
+            Self::SetFlag(flag, value) => CameraZoneAction::SetFlag(flag.clone(), *value),
+            Self::Custom(name) => CameraZoneAction::Custom(name.clone()),
+            Self::Wait(seconds) => CameraZoneAction::Wait(*seconds),
+            Self::Sequence(actions) => CameraZoneAction::Sequence(actions.iter().map(Self::to_action).collect()),
+        }
+    }
+
+    fn from_action(action : &CameraZoneAction) -> Self {
+        match action {
+            CameraZoneAction::Move(target) => Self::Move(ZonePose::from_transform(target)),
+            CameraZoneAction::Computer(screen) => Self::Computer(screen.clone()),
+            CameraZoneAction::EnterComputer => Self::EnterComputer,
+            CameraZoneAction::FadeOut(seconds) => Self::FadeOut(*seconds),
+            CameraZoneAction::FadeOutAmbient(ambient, seconds) => Self::FadeOutAmbient(*ambient, *seconds),
+            CameraZoneAction::Inspect(target) => Self::Inspect(ZonePose::from_transform(target)),
+            CameraZoneAction::PlaySound(path) => Self::PlaySound(path.clone()),
+            CameraZoneAction::SetFlag(flag, value) => Self::SetFlag(flag.clone(), *value),
+            CameraZoneAction::Custom(name) => Self::Custom(name.clone()),
+            CameraZoneAction::Wait(seconds) => Self::Wait(*seconds),
+            CameraZoneAction::Sequence(actions) => Self::Sequence(actions.iter().map(Self::from_action).collect()),
+        }
+    }
+}
+
+/// The volume that has to be clicked, as written in the file.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ZoneGraphShape {
+    /// A box of this size.
+    Box([f32; 3]),
+    /// A sphere with this radius.
+    Sphere(f32),
+    /// An upright capsule with this radius, and this length between the centers of its ends.
+    Capsule(f32, f32),
+    /// The mesh at this asset path, e.g. `models/ghoul_room.glb#Mesh3/Primitive0`.
+    Mesh(String),
+}
+
+impl Default for ZoneGraphShape {
+    fn default() -> Self {
+        Self::Box([1.0, 1.0, 1.0])
+    }
+}
+
+impl ZoneGraphShape {
+    fn to_shape(&self, asset_server : &AssetServer) -> CameraZoneShape {
+        match self {
+            Self::Box([x, y, z]) => CameraZoneShape::Box(Cuboid::new(*x, *y, *z)),
+            Self::Sphere(radius) => CameraZoneShape::Sphere(Sphere::new(*radius)),
+            Self::Capsule(radius, length) => CameraZoneShape::Capsule(Capsule::new(*radius, *length)),
+            Self::Mesh(path) => CameraZoneShape::Mesh(asset_server.load(path.clone())),
+        }
+    }
+
+    /// `None` for a mesh that wasn't loaded from a file, which can't be written down.
+    fn from_shape(shape : &CameraZoneShape) -> Option<Self> {
+        Some(match shape {
+            CameraZoneShape::Box(cuboid) => Self::Box((cuboid.half_size * 2.0).to_array()),
+            CameraZoneShape::Sphere(sphere) => Self::Sphere(sphere.radius),
+            CameraZoneShape::Capsule(capsule) => Self::Capsule(capsule.radius, capsule.half_length * 2.0),
+            CameraZoneShape::Mesh(handle) => Self::Mesh(handle.path()?.to_string()),
+        })
+    }
+}
+
+/// One zone and the zones that can be clicked from it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ZoneGraphNode {
+    pub name : String,
+    /// Where the zone is, relative to its parent.
+    #[serde(default)]
+    pub pose : ZonePose,
+    /// The clickable volume, a unit box if left out.
+    #[serde(default)]
+    pub shape : ZoneGraphShape,
+    pub action : ZoneGraphAction,
+    /// The node in the room that lights up when the zone is hovered.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub highlight : Option<String>,
+    #[serde(default)]
+    pub children : Vec<ZoneGraphNode>,
+}
+
+impl ZoneGraphNode {
+    fn to_zone(&self, asset_server : &AssetServer) -> CameraZone {
+        CameraZone::new(self.shape.to_shape(asset_server), self.action.to_action())
+    }
+
+    fn from_zone(name : &str, zone : &CameraZone, highlight : Option<&CameraZoneHighlight>, transform : &Transform) -> Self {
+        Self {
+            name : name.to_string(),
+            pose : ZonePose::from_transform(transform),
+            shape : ZoneGraphShape::from_shape(&zone.shape).unwrap_or_else(|| {
+                println!("Camera zone {} has a mesh with no file, saving it as a box", name);
+                ZoneGraphShape::default()
+            }),
+            action : ZoneGraphAction::from_action(&zone.event),
+            highlight : highlight.map(|highlight| highlight.0.clone()),
+            children : Vec::new(),
+        }
+    }
+}
+
+/// A tree of camera zones, loaded from a `.zones.ron` file. The camera starts in `root`.
+#[derive(Asset, TypePath, Debug, Clone, Serialize, Deserialize)]
+pub struct CameraZoneGraph {
+    pub root : ZoneGraphNode,
+}
+
+#[derive(Debug, Error)]
+pub enum CameraZoneGraphError {
+    #[error("Could not read camera zone graph: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Could not parse camera zone graph: {0}")]
+    Ron(#[from] ron::error::SpannedError),
+}
+
+#[derive(Default)]
+pub struct CameraZoneGraphLoader;
+
+impl AssetLoader for CameraZoneGraphLoader {
+    type Asset = CameraZoneGraph;
+    type Settings = ();
+    type Error = CameraZoneGraphError;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _settings: &'a Self::Settings,
+        _load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<Self::Asset, Self::Error>> {
+        Box::pin(async move {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).await?;
+            Ok(ron::de::from_bytes::<CameraZoneGraph>(&bytes)?)
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["zones.ron"]
+    }
+}
+
+//==============================================================================
+//         Zone Graph Instance
+//==============================================================================
+
+/// The graph being used and the zones last spawned from it.
+#[derive(Resource, Debug)]
+pub struct ZoneGraphInstance {
+    pub handle : Handle<CameraZoneGraph>,
+    pub root : Option<Entity>,
+}
+
+/// Asks for the zones the camera is in to be written to a `.zones.ron` file,
+/// the selected graph or [`DEFAULT_ZONE_GRAPH`].
+#[derive(Event, Debug, Clone, Copy, Default)]
+pub struct SaveCameraZoneGraphEvent;
+
+//==============================================================================
+//         Zone Graph Systems
+//==============================================================================
+
+fn load_zone_graph(
+    mut commands : Commands,
+    source : Res<CameraZoneSource>,
+    asset_server : Res<AssetServer>,
+) {
+    let CameraZoneSource::Graph(path) = &*source else { return };
+    commands.insert_resource(ZoneGraphInstance { handle : asset_server.load(path.clone()), root : None });
+}
+
+/// Spawns the zones once the graph loads, and replaces them whenever it is
+/// reloaded. The room camera stays in the zone with the same name, if there
+/// still is one, even while the player is sitting at a computer.
+fn spawn_zone_graph(
+    mut commands : Commands,
+    mut graph_events : EventReader<AssetEvent<CameraZoneGraph>>,
+    mut cameras : Query<(Entity, Option<&CurrentZone>, &mut Transform), With<RoomCamera>>,
+    instance : Option<ResMut<ZoneGraphInstance>>,
+    graphs : Res<Assets<CameraZoneGraph>>,
+    names : Query<&Name>,
+    asset_server : Res<AssetServer>,
+) {
+    let Some(mut instance) = instance else { return };
+    let changed = graph_events.read().filter(|event| match event {
+        AssetEvent::LoadedWithDependencies { id } | AssetEvent::Modified { id } => *id == instance.handle.id(),
+        _ => false,
+    }).count();
+    if changed == 0 { return }
+    let Some(graph) = graphs.get(&instance.handle) else { return };
+
+    let current = cameras.get_single().ok()
+        .and_then(|(_, current, _)| current)
+        .and_then(|current| names.get(current.0).ok())
+        .map(|name| name.to_string());
+
+    if let Some(root) = instance.root.take() {
+        commands.entity(root).despawn_recursive();
+    }
+
+    let mut spawned = HashMap::new();
+    let root = spawn_zone_node(&mut commands, &asset_server, &graph.root, &mut spawned);
+    instance.root = Some(root);
+    println!("Spawned {} camera zones", spawned.len());
+
+    let Ok((camera, _, mut camera_transform)) = cameras.get_single_mut() else { return };
+    let zone = match current {
+        Some(name) => spawned.get(&name).copied().unwrap_or(root),
+        // The first time, the camera starts where the root puts it.
+        None => {
+            if let Some(pose) = graph.root.action.to_action().camera_pose() {
+                *camera_transform = pose;
+            }
+            root
+        },
+    };
+    commands.entity(camera).insert(CurrentZone(zone));
+}
+
+fn spawn_zone_node(commands : &mut Commands, asset_server : &AssetServer, node : &ZoneGraphNode, spawned : &mut HashMap<String, Entity>) -> Entity {
+    let children = node.children.iter()
+        .map(|child| spawn_zone_node(commands, asset_server, child, spawned))
+        .collect::<Vec<_>>();
+
+    let mut entity = commands.spawn((
+        CameraZoneBundle::new(node.to_zone(asset_server), SpatialBundle::from_transform(node.pose.to_transform())),
+        Name::new(node.name.clone()),
+    ));
+    if let Some(highlight) = &node.highlight {
+        entity.insert(CameraZoneHighlight(highlight.clone()));
+    }
+    let entity = entity.push_children(&children).id();
+
+    spawned.insert(node.name.clone(), entity);
+    entity
+}
+
+/// Writes out the tree the room camera is in, from its top zone down.
+fn save_zone_graph(
+    mut save_events : EventReader<SaveCameraZoneGraphEvent>,
+    cameras : Query<&CurrentZone, With<RoomCamera>>,
+    zones : Query<(&CameraZone, Option<&CameraZoneHighlight>, &Transform, Option<&Name>, Option<&Parent>, Option<&Children>)>,
+    source : Res<CameraZoneSource>,
+) {
+    if save_events.read().count() == 0 { return }
+    let Ok(current) = cameras.get_single() else { return };
+
+    let mut root = current.0;
+    while let Some(parent) = zones.get(root).ok().and_then(|(_, _, _, _, parent, _)| parent).filter(|parent| zones.contains(***parent)) {
+        root = **parent;
+    }
+
+    let Some(root) = graph_node(root, &zones) else { return };
+    let path = match &*source {
+        CameraZoneSource::Graph(path) => path.as_str(),
+        CameraZoneSource::Gltf => DEFAULT_ZONE_GRAPH,
+    };
+
+    let text = match ron::ser::to_string_pretty(&CameraZoneGraph { root }, ron::ser::PrettyConfig::default()) {
+        Ok(text) => text,
+        Err(error) => return println!("Could not write camera zone graph: {}", error),
+    };
+
+    // Straight to the source folder, so the change sticks and the watcher picks it up.
+    let file = std::path::Path::new("assets").join(path);
+    if let Some(folder) = file.parent() {
+        let _ = std::fs::create_dir_all(folder);
+    }
+    match std::fs::write(&file, text) {
+        Ok(_) => println!("Saved camera zones to {}", file.display()),
+        Err(error) => println!("Could not save camera zones to {}: {}", file.display(), error),
+    }
+}
+
+fn graph_node(
+    entity : Entity,
+    zones : &Query<(&CameraZone, Option<&CameraZoneHighlight>, &Transform, Option<&Name>, Option<&Parent>, Option<&Children>)>,
+) -> Option<ZoneGraphNode> {
+    let (zone, highlight, transform, name, _, children) = zones.get(entity).ok()?;
+    let name = name.map(|name| name.to_string()).unwrap_or_else(|| format!("Zone {}", entity.index()));
+
+    let mut node = ZoneGraphNode::from_zone(&name, zone, highlight, transform);
+    node.children = children.into_iter().flatten()
+        .filter_map(|child| graph_node(*child, zones))
+        .collect();
+    Some(node)
+}
@@ -0,0 +1,210 @@
+use bevy::{prelude::*, render::view::RenderLayers, window::{CursorIcon, PrimaryWindow}};
+
+use crate::game::ActiveCamera;
+
+use super::{path::CameraPathFollower, zone::{CameraZone, CurrentZone}, zone_shape::ZonePicker};
+
+/// How far the tooltip sits from the cursor, in pixels.
+const TOOLTIP_OFFSET : Vec2 = Vec2::new(16.0, 16.0);
+
+//==============================================================================
+//         Zone Hover Plugin
+//==============================================================================
+
+/// Shows which zones can be clicked: the zone under the cursor lights up the
+/// node named by its [`CameraZoneHighlight`], turns the cursor into a pointer
+/// and gets a tooltip with its name.
+pub struct CameraZoneHoverPlugin;
+
+impl Plugin for CameraZoneHoverPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_systems(Startup, init_zone_tooltip)
+            .add_systems(Update, (
+                update_hovered_zone,
+                (highlight_hovered_zone, update_zone_cursor, update_zone_tooltip),
+            ).chain())
+
+            .init_resource::<HoveredZone>()
+            .init_resource::<ZoneHoverSettings>()
+
+            .add_event::<ZoneHoverStart>()
+            .add_event::<ZoneHoverEnd>()
+
+            .register_type::<CameraZoneHighlight>()
+        ;
+    }
+}
+
+//==============================================================================
+//         Zone Hover Types
+//==============================================================================
+
+/// The zone under the cursor, if it can be clicked from where the camera is.
+#[derive(Resource, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct HoveredZone(pub Option<Entity>);
+
+#[derive(Resource, Debug, Clone)]
+pub struct ZoneHoverSettings {
+    /// Added to the emissive color of the highlighted meshes.
+    pub highlight : Color,
+    /// Whether the zone's name is shown next to the cursor.
+    pub tooltips : bool,
+}
+
+impl Default for ZoneHoverSettings {
+    fn default() -> Self {
+        Self { highlight : Color::rgb(0.15, 0.15, 0.12), tooltips : true }
+    }
+}
+
+/// The cursor moved onto this zone.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct ZoneHoverStart(pub Entity);
+
+/// The cursor moved off this zone, or it can't be clicked anymore.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct ZoneHoverEnd(pub Entity);
+
+/// The name of the node in the room that lights up while the zone is hovered,
+/// e.g. `Monitor` for the computer. Every mesh under it is highlighted.
+#[derive(Component, Debug, Clone, Reflect)]
+pub struct CameraZoneHighlight(pub String);
+
+/// A mesh lit up by a hovered zone, and the material to put back afterwards.
+#[derive(Component)]
+struct ZoneHighlighted(Handle<StandardMaterial>);
+
+#[derive(Component)]
+struct ZoneTooltip;
+
+//==============================================================================
+//         Zone Hover Systems
+//==============================================================================
+
+fn init_zone_tooltip(
+    mut commands : Commands,
+) {
+    commands.spawn((
+        TextBundle {
+            text : Text::from_section("", TextStyle { font_size : 18.0, color : Color::WHITE, ..default() }),
+            style : Style {
+                position_type : PositionType::Absolute,
+                ..default()
+            },
+            background_color : BackgroundColor(Color::rgba(0.0, 0.0, 0.0, 0.6)),
+            visibility : Visibility::Hidden,
+            ..default()
+        },
+        Name::new("Zone Tooltip"),
+        RenderLayers::layer(0),
+        ZoneTooltip,
+    ));
+}
+
+/// Casts the cursor into the room every frame, the same way a click does.
+fn update_hovered_zone(
+    mut hovered : ResMut<HoveredZone>,
+    mut start_events : EventWriter<ZoneHoverStart>,
+    mut end_events : EventWriter<ZoneHoverEnd>,
+    camera : Query<(&Camera, &GlobalTransform, &CurrentZone, Has<CameraPathFollower>), With<ActiveCamera>>,
+    window : Query<&Window, With<PrimaryWindow>>,
+    zone_picker : ZonePicker,
+) {
+    // Nothing is hovered while the camera is moving, or away from the room.
+    let zone = match (window.get_single(), camera.get_single()) {
+        (Ok(window), Ok((camera, camera_transform, current_zone, false))) => zone_picker.pick_at_cursor(current_zone.0, window, camera, camera_transform),
+        _ => None,
+    };
+    if zone == hovered.0 { return }
+
+    if let Some(previous) = hovered.0 {
+        end_events.send(ZoneHoverEnd(previous));
+    }
+    if let Some(zone) = zone {
+        start_events.send(ZoneHoverStart(zone));
+    }
+    hovered.0 = zone;
+}
+
+/// Swaps the highlighted meshes onto brighter copies of their materials, so
+/// other meshes sharing a material stay as they are.
+fn highlight_hovered_zone(
+    mut commands : Commands,
+    mut start_events : EventReader<ZoneHoverStart>,
+    mut end_events : EventReader<ZoneHoverEnd>,
+    mut materials : ResMut<Assets<StandardMaterial>>,
+    mut meshes : Query<&mut Handle<StandardMaterial>>,
+    highlighted : Query<(Entity, &ZoneHighlighted)>,
+    highlights : Query<&CameraZoneHighlight>,
+    names : Query<(Entity, &Name), Without<CameraZone>>,
+    children : Query<&Children>,
+    settings : Res<ZoneHoverSettings>,
+) {
+    if end_events.read().count() > 0 {
+        for (entity, original) in highlighted.iter() {
+            if let Ok(mut material) = meshes.get_mut(entity) {
+                *material = original.0.clone();
+            }
+            commands.entity(entity).remove::<ZoneHighlighted>();
+        }
+    }
+
+    let Some(ZoneHoverStart(zone)) = start_events.read().last() else { return };
+    let Ok(highlight) = highlights.get(*zone) else { return };
+    let Some((target, _)) = names.iter().find(|(_, name)| name.as_str() == highlight.0) else {
+        println!("Camera zone highlight {} is not in the room", highlight.0);
+        return;
+    };
+
+    for entity in std::iter::once(target).chain(children.iter_descendants(target)) {
+        let Ok(mut handle) = meshes.get_mut(entity) else { continue };
+        let Some(mut material) = materials.get(&*handle).cloned() else { continue };
+
+        material.emissive = material.emissive + settings.highlight;
+        commands.entity(entity).insert(ZoneHighlighted(handle.clone()));
+        *handle = materials.add(material);
+    }
+}
+
+fn update_zone_cursor(
+    mut window : Query<&mut Window, With<PrimaryWindow>>,
+    hovered : Res<HoveredZone>,
+) {
+    if !hovered.is_changed() { return }
+    let Ok(mut window) = window.get_single_mut() else { return };
+    window.cursor.icon = match hovered.0 {
+        Some(_) => CursorIcon::Pointer,
+        None => CursorIcon::Default,
+    };
+}
+
+/// Follows the cursor with the hovered zone's name, the last part of it for
+/// glTF names like `Zone.Desk`.
+fn update_zone_tooltip(
+    mut tooltip : Query<(&mut Text, &mut Style, &mut Visibility), With<ZoneTooltip>>,
+    window : Query<&Window, With<PrimaryWindow>>,
+    names : Query<&Name>,
+    hovered : Res<HoveredZone>,
+    settings : Res<ZoneHoverSettings>,
+) {
+    let Ok((mut text, mut style, mut visibility)) = tooltip.get_single_mut() else { return };
+
+    let label = hovered.0
+        .filter(|_| settings.tooltips)
+        .and_then(|zone| names.get(zone).ok())
+        .and_then(|name| name.as_str().rsplit('.').next());
+    let cursor = window.get_single().ok().and_then(|window| window.cursor_position());
+
+    let (Some(label), Some(cursor)) = (label, cursor) else {
+        *visibility = Visibility::Hidden;
+        return;
+    };
+
+    if text.sections[0].value != label {
+        text.sections[0].value = label.to_string();
+    }
+    style.left = Val::Px(cursor.x + TOOLTIP_OFFSET.x);
+    style.top = Val::Px(cursor.y + TOOLTIP_OFFSET.y);
+    *visibility = Visibility::Visible;
+}
@@ -0,0 +1,237 @@
+use bevy::{ecs::system::SystemParam, prelude::*, render::{mesh::{Indices, VertexAttributeValues}, primitives::Aabb}};
+
+use crate::scene::RoomGeometry;
+
+use super::zone::CameraZone;
+
+/// How far from the camera a zone can be clicked.
+pub const ZONE_PICK_DISTANCE : f32 = 10.0;
+
+/// How much closer the room has to be than a zone to hide it, so a zone
+/// wrapped tightly around its object isn't hidden by the object itself.
+const OCCLUSION_EPSILON : f32 = 0.01;
+
+//==============================================================================
+//         CameraZone Shape
+//==============================================================================
+
+/// The volume that has to be clicked to use a zone, centered on the zone
+/// and turned and scaled with it.
+#[derive(Debug, Clone, Reflect)]
+pub enum CameraZoneShape {
+    Box(Cuboid),
+    Sphere(Sphere),
+    /// Stands upright along the zone's Y axis.
+    Capsule(Capsule),
+    /// The triangles of a mesh, for objects no simple shape fits.
+    Mesh(Handle<Mesh>),
+}
+
+impl Default for CameraZoneShape {
+    fn default() -> Self {
+        Self::Box(Cuboid::default())
+    }
+}
+
+impl From<Cuboid> for CameraZoneShape {
+    fn from(cuboid : Cuboid) -> Self {
+        Self::Box(cuboid)
+    }
+}
+
+impl From<Sphere> for CameraZoneShape {
+    fn from(sphere : Sphere) -> Self {
+        Self::Sphere(sphere)
+    }
+}
+
+impl From<Capsule> for CameraZoneShape {
+    fn from(capsule : Capsule) -> Self {
+        Self::Capsule(capsule)
+    }
+}
+
+impl From<Handle<Mesh>> for CameraZoneShape {
+    fn from(mesh : Handle<Mesh>) -> Self {
+        Self::Mesh(mesh)
+    }
+}
+
+impl CameraZoneShape {
+    /// How far along `ray` it first enters the shape, placed at `transform`.
+    pub fn ray_distance(&self, ray : Ray3d, transform : &GlobalTransform, meshes : &Assets<Mesh>) -> Option<f32> {
+        // Working in the shape's own space takes care of rotation and scale. The
+        // direction isn't normalized there, so distances still come out in world units.
+        let inverse = transform.affine().inverse();
+        let origin = inverse.transform_point3(ray.origin);
+        let direction = inverse.transform_vector3(*ray.direction);
+
+        let distance = match self {
+            Self::Box(cuboid) => ray_box(origin, direction, -cuboid.half_size, cuboid.half_size),
+            Self::Sphere(sphere) => ray_sphere(origin, direction, Vec3::ZERO, sphere.radius),
+            Self::Capsule(capsule) => ray_capsule(origin, direction, capsule.radius, capsule.half_length),
+            Self::Mesh(handle) => meshes.get(handle).and_then(|mesh| ray_mesh(origin, direction, mesh)),
+        };
+        distance.filter(|distance| *distance <= ZONE_PICK_DISTANCE)
+    }
+}
+
+//==============================================================================
+//         Zone Picker
+//==============================================================================
+
+/// Finds the zone under the cursor, for clicking and hovering alike.
+#[derive(SystemParam)]
+pub struct ZonePicker<'w, 's> {
+    zones : Query<'w, 's, (&'static CameraZone, &'static GlobalTransform, Option<&'static Children>)>,
+    geometry : Query<'w, 's, (&'static Handle<Mesh>, &'static GlobalTransform, Option<&'static Aabb>), With<RoomGeometry>>,
+    meshes : Res<'w, Assets<Mesh>>,
+}
+
+impl<'w, 's> ZonePicker<'w, 's> {
+    pub fn zone(&self, entity : Entity) -> Option<&CameraZone> {
+        self.zones.get(entity).ok().map(|(zone, _, _)| zone)
+    }
+
+    /// The nearest zone under `ray` that can be reached from `current`, unless
+    /// something in the room is in front of it.
+    pub fn pick(&self, current : Entity, ray : Ray3d) -> Option<Entity> {
+        let Ok((_, _, Some(children))) = self.zones.get(current) else { return None };
+
+        let (entity, distance) = children.iter()
+            .filter_map(|child| {
+                let (zone, transform, _) = self.zones.get(*child).ok()?;
+                zone.shape.ray_distance(ray, transform, &self.meshes).map(|distance| (*child, distance))
+            })
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))?;
+
+        match self.room_distance(ray, distance) {
+            Some(blocked) if blocked + OCCLUSION_EPSILON < distance => None,
+            _ => Some(entity),
+        }
+    }
+
+    /// [`Self::pick`] along the ray under the window's cursor.
+    pub fn pick_at_cursor(&self, current : Entity, window : &Window, camera : &Camera, camera_transform : &GlobalTransform) -> Option<Entity> {
+        let ray = camera.viewport_to_world(camera_transform, window.cursor_position()?)?;
+        self.pick(current, ray)
+    }
+
+    /// How far along `ray` it hits the room, looking no further than `limit`.
+    fn room_distance(&self, ray : Ray3d, limit : f32) -> Option<f32> {
+        self.geometry.iter()
+            .filter_map(|(handle, transform, aabb)| {
+                let inverse = transform.affine().inverse();
+                let origin = inverse.transform_point3(ray.origin);
+                let direction = inverse.transform_vector3(*ray.direction);
+
+                // Most of the room is nowhere near the ray, so check the bounds first.
+                if let Some(aabb) = aabb {
+                    let center = Vec3::from(aabb.center);
+                    let half_extents = Vec3::from(aabb.half_extents);
+                    if ray_box(origin, direction, center - half_extents, center + half_extents).map_or(true, |distance| distance > limit) {
+                        return None;
+                    }
+                }
+
+                ray_mesh(origin, direction, self.meshes.get(handle)?)
+            })
+            .min_by(|a, b| a.total_cmp(b))
+    }
+}
+
+//==============================================================================
+//         Ray Tests
+//==============================================================================
+
+// Each returns how far along the ray it first hits, counting in lengths of
+// `direction`, or `None` when it misses or the shape is behind the ray. A ray
+// starting inside a shape hits it straight away.
+
+fn ray_box(origin : Vec3, direction : Vec3, min : Vec3, max : Vec3) -> Option<f32> {
+    let inverse = direction.recip();
+    let near = (min - origin) * inverse;
+    let far = (max - origin) * inverse;
+
+    let enter = near.min(far).max_element();
+    let exit = near.max(far).min_element();
+    (enter <= exit && exit >= 0.0).then(|| enter.max(0.0))
+}
+
+fn ray_sphere(origin : Vec3, direction : Vec3, center : Vec3, radius : f32) -> Option<f32> {
+    let offset = origin - center;
+    let a = direction.length_squared();
+    let b = 2.0 * offset.dot(direction);
+    let c = offset.length_squared() - radius * radius;
+    nearest_root(a, b, c)
+}
+
+fn ray_capsule(origin : Vec3, direction : Vec3, radius : f32, half_length : f32) -> Option<f32> {
+    // The side of the cylinder, only counting hits between the two caps.
+    let flat_origin = Vec2::new(origin.x, origin.z);
+    let flat_direction = Vec2::new(direction.x, direction.z);
+    let side = nearest_root(
+        flat_direction.length_squared(),
+        2.0 * flat_origin.dot(flat_direction),
+        flat_origin.length_squared() - radius * radius,
+    ).filter(|distance| (origin.y + direction.y * distance).abs() <= half_length);
+
+    [
+        side,
+        ray_sphere(origin, direction, Vec3::Y * half_length, radius),
+        ray_sphere(origin, direction, Vec3::NEG_Y * half_length, radius),
+    ].into_iter().flatten().min_by(|a, b| a.total_cmp(b))
+}
+
+fn ray_mesh(origin : Vec3, direction : Vec3, mesh : &Mesh) -> Option<f32> {
+    let Some(VertexAttributeValues::Float32x3(positions)) = mesh.attribute(Mesh::ATTRIBUTE_POSITION) else { return None };
+    let vertex = |index : usize| positions.get(index).copied().map(Vec3::from);
+
+    let indices = match mesh.indices() {
+        Some(Indices::U16(indices)) => indices.iter().map(|index| *index as usize).collect(),
+        Some(Indices::U32(indices)) => indices.iter().map(|index| *index as usize).collect(),
+        None => (0..positions.len()).collect::<Vec<_>>(),
+    };
+
+    indices.chunks_exact(3)
+        .filter_map(|triangle| ray_triangle(origin, direction, [vertex(triangle[0])?, vertex(triangle[1])?, vertex(triangle[2])?]))
+        .min_by(|a, b| a.total_cmp(b))
+}
+
+/// Möller–Trumbore. Both sides of the triangle count.
+fn ray_triangle(origin : Vec3, direction : Vec3, [a, b, c] : [Vec3; 3]) -> Option<f32> {
+    let edge_ab = b - a;
+    let edge_ac = c - a;
+    let p = direction.cross(edge_ac);
+    let determinant = edge_ab.dot(p);
+    if determinant.abs() < f32::EPSILON { return None }
+
+    let inverse = 1.0 / determinant;
+    let offset = origin - a;
+    let u = offset.dot(p) * inverse;
+    if !(0.0..=1.0).contains(&u) { return None }
+
+    let q = offset.cross(edge_ab);
+    let v = direction.dot(q) * inverse;
+    if v < 0.0 || u + v > 1.0 { return None }
+
+    let distance = edge_ac.dot(q) * inverse;
+    (distance >= 0.0).then_some(distance)
+}
+
+/// The smallest root of `a·t² + b·t + c` that isn't behind the ray, or 0 when the ray starts inside.
+fn nearest_root(a : f32, b : f32, c : f32) -> Option<f32> {
+    if a.abs() < f32::EPSILON { return None }
+
+    let discriminant = b * b - 4.0 * a * c;
+    if discriminant < 0.0 { return None }
+
+    let root = discriminant.sqrt();
+    let near = (-b - root) / (2.0 * a);
+    let far = (-b + root) / (2.0 * a);
+    match (near >= 0.0, far >= 0.0) {
+        (true, _) => Some(near),
+        (false, true) => Some(0.0),
+        _ => None,
+    }
+}
@@ -2,7 +2,7 @@ use bevy::{diagnostic::{DiagnosticsStore, FrameTimeDiagnosticsPlugin}, prelude::
 use bevy_debug_text_overlay::{screen_print, OverlayPlugin};
 use bevy_inspector_egui::quick::WorldInspectorPlugin;
 
-use crate::{camera::{fly::ToggleFlyCam, path::CameraPathFollower, zone::{CameraZone, CurrentZone}}, game::{ActiveCamera, GameState}};
+use crate::{audio::{sound::PlaySoundEvent, AudioAssets}, scene::computer_world::{ActiveComputer, Computer, ComputerNode, ComputerPowerEvent}, camera::{fly::ToggleFlyCam, path::CameraPathFollower, zone::{CameraZone, CurrentZone}, zone_graph::SaveCameraZoneGraphEvent, zone_shape::CameraZoneShape}, game::{ActiveCamera, GameState}, os::bbs::server::BbsServerPlugin, ui::{director::{CommandOverride, TerminalDirectorEvent}, terminal::FocusedTerminal}, util::DelayedEvent};
 
 //==============================================================================
 //         Debug Plugin
@@ -17,9 +17,10 @@ impl Plugin for DebugPlugin {
                 WorldInspectorPlugin::default(),
                 OverlayPlugin { font_size: 18.0, ..default() },
                 FrameTimeDiagnosticsPlugin,
+                BbsServerPlugin,
             ))
             
-            .add_systems(Update, (close_on_esc, display_debug_info, toggle_debug_mode, debug_camera_paths, debug_camera_zones))
+            .add_systems(Update, (close_on_esc, display_debug_info, toggle_debug_mode, debug_camera_paths, debug_camera_zones, debug_lightning_strike, debug_ghost, debug_save_camera_zones))
         
             .init_resource::<DebugMode>()
         ;
@@ -83,6 +84,53 @@ fn toggle_debug_mode(
     }
 }
 
+/// F5 knocks the power out in the whole house, the same way the storm will.
+fn debug_lightning_strike(
+    mut power_events : EventWriter<ComputerPowerEvent>,
+    mut sound_effect_events : EventWriter<PlaySoundEvent>,
+    computers : Query<Entity, With<Computer>>,
+    audio_assets : Res<AudioAssets>,
+    input : Res<ButtonInput<KeyCode>>,
+) {
+    if input.just_pressed(KeyCode::F5) {
+        sound_effect_events.send(PlaySoundEvent::new(audio_assets.storm_thunder.clone(), 0.3, None));
+        for computer in computers.iter() {
+            power_events.send(ComputerPowerEvent::power_loss(computer));
+        }
+    }
+}
+
+/// F6 lets the ghost have a go at the terminal in front of the player.
+fn debug_ghost(
+    mut director_events : EventWriter<TerminalDirectorEvent>,
+    mut delayed_director_events : EventWriter<DelayedEvent<TerminalDirectorEvent>>,
+    terminals : Query<(Entity, &ComputerNode), With<FocusedTerminal>>,
+    active_computer : Res<ActiveComputer>,
+    input : Res<ButtonInput<KeyCode>>,
+) {
+    if !input.just_pressed(KeyCode::F6) { return }
+    let Some((terminal, _)) = terminals.iter().find(|(_, node)| active_computer.is(node.0)) else { return };
+
+    director_events.send_batch([
+        TerminalDirectorEvent::lock(terminal),
+        TerminalDirectorEvent::erase(terminal),
+        TerminalDirectorEvent::run(terminal, "whoami"),
+        TerminalDirectorEvent::override_command(terminal, CommandOverride::refuse("help", "help: nobody is coming").times(1)),
+    ]);
+    delayed_director_events.send(DelayedEvent::new(TerminalDirectorEvent::print(terminal, "[c=red]I can see you.[/c]"), 3.0));
+    delayed_director_events.send(DelayedEvent::new(TerminalDirectorEvent::unlock(terminal), 4.0));
+}
+
+/// F7 saves the camera zones, with whatever was changed in the inspector, to a `.zones.ron` file.
+fn debug_save_camera_zones(
+    mut save_events : EventWriter<SaveCameraZoneGraphEvent>,
+    input : Res<ButtonInput<KeyCode>>,
+) {
+    if input.just_pressed(KeyCode::F7) {
+        save_events.send(SaveCameraZoneGraphEvent);
+    }
+}
+
 fn debug_camera_paths(
     mut gizmos : Gizmos,
     cameras : Query<(&Transform, &CameraPathFollower)>,
@@ -97,7 +145,7 @@ fn debug_camera_paths(
 fn debug_camera_zones (
     mut gizmos: Gizmos,
     cameras : Query<&CurrentZone, With<ActiveCamera>>,
-    camera_zones: Query<(&Transform, &CameraZone, Option<&Children>)>,
+    camera_zones: Query<(&GlobalTransform, &CameraZone, Option<&Children>)>,
 ) {
     for current_zone in cameras.iter() {
         let Ok((_, _, children)) = camera_zones.get(current_zone.0) else { continue };
@@ -105,13 +153,18 @@ fn debug_camera_zones (
         if let Some(children) = children {
             for child in children.iter() {
                 let Ok((transform, zone, _)) = camera_zones.get(*child) else { continue };
-                // let (_, rotation, translation) = transform.to_scale_rotation_translation();
-                gizmos.primitive_3d(zone.bounds, transform.translation, transform.rotation, Color::WHITE);
+                let (_, rotation, translation) = transform.to_scale_rotation_translation();
+                match &zone.shape {
+                    CameraZoneShape::Box(cuboid) => gizmos.primitive_3d(*cuboid, translation, rotation, Color::WHITE),
+                    CameraZoneShape::Sphere(sphere) => { gizmos.primitive_3d(*sphere, translation, rotation, Color::WHITE); },
+                    CameraZoneShape::Capsule(capsule) => { gizmos.primitive_3d(*capsule, translation, rotation, Color::WHITE); },
+                    CameraZoneShape::Mesh(_) => {},
+                }
             }
         }
     }
     // for (transform, zone) in camera_zones.iter() {
     //     let (_, rotation, translation) = transform.to_scale_rotation_translation();
-    //     gizmos.primitive_3d(zone.bounds, translation, rotation, Color::WHITE);
+    //     gizmos.primitive_3d(zone.shape, translation, rotation, Color::WHITE);
     // }
 }
\ No newline at end of file
@@ -1,8 +1,8 @@
-use std::default;
+use std::{collections::HashSet, default};
 
 use bevy::{prelude::*, render::camera::RenderTarget, window::WindowRef};
 
-use crate::{audio::{sound::PlaySoundEvent, AudioAssets}, scene::{computer_world::{ComputerCamera, ComputerWorldAssets}, RoomCamera}, util::DelayedEventPlugin};
+use crate::{audio::{sound::PlaySoundEvent, AudioAssets}, scene::{computer_world::{ActiveComputer, Computer}, RoomCamera}, util::DelayedEventPlugin};
 
 //==============================================================================
 //         Game Plugin
@@ -46,8 +46,27 @@ pub enum GameState {
 //         GameFlags
 //==============================================================================
 
+/// Named story flags, set by camera zones and anything else that needs to
+/// remember what the player has done.
 #[derive(Resource, Default)]
-pub struct GameFlags {}
+pub struct GameFlags {
+    flags : HashSet<String>,
+}
+
+impl GameFlags {
+    pub fn set(&mut self, flag : impl Into<String>, value : bool) {
+        let flag = flag.into();
+        if value {
+            self.flags.insert(flag);
+        } else {
+            self.flags.remove(&flag);
+        }
+    }
+
+    pub fn is_set(&self, flag : &str) -> bool {
+        self.flags.contains(flag)
+    }
+}
 
 //==============================================================================
 //         Active Camera
@@ -71,6 +90,10 @@ pub enum GameWorld {
 pub struct CurrentGameWorld(GameWorld);
 
 impl CurrentGameWorld {
+    pub fn get(&self) -> GameWorld {
+        self.0
+    }
+    
     pub fn toggle(&mut self) {
         self.0 = match self.0 {
             GameWorld::Computer => GameWorld::Room,
@@ -84,18 +107,21 @@ pub struct ToggleGameWorldEvent;
 
 pub fn switch_game_world(
     mut commands : Commands,
-    mut computer_world_camera : Query<(Entity, &mut Camera, &mut Projection), (With<ComputerCamera>, Without<RoomCamera>)>,
-    mut room_camera : Query<(Entity, &mut Camera), (With<RoomCamera>, Without<ComputerCamera>)>,
+    mut computer_cameras : Query<(&Computer, &mut Camera, &mut Projection), Without<RoomCamera>>,
+    mut room_camera : Query<(Entity, &mut Camera), (With<RoomCamera>, Without<Computer>)>,
     mut current_game_world : ResMut<CurrentGameWorld>,
     mut events : EventReader<ToggleGameWorldEvent>,
     mut on_game_world_change_events : EventWriter<OnGameWorldChangeEvent>,
-    computer_world_assets : Res<ComputerWorldAssets>,
+    active_computer : Res<ActiveComputer>,
     input : Res<ButtonInput<KeyCode>>,
 ) {
     
-    if !events.is_empty() || input.just_pressed(KeyCode::F1) {
+    // Alt+F1 belongs to the computer's virtual consoles.
+    let alt = input.any_pressed([KeyCode::AltLeft, KeyCode::AltRight]);
+    if !events.is_empty() || (input.just_pressed(KeyCode::F1) && !alt) {
         println!("Event Recieved");
-        let Ok((comp_cam_entity, mut comp_cam, mut comp_projection)) = computer_world_camera.get_single_mut() else { return };
+        let Some(comp_cam_entity) = active_computer.0 else { return };
+        let Ok((computer, mut comp_cam, mut comp_projection)) = computer_cameras.get_mut(comp_cam_entity) else { return };
         let Ok((room_cam_entity, mut room_cam)) = room_camera.get_single_mut() else { return };
         
         match current_game_world.0 {
@@ -109,7 +135,7 @@ pub fn switch_game_world(
                 on_game_world_change_events.send(OnGameWorldChangeEvent(GameWorld::Computer));
             },
             GameWorld::Computer => {
-                comp_cam.target = RenderTarget::Image(computer_world_assets.render_surface_image.clone());
+                comp_cam.target = RenderTarget::Image(computer.render_surface_image.clone());
                 *comp_projection = Projection::default();
                 
                 room_cam.is_active = true;
@@ -1,11 +1,11 @@
 use audio::AudioPlugin;
 use bevy::{prelude::*, render::view::RenderLayers};
-use camera::{path::CameraPathFollower, zone::{CameraZone, CameraZoneBundle, CurrentZone}, CameraPlugin};
+use camera::{zone::CameraZoneSource, zone_graph::DEFAULT_ZONE_GRAPH, CameraPlugin};
 use game::{ActiveCamera, GamePlugin};
 use loading::LoadingPlugin;
+use os::OsPlugin;
 use scene::{RoomCamera, ScenePlugin};
 use ui::UiPlugin;
-use util::EasingFunction;
 
 #[cfg(debug_assertions)]
 mod debug;
@@ -19,11 +19,16 @@ pub mod anim;
 pub mod util;
 pub mod audio;
 pub mod ui;
+pub mod os;
 
 fn main() {
     
     let mut app = App::new();
     
+    if let Some(source) = zone_source_from_args() {
+        app.insert_resource(source);
+    }
+    
     app
         .add_plugins(DefaultPlugins)
         .add_plugins(ScenePlugin)
@@ -32,6 +37,7 @@ fn main() {
         .add_plugins(CameraPlugin)
         .add_plugins(AudioPlugin)
         .add_plugins(UiPlugin)
+        .add_plugins(OsPlugin)
     
         .add_systems(Startup, initialize_essentials)
     ;
@@ -46,41 +52,23 @@ fn main() {
     app.run();
 }
 
+/// `--zones` takes the camera zones from a `.zones.ron` graph instead of the
+/// room model, `--zones path/to.zones.ron` from a particular one.
+fn zone_source_from_args() -> Option<CameraZoneSource> {
+    let args : Vec<String> = std::env::args().collect();
+    let index = args.iter().position(|arg| arg == "--zones")?;
+    let path = args.get(index + 1)
+        .filter(|arg| !arg.starts_with("--"))
+        .cloned()
+        .unwrap_or_else(|| DEFAULT_ZONE_GRAPH.to_string());
+    Some(CameraZoneSource::Graph(path))
+}
+
+/// The camera zones come from the room model, see [`camera::gltf_zone`]. The
+/// camera starts in the top one once the room has been spawned.
 fn initialize_essentials(
     mut commands : Commands
 ) {
-    let mut follower = CameraPathFollower::default();
-    
-    follower.push_node(Transform::from_xyz(-0.0, 1.0, 0.841).with_rotation(Quat::from_euler(EulerRot::XYZ, -3.072, -0.02, -3.1403)), 1.0, EasingFunction::EaseInOut);
-    
-    let computer_node = commands.spawn(CameraZoneBundle {
-        camera_zone: CameraZone::new( Cuboid::new(0.58, 0.58, 0.42) , camera::zone::CameraZoneAction::Computer),
-        spatial_bundle : SpatialBundle {
-            transform: Transform::from_xyz(0.18, 1.09, 2.0),
-            ..Default::default()
-        }
-    }).id();
-    
-    // Load the essientials first
-    let desk_node = commands.spawn(CameraZoneBundle {
-        camera_zone: CameraZone::new_movement_zone(Cuboid::default(), Transform::from_xyz(-0.0, 1.0, 0.841).with_rotation(Quat::from_euler(EulerRot::XYZ, -3.072, -0.02, -3.1403))),
-        spatial_bundle : SpatialBundle {
-            transform: Transform::from_xyz(0.0, 1.0, 2.0),
-            ..Default::default()
-        }
-    }).add_child(computer_node).id();
-    
-    let root = commands.spawn((
-        CameraZoneBundle {
-            camera_zone: CameraZone::new_movement_zone(Cuboid::default(), Transform::from_xyz(-1.0, 1.0, -1.0).with_rotation(Quat::from_euler(EulerRot::XYZ, 0.0, 3.8, 0.0))),
-            spatial_bundle : SpatialBundle {
-                transform: Transform::from_xyz(-1.0, 1.0, -1.0).with_rotation(Quat::from_euler(EulerRot::XYZ, 0.0, 3.8, 0.0)),
-                ..Default::default()
-            },
-        },
-        Name::new("Root Camera Zone")
-    )).add_child(desk_node).id();
-    
     commands.spawn((
         Camera3dBundle {
             transform: Transform::from_xyz(-1.0, 1.0, -1.0).with_rotation(Quat::from_euler(EulerRot::XYZ, 0.0, 3.8, 0.0)),
@@ -93,6 +81,5 @@ fn initialize_essentials(
         RenderLayers::layer(0),
         RoomCamera,
         ActiveCamera,
-        CurrentZone(root)
     ));
 }
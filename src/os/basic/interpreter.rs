@@ -0,0 +1,466 @@
+use std::collections::HashMap;
+
+use super::parser::{BasicError, BasicProgram, BinaryOp, Expr, PrintItem, Statement, Target, UnaryOp};
+
+/// The most elements a `DIM` can ask for before it runs out of memory.
+const MAX_ARRAY_LENGTH : usize = 65536;
+
+//==============================================================================
+//         Values
+//==============================================================================
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Number(f64),
+    Text(String),
+}
+
+impl Value {
+    fn default_for(name : &str) -> Self {
+        if name.ends_with('$') { Self::Text(String::new()) } else { Self::Number(0.0) }
+    }
+
+    fn number(&self) -> Result<f64, BasicError> {
+        match self {
+            Self::Number(number) => Ok(*number),
+            Self::Text(_) => Err(BasicError::new("TYPE MISMATCH")),
+        }
+    }
+
+    fn text(&self) -> Result<&str, BasicError> {
+        match self {
+            Self::Text(text) => Ok(text),
+            Self::Number(_) => Err(BasicError::new("TYPE MISMATCH")),
+        }
+    }
+
+    fn truth(value : bool) -> Self {
+        Self::Number(if value { -1.0 } else { 0.0 })
+    }
+
+    /// Numbers print with a leading space for the sign, like the C64.
+    fn to_print(&self) -> String {
+        match self {
+            Self::Number(number) if *number >= 0.0 => format!(" {} ", format_number(*number)),
+            Self::Number(number) => format!("{} ", format_number(*number)),
+            Self::Text(text) => text.clone(),
+        }
+    }
+}
+
+fn format_number(number : f64) -> String {
+    if number.fract() == 0.0 && number.abs() < 1e15 {
+        format!("{}", number as i64)
+    } else {
+        format!("{}", number)
+    }
+}
+
+//==============================================================================
+//         Basic Output
+//==============================================================================
+
+/// What a running program asks the terminal to do.
+#[derive(Debug, Clone, PartialEq)]
+pub enum BasicOutput {
+    Line(String),
+    Clear,
+}
+
+/// Why [`BasicMachine::run`] stopped before using up its budget.
+#[derive(Debug, Clone, PartialEq)]
+pub enum BasicStatus {
+    Running,
+    /// Waiting for the player to answer an `INPUT` with the given prompt.
+    WaitingForInput(String),
+    Finished,
+    Error(BasicError),
+}
+
+//==============================================================================
+//         Basic Machine
+//==============================================================================
+
+/// Where execution is: a line index into the program and a statement index into that line.
+type ProgramCounter = (usize, usize);
+
+struct ForLoop {
+    variable : String,
+    to : f64,
+    step : f64,
+    body : ProgramCounter,
+}
+
+/// The state of a running G64 BASIC program. It only ever runs a bounded number
+/// of statements at a time so the caller can spread a program over many frames.
+pub struct BasicMachine {
+    program : BasicProgram,
+    pc : ProgramCounter,
+    variables : HashMap<String, Value>,
+    arrays : HashMap<String, (Vec<usize>, Vec<Value>)>,
+    gosubs : Vec<ProgramCounter>,
+    loops : Vec<ForLoop>,
+    pending_input : Vec<Target>,
+    line_buffer : String,
+    rng : u64,
+    pub output : Vec<BasicOutput>,
+}
+
+impl BasicMachine {
+    pub fn new(program : BasicProgram, seed : u64) -> Self {
+        Self {
+            program,
+            pc : (0, 0),
+            variables : HashMap::new(),
+            arrays : HashMap::new(),
+            gosubs : Vec::new(),
+            loops : Vec::new(),
+            pending_input : Vec::new(),
+            line_buffer : String::new(),
+            rng : seed | 1,
+            output : Vec::new(),
+        }
+    }
+
+    /// The line number that is about to run, used for `BREAK IN` messages.
+    pub fn current_line(&self) -> Option<u32> {
+        self.program.lines.get(self.pc.0).map(|line| line.number)
+    }
+
+    pub fn is_waiting_for_input(&self) -> bool {
+        !self.pending_input.is_empty()
+    }
+
+    /// Runs at most `budget` statements.
+    pub fn run(&mut self, budget : usize) -> BasicStatus {
+        if self.is_waiting_for_input() {
+            return BasicStatus::WaitingForInput("? ".to_string());
+        }
+
+        for _ in 0..budget {
+            let Some(line) = self.program.lines.get(self.pc.0) else {
+                self.flush();
+                return BasicStatus::Finished;
+            };
+
+            let number = line.number;
+            let Some(statement) = line.statements.get(self.pc.1).cloned() else {
+                self.pc = (self.pc.0 + 1, 0);
+                continue;
+            };
+
+            self.pc.1 += 1;
+            match self.execute(statement) {
+                Ok(BasicStatus::Running) => {},
+                Ok(BasicStatus::Error(error)) | Err(error) => {
+                    self.flush();
+                    return BasicStatus::Error(error.at(number));
+                },
+                Ok(status) => return status,
+            }
+        }
+
+        BasicStatus::Running
+    }
+
+    /// Feeds a line typed by the player into the `INPUT` statement that is waiting for it.
+    pub fn provide_input(&mut self, line : &str) -> Result<(), BasicError> {
+        let targets = std::mem::take(&mut self.pending_input);
+        let mut values = line.split(',').map(str::trim);
+
+        for target in targets.iter() {
+            let text = values.next().unwrap_or("");
+            let value = if target.name.ends_with('$') {
+                Value::Text(text.to_string())
+            } else {
+                match text.parse() {
+                    Ok(number) => Value::Number(number),
+                    Err(_) => {
+                        self.pending_input = targets.clone();
+                        self.output.push(BasicOutput::Line("?REDO FROM START".to_string()));
+                        return Ok(());
+                    },
+                }
+            };
+            self.store(target, value)?;
+        }
+
+        Ok(())
+    }
+
+    fn flush(&mut self) {
+        if !self.line_buffer.is_empty() {
+            let line = std::mem::take(&mut self.line_buffer);
+            self.output.push(BasicOutput::Line(line));
+        }
+    }
+
+    fn jump(&mut self, number : u32) -> Result<(), BasicError> {
+        let index = self.program.find_line(number).ok_or(BasicError::new("UNDEF'D STATEMENT"))?;
+        self.pc = (index, 0);
+        Ok(())
+    }
+
+    fn execute(&mut self, statement : Statement) -> Result<BasicStatus, BasicError> {
+        match statement {
+            Statement::Print { items, newline } => {
+                for item in items {
+                    match item {
+                        PrintItem::Expr(expr) => {
+                            let value = self.evaluate(&expr)?;
+                            self.line_buffer.push_str(&value.to_print());
+                        },
+                        PrintItem::Zone => {
+                            let padding = 10 - self.line_buffer.chars().count() % 10;
+                            self.line_buffer.push_str(&" ".repeat(padding));
+                        },
+                    }
+                }
+                if newline {
+                    let line = std::mem::take(&mut self.line_buffer);
+                    self.output.push(BasicOutput::Line(line));
+                }
+            },
+            Statement::Input { prompt, targets } => {
+                let prompt = format!("{}{}? ", std::mem::take(&mut self.line_buffer), prompt.unwrap_or_default());
+                self.pending_input = targets;
+                return Ok(BasicStatus::WaitingForInput(prompt));
+            },
+            Statement::Let(target, expr) => {
+                let value = self.evaluate(&expr)?;
+                self.store(&target, value)?;
+            },
+            Statement::Goto(number) => self.jump(number)?,
+            Statement::Gosub(number) => {
+                if self.gosubs.len() >= 64 { return Err(BasicError::new("OUT OF MEMORY")) }
+                self.gosubs.push(self.pc);
+                self.jump(number)?;
+            },
+            Statement::Return => {
+                self.pc = self.gosubs.pop().ok_or(BasicError::new("RETURN WITHOUT GOSUB"))?;
+            },
+            Statement::If(condition) => {
+                if self.evaluate(&condition)?.number()? == 0.0 {
+                    self.pc = (self.pc.0 + 1, 0);
+                }
+            },
+            Statement::For { variable, from, to, step } => {
+                let from = self.evaluate(&from)?.number()?;
+                let to = self.evaluate(&to)?.number()?;
+                let step = match step {
+                    Some(step) => self.evaluate(&step)?.number()?,
+                    None => 1.0,
+                };
+                self.variables.insert(variable.clone(), Value::Number(from));
+                self.loops.retain(|active| active.variable != variable);
+                self.loops.push(ForLoop { variable, to, step, body : self.pc });
+            },
+            Statement::Next(variable) => {
+                if let Some(variable) = variable {
+                    let position = self.loops.iter().rposition(|active| active.variable == variable)
+                        .ok_or(BasicError::new("NEXT WITHOUT FOR"))?;
+                    self.loops.truncate(position + 1);
+                }
+                let active = self.loops.last().ok_or(BasicError::new("NEXT WITHOUT FOR"))?;
+                let (variable, to, step, body) = (active.variable.clone(), active.to, active.step, active.body);
+
+                let value = self.variables.get(&variable).map(Value::number).transpose()?.unwrap_or(0.0) + step;
+                self.variables.insert(variable, Value::Number(value));
+
+                let done = if step >= 0.0 { value > to } else { value < to };
+                if done {
+                    self.loops.pop();
+                } else {
+                    self.pc = body;
+                }
+            },
+            Statement::Dim(arrays) => {
+                for (name, sizes) in arrays {
+                    if self.arrays.contains_key(&name) { return Err(BasicError::new("REDIM'D ARRAY")) }
+                    let out_of_memory = || BasicError::new("OUT OF MEMORY");
+                    let mut dimensions = Vec::new();
+                    for size in sizes.iter() {
+                        // Checked before the cast, a huge size would overflow the length.
+                        let size = self.evaluate(size)?.number()?.max(0.0);
+                        if size >= MAX_ARRAY_LENGTH as f64 { return Err(out_of_memory()) }
+                        dimensions.push((size as usize).checked_add(1).ok_or_else(out_of_memory)?);
+                    }
+                    let length = dimensions.iter().try_fold(1usize, |length, dimension| length.checked_mul(*dimension)).ok_or_else(out_of_memory)?;
+                    if length > MAX_ARRAY_LENGTH { return Err(out_of_memory()) }
+                    self.arrays.insert(name.clone(), (dimensions, vec![Value::default_for(&name); length]));
+                }
+            },
+            Statement::Cls => {
+                self.line_buffer.clear();
+                self.output.push(BasicOutput::Clear);
+            },
+            Statement::End => {
+                self.flush();
+                return Ok(BasicStatus::Finished);
+            },
+            Statement::Rem => {},
+        }
+
+        Ok(BasicStatus::Running)
+    }
+
+    fn array_index(&mut self, name : &str, indices : &[Expr]) -> Result<usize, BasicError> {
+        let mut values = Vec::new();
+        for index in indices {
+            values.push(self.evaluate(index)?.number()?);
+        }
+
+        // Arrays that were never dimensioned get ten elements per dimension, like the C64.
+        if !self.arrays.contains_key(name) {
+            let dimensions = vec![11; values.len()];
+            let length = dimensions.iter().product::<usize>();
+            self.arrays.insert(name.to_string(), (dimensions, vec![Value::default_for(name); length]));
+        }
+
+        let (dimensions, _) = &self.arrays[name];
+        if dimensions.len() != values.len() {
+            return Err(BasicError::new("BAD SUBSCRIPT"));
+        }
+
+        values.iter().zip(dimensions.iter()).try_fold(0, |offset, (value, size)| {
+            if *value < 0.0 || *value as usize >= *size {
+                Err(BasicError::new("BAD SUBSCRIPT"))
+            } else {
+                Ok(offset * size + *value as usize)
+            }
+        })
+    }
+
+    fn store(&mut self, target : &Target, value : Value) -> Result<(), BasicError> {
+        if target.name.ends_with('$') != matches!(value, Value::Text(_)) {
+            return Err(BasicError::new("TYPE MISMATCH"));
+        }
+
+        if target.indices.is_empty() {
+            self.variables.insert(target.name.clone(), value);
+        } else {
+            let index = self.array_index(&target.name, &target.indices)?;
+            if let Some((_, values)) = self.arrays.get_mut(&target.name) {
+                values[index] = value;
+            }
+        }
+        Ok(())
+    }
+
+    fn random(&mut self) -> f64 {
+        self.rng ^= self.rng << 13;
+        self.rng ^= self.rng >> 7;
+        self.rng ^= self.rng << 17;
+        (self.rng >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    fn evaluate(&mut self, expr : &Expr) -> Result<Value, BasicError> {
+        match expr {
+            Expr::Number(number) => Ok(Value::Number(*number)),
+            Expr::Text(text) => Ok(Value::Text(text.clone())),
+            Expr::Variable(name) => Ok(self.variables.get(name).cloned().unwrap_or_else(|| Value::default_for(name))),
+            Expr::Call(name, arguments) => {
+                if self.arrays.contains_key(name) || !is_function(name) {
+                    let index = self.array_index(name, arguments)?;
+                    return Ok(self.arrays[name].1[index].clone());
+                }
+
+                let mut values = Vec::new();
+                for argument in arguments {
+                    values.push(self.evaluate(argument)?);
+                }
+                self.call(name, &values)
+            },
+            Expr::Unary(op, operand) => {
+                let value = self.evaluate(operand)?.number()?;
+                Ok(match op {
+                    UnaryOp::Neg => Value::Number(-value),
+                    UnaryOp::Not => Value::Number(!(value as i64) as f64),
+                })
+            },
+            Expr::Binary(op, left, right) => {
+                let left = self.evaluate(left)?;
+                let right = self.evaluate(right)?;
+                binary(*op, left, right)
+            },
+        }
+    }
+
+    fn call(&mut self, name : &str, values : &[Value]) -> Result<Value, BasicError> {
+        let argument = |index : usize| values.get(index).ok_or(BasicError::syntax());
+        let illegal = || BasicError::new("ILLEGAL QUANTITY");
+
+        Ok(match name {
+            "RND" => Value::Number(self.random()),
+            "INT" => Value::Number(argument(0)?.number()?.floor()),
+            "ABS" => Value::Number(argument(0)?.number()?.abs()),
+            "SQR" => {
+                let value = argument(0)?.number()?;
+                if value < 0.0 { return Err(illegal()) }
+                Value::Number(value.sqrt())
+            },
+            "LEN" => Value::Number(argument(0)?.text()?.chars().count() as f64),
+            "VAL" => Value::Number(argument(0)?.text()?.trim().parse().unwrap_or(0.0)),
+            "STR$" => Value::Text(argument(0)?.to_print().trim_end().to_string()),
+            "CHR$" => Value::Text(char::from_u32(argument(0)?.number()? as u32).ok_or_else(illegal)?.to_string()),
+            "ASC" => Value::Number(argument(0)?.text()?.chars().next().ok_or_else(illegal)? as u32 as f64),
+            "LEFT$" => {
+                let count = argument(1)?.number()?.max(0.0) as usize;
+                Value::Text(argument(0)?.text()?.chars().take(count).collect())
+            },
+            "RIGHT$" => {
+                let text = argument(0)?.text()?;
+                let count = argument(1)?.number()?.max(0.0) as usize;
+                Value::Text(text.chars().skip(text.chars().count().saturating_sub(count)).collect())
+            },
+            "MID$" => {
+                let start = (argument(1)?.number()?.max(1.0) as usize) - 1;
+                let count = match values.get(2) {
+                    Some(count) => count.number()?.max(0.0) as usize,
+                    None => usize::MAX,
+                };
+                Value::Text(argument(0)?.text()?.chars().skip(start).take(count).collect())
+            },
+            _ => return Err(BasicError::syntax()),
+        })
+    }
+}
+
+fn is_function(name : &str) -> bool {
+    matches!(name, "RND" | "INT" | "ABS" | "SQR" | "LEN" | "VAL" | "STR$" | "CHR$" | "ASC" | "LEFT$" | "RIGHT$" | "MID$")
+}
+
+fn binary(op : BinaryOp, left : Value, right : Value) -> Result<Value, BasicError> {
+    if let (Value::Text(left), Value::Text(right)) = (&left, &right) {
+        return Ok(match op {
+            BinaryOp::Add => Value::Text(format!("{}{}", left, right)),
+            BinaryOp::Eq => Value::truth(left == right),
+            BinaryOp::NotEq => Value::truth(left != right),
+            BinaryOp::Less => Value::truth(left < right),
+            BinaryOp::LessEq => Value::truth(left <= right),
+            BinaryOp::Greater => Value::truth(left > right),
+            BinaryOp::GreaterEq => Value::truth(left >= right),
+            _ => return Err(BasicError::new("TYPE MISMATCH")),
+        });
+    }
+
+    let (left, right) = (left.number()?, right.number()?);
+    Ok(match op {
+        BinaryOp::Add => Value::Number(left + right),
+        BinaryOp::Sub => Value::Number(left - right),
+        BinaryOp::Mul => Value::Number(left * right),
+        BinaryOp::Div => {
+            if right == 0.0 { return Err(BasicError::new("DIVISION BY ZERO")) }
+            Value::Number(left / right)
+        },
+        BinaryOp::Pow => Value::Number(left.powf(right)),
+        BinaryOp::Eq => Value::truth(left == right),
+        BinaryOp::NotEq => Value::truth(left != right),
+        BinaryOp::Less => Value::truth(left < right),
+        BinaryOp::LessEq => Value::truth(left <= right),
+        BinaryOp::Greater => Value::truth(left > right),
+        BinaryOp::GreaterEq => Value::truth(left >= right),
+        BinaryOp::And => Value::Number(((left as i64) & (right as i64)) as f64),
+        BinaryOp::Or => Value::Number(((left as i64) | (right as i64)) as f64),
+    })
+}
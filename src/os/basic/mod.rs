@@ -0,0 +1,177 @@
+pub mod parser;
+pub mod interpreter;
+
+use bevy::prelude::*;
+use bevy_ascii::prelude::*;
+
+use crate::{scene::computer_world::{ComputerNode, ComputerPoweredOffEvent}, ui::{commands::{RegisterTerminalCommand, TerminalCommandInfo, TerminalCommandInput, TerminalOutput}, parser::TerminalArgKind, terminal::{TerminalComponent, TerminalForeground, TerminalInterruptEvent, TerminalProgramInputEvent, DEFAULT_PROMPT}}};
+
+use self::{interpreter::{BasicMachine, BasicOutput, BasicStatus}, parser::BasicProgram};
+
+use super::fs::VirtualFs;
+
+/// How many statements a program may run each frame, so `10 GOTO 10` can't stall the game.
+const STATEMENTS_PER_FRAME : usize = 200;
+
+//==============================================================================
+//         Basic Plugin
+//==============================================================================
+
+pub struct BasicPlugin;
+
+impl Plugin for BasicPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_systems(Update, (basic_program_input, interrupt_basic_programs, run_basic_programs).chain())
+            .add_systems(PostUpdate, kill_basic_programs)
+        
+            .register_terminal_command(
+                TerminalCommandInfo::new("basic", "run a G64 BASIC program")
+                    .with_arg("file", TerminalArgKind::Path),
+                basic_command
+            )
+        ;
+    }
+}
+
+//==============================================================================
+//         Basic Process
+//==============================================================================
+
+/// A BASIC program running in the terminal it is attached to.
+#[derive(Component)]
+pub struct BasicProcess {
+    pub machine : BasicMachine,
+}
+
+//==============================================================================
+//         Basic Systems
+//==============================================================================
+
+fn basic_command(
+    In(input) : In<TerminalCommandInput>,
+    mut commands : Commands,
+    mut terminals : Query<&mut TerminalComponent>,
+    mut output : ResMut<TerminalOutput>,
+    filesystem : Res<VirtualFs>,
+    time : Res<Time>,
+) {
+    let Ok(mut terminal) = terminals.get_mut(input.terminal) else { return };
+    let Some(file) = input.args.str(0) else { return };
+
+    let path = VirtualFs::resolve(&terminal.working_directory, file);
+    let program = match filesystem.read(&path, terminal.user()).map(BasicProgram::parse) {
+        Ok(Ok(program)) => program,
+        Ok(Err(error)) => return output.add_error_line(&error.to_string()),
+        Err(error) => return output.add_error_line(&format!("basic: {}", error)),
+    };
+
+    terminal.prompt.clear();
+    commands.entity(input.terminal).insert((
+        BasicProcess { machine : BasicMachine::new(program, time.elapsed().as_nanos() as u64) },
+        TerminalForeground { program : "basic".to_string() },
+    ));
+}
+
+fn run_basic_programs(
+    mut commands : Commands,
+    mut programs : Query<(Entity, &mut BasicProcess, &mut TerminalComponent)>,
+    mut mark_dirty : EventWriter<AsciiMarkDirtyEvent>,
+) {
+    for (entity, mut process, mut terminal) in programs.iter_mut() {
+        // A waiting program only has output left over from a rejected answer.
+        let status = match process.machine.is_waiting_for_input() {
+            true => None,
+            false => Some(process.machine.run(STATEMENTS_PER_FRAME)),
+        };
+        
+        for output in process.machine.output.drain(..) {
+            match output {
+                BasicOutput::Line(line) => terminal.add_line(&line),
+                BasicOutput::Clear => terminal.scrollback.clear(),
+            }
+            terminal.scrollback.scroll_to_bottom();
+            mark_dirty.send(AsciiMarkDirtyEvent);
+        }
+        
+        match status {
+            None | Some(BasicStatus::Running) => {},
+            Some(BasicStatus::WaitingForInput(prompt)) => {
+                terminal.prompt = prompt;
+                mark_dirty.send(AsciiMarkDirtyEvent);
+            },
+            Some(BasicStatus::Finished) => {
+                end_program(&mut commands, entity, &mut terminal);
+                mark_dirty.send(AsciiMarkDirtyEvent);
+            },
+            Some(BasicStatus::Error(error)) => {
+                terminal.add_error_line(&error.to_string());
+                end_program(&mut commands, entity, &mut terminal);
+                mark_dirty.send(AsciiMarkDirtyEvent);
+            },
+        }
+    }
+}
+
+fn basic_program_input(
+    mut commands : Commands,
+    mut programs : Query<(&mut BasicProcess, &mut TerminalComponent)>,
+    mut input_events : EventReader<TerminalProgramInputEvent>,
+) {
+    for event in input_events.read() {
+        let Ok((mut process, mut terminal)) = programs.get_mut(event.terminal) else { continue };
+        if !process.machine.is_waiting_for_input() { continue }
+        
+        if let Err(error) = process.machine.provide_input(&event.line) {
+            let error = match process.machine.current_line() {
+                Some(line) => error.at(line),
+                None => error,
+            };
+            terminal.add_error_line(&error.to_string());
+            end_program(&mut commands, event.terminal, &mut terminal);
+            continue;
+        }
+        
+        terminal.prompt = match process.machine.is_waiting_for_input() {
+            true => "? ".to_string(),
+            false => String::new(),
+        };
+    }
+}
+
+fn interrupt_basic_programs(
+    mut commands : Commands,
+    mut programs : Query<(&BasicProcess, &mut TerminalComponent)>,
+    mut interrupt_events : EventReader<TerminalInterruptEvent>,
+    mut mark_dirty : EventWriter<AsciiMarkDirtyEvent>,
+) {
+    for event in interrupt_events.read() {
+        let Ok((process, mut terminal)) = programs.get_mut(event.terminal) else { continue };
+        
+        let message = match process.machine.current_line() {
+            Some(line) => format!("BREAK IN {}", line),
+            None => "BREAK".to_string(),
+        };
+        terminal.add_line(&message);
+        end_program(&mut commands, event.terminal, &mut terminal);
+        mark_dirty.send(AsciiMarkDirtyEvent);
+    }
+}
+
+fn kill_basic_programs(
+    mut commands : Commands,
+    programs : Query<(Entity, &ComputerNode), With<BasicProcess>>,
+    mut powered_off_events : EventReader<ComputerPoweredOffEvent>,
+) {
+    for ComputerPoweredOffEvent(computer) in powered_off_events.read() {
+        for (entity, node) in programs.iter() {
+            if node.0 != *computer { continue }
+            commands.entity(entity).remove::<BasicProcess>();
+        }
+    }
+}
+
+fn end_program(commands : &mut Commands, terminal_entity : Entity, terminal : &mut TerminalComponent) {
+    terminal.prompt = DEFAULT_PROMPT.to_string();
+    commands.entity(terminal_entity).remove::<(BasicProcess, TerminalForeground)>();
+}
@@ -0,0 +1,497 @@
+use std::fmt::Display;
+
+//==============================================================================
+//         Basic Errors
+//==============================================================================
+
+/// A G64 BASIC error, printed C64 style as `?SYNTAX ERROR IN 20`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BasicError {
+    pub kind : &'static str,
+    pub line : Option<u32>,
+}
+
+impl BasicError {
+    pub fn new(kind : &'static str) -> Self {
+        Self { kind, line : None }
+    }
+
+    pub fn at(mut self, line : u32) -> Self {
+        self.line.get_or_insert(line);
+        self
+    }
+
+    pub fn syntax() -> Self {
+        Self::new("SYNTAX")
+    }
+}
+
+impl Display for BasicError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "?{} ERROR IN {}", self.kind, line),
+            None => write!(f, "?{} ERROR", self.kind),
+        }
+    }
+}
+
+//==============================================================================
+//         Syntax Tree
+//==============================================================================
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryOp {
+    Add, Sub, Mul, Div, Pow,
+    Eq, NotEq, Less, LessEq, Greater, GreaterEq,
+    And, Or,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnaryOp {
+    Neg,
+    Not,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    Number(f64),
+    Text(String),
+    Variable(String),
+    /// Either an array element or a built-in function call, decided when it runs.
+    Call(String, Vec<Expr>),
+    Unary(UnaryOp, Box<Expr>),
+    Binary(BinaryOp, Box<Expr>, Box<Expr>),
+}
+
+/// Somewhere a value can be stored: a variable or an array element.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Target {
+    pub name : String,
+    pub indices : Vec<Expr>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PrintItem {
+    Expr(Expr),
+    /// A `,` between items, which moves to the next print zone.
+    Zone,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Statement {
+    Print { items : Vec<PrintItem>, newline : bool },
+    Input { prompt : Option<String>, targets : Vec<Target> },
+    Let(Target, Expr),
+    Goto(u32),
+    Gosub(u32),
+    Return,
+    /// Skips the rest of the line when the condition is false.
+    If(Expr),
+    For { variable : String, from : Expr, to : Expr, step : Option<Expr> },
+    Next(Option<String>),
+    Dim(Vec<(String, Vec<Expr>)>),
+    Cls,
+    End,
+    Rem,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BasicLine {
+    pub number : u32,
+    pub statements : Vec<Statement>,
+}
+
+/// A parsed program with its lines in ascending line number order.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BasicProgram {
+    pub lines : Vec<BasicLine>,
+}
+
+impl BasicProgram {
+    pub fn parse(source : &str) -> Result<Self, BasicError> {
+        let mut lines = Vec::<BasicLine>::new();
+
+        for text in source.lines() {
+            let text = text.trim();
+            if text.is_empty() { continue }
+
+            let digits = text.chars().take_while(char::is_ascii_digit).count();
+            let number = text[..digits].parse::<u32>().map_err(|_| BasicError::syntax())?;
+            let statements = Parser::new(&text[digits..]).map_err(|error| error.at(number))?
+                .statements()
+                .map_err(|error| error.at(number))?;
+
+            lines.retain(|line| line.number != number);
+            lines.push(BasicLine { number, statements });
+        }
+
+        lines.sort_by_key(|line| line.number);
+        Ok(Self { lines })
+    }
+
+    pub fn find_line(&self, number : u32) -> Option<usize> {
+        self.lines.binary_search_by_key(&number, |line| line.number).ok()
+    }
+}
+
+//==============================================================================
+//         Lexer
+//==============================================================================
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(f64),
+    Text(String),
+    Word(String),
+    Symbol(&'static str),
+    /// Everything after `REM`, kept so comments can hold any character.
+    Remark,
+}
+
+fn lex(source : &str) -> Result<Vec<Token>, BasicError> {
+    let chars = source.chars().collect::<Vec<_>>();
+    let mut tokens = Vec::new();
+    let mut index = 0;
+
+    while index < chars.len() {
+        let c = chars[index];
+
+        if c.is_whitespace() {
+            index += 1;
+        } else if c.is_ascii_digit() || (c == '.' && chars.get(index + 1).map_or(false, char::is_ascii_digit)) {
+            let start = index;
+            while index < chars.len() && (chars[index].is_ascii_digit() || chars[index] == '.') {
+                index += 1;
+            }
+            let text = chars[start..index].iter().collect::<String>();
+            tokens.push(Token::Number(text.parse().map_err(|_| BasicError::syntax())?));
+        } else if c == '"' {
+            let start = index + 1;
+            index = start;
+            while index < chars.len() && chars[index] != '"' {
+                index += 1;
+            }
+            tokens.push(Token::Text(chars[start..index].iter().collect()));
+            index += 1;
+        } else if c.is_ascii_alphabetic() {
+            let start = index;
+            while index < chars.len() && chars[index].is_ascii_alphanumeric() {
+                index += 1;
+            }
+            if chars.get(index) == Some(&'$') {
+                index += 1;
+            }
+            let word = chars[start..index].iter().collect::<String>().to_uppercase();
+            if word == "REM" {
+                tokens.push(Token::Remark);
+                break;
+            }
+            tokens.push(Token::Word(word));
+        } else {
+            let pair = chars[index..].iter().take(2).collect::<String>();
+            let symbol = ["<=", ">=", "<>"].into_iter().find(|symbol| *symbol == pair);
+            let symbol = match symbol {
+                Some(symbol) => symbol,
+                None => ["+", "-", "*", "/", "^", "(", ")", ",", ";", ":", "=", "<", ">"]
+                    .into_iter()
+                    .find(|symbol| symbol.starts_with(c))
+                    .ok_or_else(BasicError::syntax)?,
+            };
+            index += symbol.len();
+            tokens.push(Token::Symbol(symbol));
+        }
+    }
+
+    Ok(tokens)
+}
+
+//==============================================================================
+//         Parser
+//==============================================================================
+
+struct Parser {
+    tokens : Vec<Token>,
+    position : usize,
+}
+
+impl Parser {
+    fn new(source : &str) -> Result<Self, BasicError> {
+        Ok(Self { tokens : lex(source)?, position : 0 })
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn at_symbol(&self, symbol : &str) -> bool {
+        matches!(self.peek(), Some(Token::Symbol(found)) if *found == symbol)
+    }
+
+    fn at_word(&self, word : &str) -> bool {
+        matches!(self.peek(), Some(Token::Word(found)) if found == word)
+    }
+
+    fn eat_symbol(&mut self, symbol : &str) -> bool {
+        let found = self.at_symbol(symbol);
+        if found { self.position += 1 }
+        found
+    }
+
+    fn eat_word(&mut self, word : &str) -> bool {
+        let found = self.at_word(word);
+        if found { self.position += 1 }
+        found
+    }
+
+    fn expect_symbol(&mut self, symbol : &str) -> Result<(), BasicError> {
+        if self.eat_symbol(symbol) { Ok(()) } else { Err(BasicError::syntax()) }
+    }
+
+    fn expect_word(&mut self, word : &str) -> Result<(), BasicError> {
+        if self.eat_word(word) { Ok(()) } else { Err(BasicError::syntax()) }
+    }
+
+    fn at_statement_end(&self) -> bool {
+        matches!(self.peek(), None | Some(Token::Remark)) || self.at_symbol(":")
+    }
+
+    fn identifier(&mut self) -> Result<String, BasicError> {
+        match self.next() {
+            Some(Token::Word(word)) if !is_keyword(&word) => Ok(word),
+            _ => Err(BasicError::syntax()),
+        }
+    }
+
+    fn line_number(&mut self) -> Result<u32, BasicError> {
+        match self.next() {
+            Some(Token::Number(number)) if number >= 0.0 && number.fract() == 0.0 => Ok(number as u32),
+            _ => Err(BasicError::syntax()),
+        }
+    }
+
+    fn statements(mut self) -> Result<Vec<Statement>, BasicError> {
+        let mut statements = Vec::new();
+
+        loop {
+            match self.peek() {
+                None => break,
+                Some(Token::Remark) => {
+                    statements.push(Statement::Rem);
+                    break;
+                },
+                Some(Token::Symbol(":")) => { self.position += 1; },
+                _ => self.statement(&mut statements)?,
+            }
+        }
+
+        Ok(statements)
+    }
+
+    fn statement(&mut self, statements : &mut Vec<Statement>) -> Result<(), BasicError> {
+        let Some(Token::Word(word)) = self.peek().cloned() else { return Err(BasicError::syntax()) };
+        if is_keyword(&word) {
+            self.position += 1;
+        }
+
+        let statement = match word.as_str() {
+            "PRINT" => self.print()?,
+            "INPUT" => self.input()?,
+            "LET" => self.assignment()?,
+            "GOTO" => Statement::Goto(self.line_number()?),
+            "GOSUB" => Statement::Gosub(self.line_number()?),
+            "RETURN" => Statement::Return,
+            "IF" => {
+                let condition = self.expression()?;
+                if !self.eat_word("THEN") {
+                    self.expect_word("GOTO")?;
+                }
+                statements.push(Statement::If(condition));
+                if let Some(Token::Number(_)) = self.peek() {
+                    Statement::Goto(self.line_number()?)
+                } else {
+                    return self.statement(statements);
+                }
+            },
+            "FOR" => {
+                let variable = self.identifier()?;
+                self.expect_symbol("=")?;
+                let from = self.expression()?;
+                self.expect_word("TO")?;
+                let to = self.expression()?;
+                let step = if self.eat_word("STEP") { Some(self.expression()?) } else { None };
+                Statement::For { variable, from, to, step }
+            },
+            "NEXT" => {
+                if self.at_statement_end() { Statement::Next(None) } else { Statement::Next(Some(self.identifier()?)) }
+            },
+            "DIM" => {
+                let mut arrays = Vec::new();
+                loop {
+                    let name = self.identifier()?;
+                    self.expect_symbol("(")?;
+                    arrays.push((name, self.arguments()?));
+                    if !self.eat_symbol(",") { break }
+                }
+                Statement::Dim(arrays)
+            },
+            "CLS" => Statement::Cls,
+            "END" | "STOP" => Statement::End,
+            _ if !is_keyword(&word) => self.assignment()?,
+            _ => return Err(BasicError::syntax()),
+        };
+
+        if !self.at_statement_end() {
+            return Err(BasicError::syntax());
+        }
+
+        statements.push(statement);
+        Ok(())
+    }
+
+    fn print(&mut self) -> Result<Statement, BasicError> {
+        let mut items = Vec::new();
+        let mut newline = true;
+
+        while !self.at_statement_end() {
+            if self.eat_symbol(";") {
+                newline = false;
+            } else if self.eat_symbol(",") {
+                items.push(PrintItem::Zone);
+                newline = false;
+            } else {
+                items.push(PrintItem::Expr(self.expression()?));
+                newline = true;
+            }
+        }
+
+        Ok(Statement::Print { items, newline })
+    }
+
+    fn input(&mut self) -> Result<Statement, BasicError> {
+        let prompt = match self.peek() {
+            Some(Token::Text(text)) => {
+                let text = text.clone();
+                self.position += 1;
+                self.expect_symbol(";")?;
+                Some(text)
+            },
+            _ => None,
+        };
+
+        let mut targets = vec![self.target()?];
+        while self.eat_symbol(",") {
+            targets.push(self.target()?);
+        }
+
+        Ok(Statement::Input { prompt, targets })
+    }
+
+    fn assignment(&mut self) -> Result<Statement, BasicError> {
+        let target = self.target()?;
+        self.expect_symbol("=")?;
+        Ok(Statement::Let(target, self.expression()?))
+    }
+
+    fn target(&mut self) -> Result<Target, BasicError> {
+        let name = self.identifier()?;
+        let indices = if self.eat_symbol("(") { self.arguments()? } else { Vec::new() };
+        Ok(Target { name, indices })
+    }
+
+    /// Comma separated expressions up to and including the closing bracket.
+    fn arguments(&mut self) -> Result<Vec<Expr>, BasicError> {
+        let mut arguments = Vec::new();
+        if self.eat_symbol(")") {
+            return Ok(arguments);
+        }
+        loop {
+            arguments.push(self.expression()?);
+            if self.eat_symbol(")") { break }
+            self.expect_symbol(",")?;
+        }
+        Ok(arguments)
+    }
+
+    fn expression(&mut self) -> Result<Expr, BasicError> {
+        self.binary(0)
+    }
+
+    /// Precedence climbing from OR (loosest) down to ^ (tightest).
+    fn binary(&mut self, level : usize) -> Result<Expr, BasicError> {
+        const LEVELS : &[&[(&str, BinaryOp)]] = &[
+            &[("OR", BinaryOp::Or)],
+            &[("AND", BinaryOp::And)],
+            &[("=", BinaryOp::Eq), ("<>", BinaryOp::NotEq), ("<=", BinaryOp::LessEq), (">=", BinaryOp::GreaterEq), ("<", BinaryOp::Less), (">", BinaryOp::Greater)],
+            &[("+", BinaryOp::Add), ("-", BinaryOp::Sub)],
+            &[("*", BinaryOp::Mul), ("/", BinaryOp::Div)],
+            &[("^", BinaryOp::Pow)],
+        ];
+
+        if level >= LEVELS.len() {
+            return self.unary();
+        }
+
+        // NOT binds looser than comparisons, so `NOT A = B` is `NOT (A = B)`.
+        if level == 2 && self.eat_word("NOT") {
+            return Ok(Expr::Unary(UnaryOp::Not, Box::new(self.binary(level)?)));
+        }
+
+        let mut left = self.binary(level + 1)?;
+        'outer: loop {
+            for (symbol, op) in LEVELS[level] {
+                if self.at_symbol(symbol) || self.at_word(symbol) {
+                    self.position += 1;
+                    let right = self.binary(level + 1)?;
+                    left = Expr::Binary(*op, Box::new(left), Box::new(right));
+                    continue 'outer;
+                }
+            }
+            break;
+        }
+
+        Ok(left)
+    }
+
+    fn unary(&mut self) -> Result<Expr, BasicError> {
+        if self.eat_symbol("-") {
+            return Ok(Expr::Unary(UnaryOp::Neg, Box::new(self.unary()?)));
+        }
+        if self.eat_symbol("+") {
+            return self.unary();
+        }
+        self.primary()
+    }
+
+    fn primary(&mut self) -> Result<Expr, BasicError> {
+        match self.next() {
+            Some(Token::Number(number)) => Ok(Expr::Number(number)),
+            Some(Token::Text(text)) => Ok(Expr::Text(text)),
+            Some(Token::Symbol("(")) => {
+                let expr = self.expression()?;
+                self.expect_symbol(")")?;
+                Ok(expr)
+            },
+            Some(Token::Word(word)) if !is_keyword(&word) => {
+                if self.eat_symbol("(") {
+                    Ok(Expr::Call(word, self.arguments()?))
+                } else {
+                    Ok(Expr::Variable(word))
+                }
+            },
+            _ => Err(BasicError::syntax()),
+        }
+    }
+}
+
+fn is_keyword(word : &str) -> bool {
+    matches!(word,
+        "PRINT" | "INPUT" | "LET" | "GOTO" | "GOSUB" | "RETURN" | "IF" | "THEN" | "FOR" | "TO" | "STEP" |
+        "NEXT" | "DIM" | "CLS" | "END" | "STOP" | "AND" | "OR" | "NOT"
+    )
+}
@@ -0,0 +1,325 @@
+pub mod script;
+pub mod session;
+pub mod modem;
+pub mod server;
+
+use bevy::{asset::LoadedFolder, prelude::*};
+use bevy_ascii::prelude::*;
+
+use crate::{audio::sound::PlayToneEvent, loading::LoadingTracker, scene::computer_world::{ComputerNode, ComputerPoweredOffEvent}, ui::{commands::{RegisterTerminalCommand, TerminalCommandInfo, TerminalCommandInput, TerminalOutput}, parser::TerminalArgKind, terminal::{TerminalComponent, TerminalForeground, TerminalInterruptEvent, TerminalProgramInputEvent, DEFAULT_PROMPT}, typewriter::TerminalTypewriter}};
+
+use self::{modem::ModemHandshake, script::{dial_digits, Bbs, BbsLoader}, session::{BbsOutput, BbsSession, DisconnectReason}};
+
+use super::fs::{FsError, FsNode, VirtualFs, HOME_DIRECTORY};
+
+//==============================================================================
+//         Bbs Plugin
+//==============================================================================
+
+pub struct BbsPlugin;
+
+impl Plugin for BbsPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_asset::<Bbs>()
+            .init_asset_loader::<BbsLoader>()
+
+            .add_systems(Startup, load_bbs_library)
+            .add_systems(Update, (bbs_input, hang_up_bbs, update_modems).chain())
+            .add_systems(PostUpdate, drop_bbs_on_power_off)
+
+            .add_event::<BbsSignalEvent>()
+            .add_event::<BbsHangUpEvent>()
+            .add_event::<BbsDisconnectedEvent>()
+
+            .register_terminal_command(
+                TerminalCommandInfo::new("dial", "call a number with the modem")
+                    .with_arg("number", TerminalArgKind::Text),
+                dial_command
+            )
+        ;
+    }
+}
+
+//==============================================================================
+//         Bbs Library
+//==============================================================================
+
+/// Every BBS script found in `assets/bbs`.
+#[derive(Resource)]
+pub struct BbsLibrary {
+    pub folder : Handle<LoadedFolder>,
+}
+
+impl BbsLibrary {
+    pub fn iter<'a>(&self, folders : &'a Assets<LoadedFolder>, scripts : &'a Assets<Bbs>) -> impl Iterator<Item = (Handle<Bbs>, &'a Bbs)> {
+        folders.get(&self.folder).into_iter()
+            .flat_map(|folder| folder.handles.iter())
+            .filter_map(|handle| handle.clone().try_typed::<Bbs>().ok())
+            .filter_map(|handle| scripts.get(&handle).map(|bbs| (handle.clone(), bbs)))
+    }
+
+    /// Finds the board that answers `number`.
+    pub fn find<'a>(&self, number : &str, folders : &'a Assets<LoadedFolder>, scripts : &'a Assets<Bbs>) -> Option<(Handle<Bbs>, &'a Bbs)> {
+        let digits = dial_digits(number);
+        self.iter(folders, scripts).find(|(_, bbs)| dial_digits(&bbs.number) == digits)
+    }
+}
+
+fn load_bbs_library(
+    mut commands : Commands,
+    mut loading_tracker : ResMut<LoadingTracker>,
+    asset_server : Res<AssetServer>,
+) {
+    let folder = asset_server.load_folder("bbs");
+    loading_tracker.push(folder.clone());
+    commands.insert_resource(BbsLibrary { folder });
+}
+
+//==============================================================================
+//         Bbs Events
+//==============================================================================
+
+/// Sent when a script reaches a [`script::BbsAction::Signal`].
+#[derive(Event, Debug, Clone)]
+pub struct BbsSignalEvent {
+    pub terminal : Entity,
+    pub number : String,
+    pub name : String,
+}
+
+/// Lets the story end a call, e.g. the storm taking the phone lines down.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct BbsHangUpEvent {
+    pub terminal : Entity,
+    pub reason : DisconnectReason,
+}
+
+/// Sent whenever a call ends, however it ended.
+#[derive(Event, Debug, Clone)]
+pub struct BbsDisconnectedEvent {
+    pub terminal : Entity,
+    pub number : String,
+    pub reason : DisconnectReason,
+}
+
+//==============================================================================
+//         Bbs Connection
+//==============================================================================
+
+/// A call in progress on the terminal it is attached to. Output is typed out
+/// by the terminal's typewriter at the board's baud rate.
+#[derive(Component)]
+pub struct BbsConnection {
+    pub number : String,
+    bbs : Option<Handle<Bbs>>,
+    handshake : ModemHandshake,
+    /// `None` until the modems have finished the handshake.
+    session : Option<BbsSession>,
+    /// The typewriter's own baud rate, put back when the call ends.
+    baud : f32,
+}
+
+//==============================================================================
+//         Bbs Systems
+//==============================================================================
+
+fn dial_command(
+    In(input) : In<TerminalCommandInput>,
+    mut commands : Commands,
+    mut terminals : Query<(&mut TerminalComponent, &mut TerminalTypewriter)>,
+    mut output : ResMut<TerminalOutput>,
+    library : Res<BbsLibrary>,
+    folders : Res<Assets<LoadedFolder>>,
+    scripts : Res<Assets<Bbs>>,
+) {
+    let Ok((mut terminal, mut typewriter)) = terminals.get_mut(input.terminal) else { return };
+    let Some(number) = input.args.str(0) else { return };
+
+    let digits = dial_digits(number);
+    if digits.is_empty() {
+        return output.add_error_line(&format!("dial: '{}' is not a phone number", number));
+    }
+
+    let bbs = library.find(&digits, &folders, &scripts).map(|(handle, _)| handle);
+
+    terminal.prompt.clear();
+    typewriter.write_line(&format!("ATDT{}", digits));
+    commands.entity(input.terminal).insert((
+        BbsConnection {
+            number : digits.clone(),
+            handshake : ModemHandshake::new(&digits, bbs.is_some()),
+            bbs,
+            session : None,
+            baud : typewriter.baud,
+        },
+        TerminalForeground { program : "dial".to_string() },
+    ));
+}
+
+/// Plays the call out and, once the modems agree, puts the caller through to the board.
+fn update_modems(
+    mut commands : Commands,
+    mut connections : Query<(Entity, &mut BbsConnection, &mut TerminalComponent, &mut TerminalTypewriter)>,
+    mut tone_events : EventWriter<PlayToneEvent>,
+    mut signal_events : EventWriter<BbsSignalEvent>,
+    mut disconnected_events : EventWriter<BbsDisconnectedEvent>,
+    mut mark_dirty : EventWriter<AsciiMarkDirtyEvent>,
+    mut filesystem : ResMut<VirtualFs>,
+    scripts : Res<Assets<Bbs>>,
+    time : Res<Time>,
+) {
+    for (entity, mut connection, mut terminal, mut typewriter) in connections.iter_mut() {
+        if connection.session.is_some() { continue }
+
+        tone_events.send_batch(connection.handshake.tick(time.delta_seconds()));
+        if !connection.handshake.is_finished() { continue }
+
+        let Some(bbs) = connection.bbs.as_ref().and_then(|handle| scripts.get(handle)) else {
+            hang_up(&mut commands, entity, &connection, &mut terminal, &mut typewriter, DisconnectReason::NoAnswer, &mut disconnected_events);
+            continue;
+        };
+
+        typewriter.write_line(&format!("CONNECT {}", bbs.baud));
+        typewriter.baud = bbs.baud;
+
+        let (session, output) = BbsSession::connect(bbs);
+        terminal.prompt = session.prompt(bbs);
+        connection.session = Some(session);
+
+        if let Some(reason) = show_output(output, entity, &connection.number, terminal.user(), &mut typewriter, &mut filesystem, &mut signal_events) {
+            hang_up(&mut commands, entity, &connection, &mut terminal, &mut typewriter, reason, &mut disconnected_events);
+        }
+        mark_dirty.send(AsciiMarkDirtyEvent);
+    }
+}
+
+fn bbs_input(
+    mut commands : Commands,
+    mut connections : Query<(&mut BbsConnection, &mut TerminalComponent, &mut TerminalTypewriter)>,
+    mut input_events : EventReader<TerminalProgramInputEvent>,
+    mut signal_events : EventWriter<BbsSignalEvent>,
+    mut disconnected_events : EventWriter<BbsDisconnectedEvent>,
+    mut mark_dirty : EventWriter<AsciiMarkDirtyEvent>,
+    mut filesystem : ResMut<VirtualFs>,
+    scripts : Res<Assets<Bbs>>,
+) {
+    for event in input_events.read() {
+        let Ok((mut connection, mut terminal, mut typewriter)) = connections.get_mut(event.terminal) else { continue };
+        let Some(bbs) = connection.bbs.as_ref().and_then(|handle| scripts.get(handle)) else { continue };
+
+        // Still dialing, there is nobody to talk to yet.
+        let Some(session) = connection.session.as_mut() else { continue };
+
+        let output = session.input(bbs, &event.line);
+        terminal.prompt = session.prompt(bbs);
+
+        if let Some(reason) = show_output(output, event.terminal, &connection.number, terminal.user(), &mut typewriter, &mut filesystem, &mut signal_events) {
+            hang_up(&mut commands, event.terminal, &connection, &mut terminal, &mut typewriter, reason, &mut disconnected_events);
+        }
+        mark_dirty.send(AsciiMarkDirtyEvent);
+    }
+}
+
+/// Ctrl+C hangs up, and so can the story.
+fn hang_up_bbs(
+    mut commands : Commands,
+    mut connections : Query<(&BbsConnection, &mut TerminalComponent, &mut TerminalTypewriter)>,
+    mut interrupt_events : EventReader<TerminalInterruptEvent>,
+    mut hang_up_events : EventReader<BbsHangUpEvent>,
+    mut disconnected_events : EventWriter<BbsDisconnectedEvent>,
+    mut mark_dirty : EventWriter<AsciiMarkDirtyEvent>,
+) {
+    let requests = interrupt_events.read().map(|event| (event.terminal, DisconnectReason::LocalHangup))
+        .chain(hang_up_events.read().map(|event| (event.terminal, event.reason)))
+        .collect::<Vec<_>>();
+
+    for (entity, reason) in requests {
+        let Ok((connection, mut terminal, mut typewriter)) = connections.get_mut(entity) else { continue };
+        hang_up(&mut commands, entity, connection, &mut terminal, &mut typewriter, reason, &mut disconnected_events);
+        mark_dirty.send(AsciiMarkDirtyEvent);
+    }
+}
+
+/// Losing power drops the line like any other lost carrier.
+fn drop_bbs_on_power_off(
+    mut commands : Commands,
+    mut connections : Query<(Entity, &BbsConnection, &ComputerNode, &mut TerminalComponent, &mut TerminalTypewriter)>,
+    mut powered_off_events : EventReader<ComputerPoweredOffEvent>,
+    mut disconnected_events : EventWriter<BbsDisconnectedEvent>,
+) {
+    for ComputerPoweredOffEvent(computer) in powered_off_events.read() {
+        for (entity, connection, node, mut terminal, mut typewriter) in connections.iter_mut() {
+            if node.0 != *computer { continue }
+            hang_up(&mut commands, entity, connection, &mut terminal, &mut typewriter, DisconnectReason::CarrierLost, &mut disconnected_events);
+            // The screen is dark, so there is nobody to say NO CARRIER to.
+            typewriter.clear();
+        }
+    }
+}
+
+/// Types out what the board sent. Returns why the line went down, if it did.
+fn show_output(
+    output : Vec<BbsOutput>,
+    terminal : Entity,
+    number : &str,
+    user : &str,
+    typewriter : &mut TerminalTypewriter,
+    filesystem : &mut VirtualFs,
+    signal_events : &mut EventWriter<BbsSignalEvent>,
+) -> Option<DisconnectReason> {
+    for item in output {
+        match item {
+            BbsOutput::Line(line) => { typewriter.write_markup_line(&line); },
+            BbsOutput::Download { name, text } => {
+                let message = match download(filesystem, user, &name, &text) {
+                    Ok(path) => format!("Saved {} ({} bytes)", path, text.len()),
+                    Err(error) => format!("[c=red]Download failed: {}[/c]", error),
+                };
+                typewriter.write_markup_line(&message);
+            },
+            BbsOutput::Signal(name) => { signal_events.send(BbsSignalEvent { terminal, number : number.to_string(), name }); },
+            BbsOutput::Disconnect(reason) => return Some(reason),
+        }
+    }
+
+    None
+}
+
+/// Saves a download into `~/downloads`, creating the folder the first time.
+fn download(filesystem : &mut VirtualFs, user : &str, name : &str, text : &str) -> Result<String, FsError> {
+    let folder = format!("{}/downloads", HOME_DIRECTORY);
+    if !filesystem.exists(&folder) {
+        filesystem.insert(&folder, FsNode::directory().owned_by(user), user)?;
+    }
+
+    let path = VirtualFs::resolve(&folder, name);
+    filesystem.write(&path, text, user)?;
+    Ok(path)
+}
+
+fn hang_up(
+    commands : &mut Commands,
+    terminal_entity : Entity,
+    connection : &BbsConnection,
+    terminal : &mut TerminalComponent,
+    typewriter : &mut TerminalTypewriter,
+    reason : DisconnectReason,
+    disconnected_events : &mut EventWriter<BbsDisconnectedEvent>,
+) {
+    if reason == DisconnectReason::LocalHangup {
+        typewriter.clear();
+        typewriter.write_line("+++");
+        typewriter.write_line("ATH0");
+    }
+    typewriter.write_line("NO CARRIER");
+    typewriter.baud = connection.baud;
+
+    terminal.prompt = DEFAULT_PROMPT.to_string();
+    commands.entity(terminal_entity).remove::<(BbsConnection, TerminalForeground)>();
+    disconnected_events.send(BbsDisconnectedEvent {
+        terminal : terminal_entity,
+        number : connection.number.clone(),
+        reason,
+    });
+}
@@ -0,0 +1,86 @@
+use crate::audio::sound::PlayToneEvent;
+
+const MODEM_VOLUME : f32 = 0.04;
+
+/// The noises a modem makes while placing a call: dialing, ringing and, if
+/// anyone answers, the handshake.
+#[derive(Debug, Clone)]
+pub struct ModemHandshake {
+    tones : Vec<(f32, PlayToneEvent)>,
+    elapsed : f32,
+    duration : f32,
+}
+
+impl ModemHandshake {
+    pub fn new(digits : &str, answered : bool) -> Self {
+        let mut handshake = Self { tones : Vec::new(), elapsed : 0.0, duration : 0.0 };
+
+        // Off hook.
+        handshake.chord(&[350.0, 440.0], 0.6);
+        handshake.silence(0.2);
+
+        for digit in digits.chars() {
+            let Some((low, high)) = dtmf(digit) else { continue };
+            handshake.chord(&[low, high], 0.1);
+            handshake.silence(0.07);
+        }
+
+        handshake.silence(1.0);
+        let rings = if answered { 1 } else { 3 };
+        for _ in 0..rings {
+            handshake.chord(&[440.0, 480.0], 1.2);
+            handshake.silence(1.5);
+        }
+        if !answered { return handshake }
+
+        // The answer tone, then the two modems screeching at each other.
+        handshake.chord(&[2100.0], 1.2);
+        handshake.silence(0.1);
+        for frequency in [1200.0, 2400.0, 980.0, 1650.0, 600.0, 3000.0, 1800.0, 2250.0, 1200.0, 2400.0, 1800.0, 980.0] {
+            handshake.chord(&[frequency, frequency * 1.5], 0.12);
+        }
+        handshake.silence(0.4);
+
+        handshake
+    }
+
+    /// Plays `frequencies` together for `duration` seconds.
+    fn chord(&mut self, frequencies : &[f32], duration : f32) {
+        for frequency in frequencies {
+            self.tones.push((self.duration, PlayToneEvent::new(*frequency, duration, MODEM_VOLUME)));
+        }
+        self.duration += duration;
+    }
+
+    fn silence(&mut self, duration : f32) {
+        self.duration += duration;
+    }
+
+    /// Moves the call on by `delta` seconds, returning the tones that start in that time.
+    pub fn tick(&mut self, delta : f32) -> Vec<PlayToneEvent> {
+        let before = self.elapsed;
+        self.elapsed += delta;
+
+        self.tones.iter()
+            .filter(|(at, _)| *at >= before && *at < self.elapsed)
+            .map(|(_, tone)| *tone)
+            .collect()
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.elapsed >= self.duration
+    }
+}
+
+/// The two frequencies of a touch tone key.
+fn dtmf(key : char) -> Option<(f32, f32)> {
+    let (row, column) = match key {
+        '1' => (0, 0), '2' => (0, 1), '3' => (0, 2),
+        '4' => (1, 0), '5' => (1, 1), '6' => (1, 2),
+        '7' => (2, 0), '8' => (2, 1), '9' => (2, 2),
+        '*' => (3, 0), '0' => (3, 1), '#' => (3, 2),
+        _ => return None,
+    };
+
+    Some(([697.0, 770.0, 852.0, 941.0][row], [1209.0, 1336.0, 1477.0][column]))
+}
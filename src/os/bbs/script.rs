@@ -0,0 +1,130 @@
+use std::collections::BTreeMap;
+
+use bevy::{asset::{io::Reader, ron, AssetLoader, AsyncReadExt, LoadContext}, prelude::*, utils::BoxedFuture};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::ui::typewriter::DEFAULT_BAUD;
+
+//==============================================================================
+//         Bbs Asset
+//==============================================================================
+
+/// A scripted bulletin board, loaded from a `.bbs.ron` file. Every line of
+/// text in it is terminal markup.
+#[derive(Asset, TypePath, Debug, Clone, Serialize, Deserialize)]
+pub struct Bbs {
+    /// The number that reaches it. Only the digits count, so `555-0134` and `5550134` are the same.
+    pub number : String,
+    pub name : String,
+    #[serde(default = "default_baud")]
+    pub baud : f32,
+    /// Shown once, right after the modems connect.
+    #[serde(default)]
+    pub welcome : Vec<String>,
+    /// The menu shown after the welcome.
+    pub start : String,
+    pub menus : BTreeMap<String, BbsMenu>,
+    #[serde(default)]
+    pub boards : BTreeMap<String, BbsBoard>,
+}
+
+fn default_baud() -> f32 {
+    DEFAULT_BAUD
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BbsMenu {
+    pub title : String,
+    #[serde(default)]
+    pub text : Vec<String>,
+    pub options : Vec<BbsOption>,
+}
+
+/// A line in a menu, picked by typing its key.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BbsOption {
+    pub key : String,
+    pub label : String,
+    pub action : BbsAction,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum BbsAction {
+    /// Goes to another menu.
+    Menu(String),
+    /// Prints some text and stays in the current menu.
+    Text(Vec<String>),
+    /// Lists a message board and lets the caller read its messages.
+    Board(String),
+    /// Saves a file into the caller's downloads folder.
+    Download { name : String, text : String },
+    /// Tells the story something happened, see [`super::BbsSignalEvent`].
+    Signal(String),
+    /// The board says goodbye and hangs up.
+    Hangup,
+    /// The line goes dead without warning.
+    DropCarrier,
+    /// Runs each action in turn, stopping early if the line goes down.
+    Sequence(Vec<BbsAction>),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BbsBoard {
+    pub title : String,
+    #[serde(default)]
+    pub messages : Vec<BbsMessage>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BbsMessage {
+    pub from : String,
+    pub subject : String,
+    pub body : Vec<String>,
+    /// Runs after the message has been read.
+    #[serde(default)]
+    pub then : Option<BbsAction>,
+}
+
+/// Strips a phone number down to what the modem actually dials.
+pub fn dial_digits(number : &str) -> String {
+    number.chars().filter(|character| character.is_ascii_digit() || *character == '*' || *character == '#').collect()
+}
+
+//==============================================================================
+//         Bbs Loader
+//==============================================================================
+
+#[derive(Debug, Error)]
+pub enum BbsLoaderError {
+    #[error("Could not read BBS script: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Could not parse BBS script: {0}")]
+    Ron(#[from] ron::error::SpannedError),
+}
+
+#[derive(Default)]
+pub struct BbsLoader;
+
+impl AssetLoader for BbsLoader {
+    type Asset = Bbs;
+    type Settings = ();
+    type Error = BbsLoaderError;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _settings: &'a Self::Settings,
+        _load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<Self::Asset, Self::Error>> {
+        Box::pin(async move {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).await?;
+            Ok(ron::de::from_bytes::<Bbs>(&bytes)?)
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["bbs.ron"]
+    }
+}
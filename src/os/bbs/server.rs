@@ -0,0 +1,224 @@
+use std::{io::{ErrorKind, Read, Write}, net::{TcpListener, TcpStream}};
+
+use bevy::{asset::LoadedFolder, prelude::*};
+
+use crate::ui::markup::strip_markup;
+
+use super::{script::{dial_digits, Bbs}, session::{BbsOutput, BbsSession}, BbsLibrary};
+
+/// Where the test server listens. Connect with `telnet 127.0.0.1 2323`.
+pub const BBS_SERVER_ADDRESS : &str = "127.0.0.1:2323";
+
+/// A client that sends this much without a line break is dropped.
+const MAX_PENDING_INPUT : usize = 4096;
+
+/// A client that lets this much output pile up without reading it is dropped.
+const MAX_PENDING_OUTPUT : usize = 256 * 1024;
+
+/// Telnet's "interpret as command" byte, followed by option negotiation we ignore.
+const TELNET_IAC : u8 = 255;
+
+//==============================================================================
+//         Bbs Server Plugin
+//==============================================================================
+
+/// Serves the BBS scripts over plain TCP, so they can be tried out from a
+/// real telnet client without walking up to a computer in game.
+pub struct BbsServerPlugin;
+
+impl Plugin for BbsServerPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_systems(Startup, start_bbs_server)
+            .add_systems(Update, (accept_bbs_clients, update_bbs_clients).chain())
+        ;
+    }
+}
+
+//==============================================================================
+//         Bbs Server
+//==============================================================================
+
+#[derive(Resource)]
+pub struct BbsServer {
+    listener : TcpListener,
+    clients : Vec<BbsClient>,
+}
+
+struct BbsClient {
+    stream : TcpStream,
+    /// Bytes received that don't make a full line yet.
+    pending : Vec<u8>,
+    /// Bytes the socket wasn't ready to take yet.
+    outgoing : Vec<u8>,
+    /// `None` while the client is still choosing a number to dial.
+    call : Option<(Handle<Bbs>, BbsSession)>,
+    /// The board hung up. The client is dropped once the last output is sent.
+    hanging_up : bool,
+    closed : bool,
+}
+
+impl BbsClient {
+    fn send_line(&mut self, line : &str) {
+        self.send(&format!("{}\r\n", strip_markup(line)));
+    }
+
+    fn send(&mut self, text : &str) {
+        self.outgoing.extend_from_slice(text.as_bytes());
+        if self.outgoing.len() > MAX_PENDING_OUTPUT {
+            self.closed = true;
+        }
+        self.flush();
+    }
+
+    /// Writes as much of the waiting output as the socket takes without blocking.
+    fn flush(&mut self) {
+        while !self.outgoing.is_empty() && !self.closed {
+            match self.stream.write(&self.outgoing) {
+                Ok(0) => self.closed = true,
+                Ok(written) => { self.outgoing.drain(..written); },
+                Err(error) if error.kind() == ErrorKind::WouldBlock => break,
+                Err(error) if error.kind() == ErrorKind::Interrupted => {},
+                Err(_) => self.closed = true,
+            }
+        }
+    }
+
+    fn is_finished(&self) -> bool {
+        self.closed || (self.hanging_up && self.outgoing.is_empty())
+    }
+
+    /// Reads whatever has arrived and returns the complete lines in it.
+    fn read_lines(&mut self) -> Vec<String> {
+        let mut buffer = [0; 512];
+        loop {
+            match self.stream.read(&mut buffer) {
+                Ok(0) => { self.closed = true; break },
+                Ok(read) => self.pending.extend_from_slice(&buffer[..read]),
+                Err(error) if error.kind() == ErrorKind::Interrupted => {},
+                Err(error) if error.kind() == ErrorKind::WouldBlock => break,
+                Err(_) => { self.closed = true; break },
+            }
+        }
+
+        let mut lines = Vec::new();
+        while let Some(end) = self.pending.iter().position(|byte| *byte == b'\n') {
+            let line = self.pending.drain(..=end).collect::<Vec<_>>();
+            lines.push(telnet_text(&line));
+        }
+        if self.pending.len() > MAX_PENDING_INPUT {
+            self.closed = true;
+        }
+        lines
+    }
+}
+
+/// Drops telnet commands and line endings, keeping the printable text.
+fn telnet_text(bytes : &[u8]) -> String {
+    let mut text = Vec::new();
+    let mut index = 0;
+    while index < bytes.len() {
+        match bytes[index] {
+            // IAC plus a command byte, plus an option byte for WILL/WONT/DO/DONT.
+            TELNET_IAC => index += if (251..=254).contains(bytes.get(index + 1).unwrap_or(&0)) { 3 } else { 2 },
+            b'\r' | b'\n' => index += 1,
+            byte => { text.push(byte); index += 1 },
+        }
+    }
+    String::from_utf8_lossy(&text).into_owned()
+}
+
+//==============================================================================
+//         Bbs Server Systems
+//==============================================================================
+
+fn start_bbs_server(
+    mut commands : Commands,
+) {
+    let listener = match TcpListener::bind(BBS_SERVER_ADDRESS) {
+        Ok(listener) => listener,
+        Err(error) => return println!("BBS test server could not listen on {}: {}", BBS_SERVER_ADDRESS, error),
+    };
+    if let Err(error) = listener.set_nonblocking(true) {
+        return println!("BBS test server could not listen on {}: {}", BBS_SERVER_ADDRESS, error);
+    }
+
+    println!("BBS test server listening on {}", BBS_SERVER_ADDRESS);
+    commands.insert_resource(BbsServer { listener, clients : Vec::new() });
+}
+
+fn accept_bbs_clients(
+    server : Option<ResMut<BbsServer>>,
+) {
+    let Some(mut server) = server else { return };
+
+    while let Ok((stream, address)) = server.listener.accept() {
+        if stream.set_nonblocking(true).is_err() { continue }
+        println!("BBS test server: {} connected", address);
+
+        let mut client = BbsClient { stream, pending : Vec::new(), outgoing : Vec::new(), call : None, hanging_up : false, closed : false };
+        client.send_line("Genifore 64 modem ready. Type a number to dial.");
+        client.send("ATDT");
+        server.clients.push(client);
+    }
+}
+
+fn update_bbs_clients(
+    server : Option<ResMut<BbsServer>>,
+    library : Res<BbsLibrary>,
+    folders : Res<Assets<LoadedFolder>>,
+    scripts : Res<Assets<Bbs>>,
+) {
+    let Some(mut server) = server else { return };
+
+    for client in server.clients.iter_mut() {
+        client.flush();
+        if client.hanging_up { continue }
+
+        for line in client.read_lines() {
+            let (output, prompt) = match client.call.as_mut() {
+                Some((handle, session)) => {
+                    let Some(bbs) = scripts.get(handle.id()) else { continue };
+                    let output = session.input(bbs, &line);
+                    (output, session.prompt(bbs))
+                },
+                None => {
+                    let Some((handle, bbs)) = library.find(&line, &folders, &scripts) else {
+                        client.send_line(&format!("NO ANSWER ({})", dial_digits(&line)));
+                        client.send("ATDT");
+                        continue;
+                    };
+
+                    client.send_line(&format!("CONNECT {}", bbs.baud));
+                    let (session, output) = BbsSession::connect(bbs);
+                    let prompt = session.prompt(bbs);
+                    client.call = Some((handle, session));
+                    (output, prompt)
+                },
+            };
+
+            for item in output {
+                match item {
+                    BbsOutput::Line(line) => client.send_line(&line),
+                    BbsOutput::Download { name, text } => {
+                        client.send_line(&format!("[download {}, {} bytes]", name, text.len()));
+                        for line in text.lines() {
+                            client.send(&format!("{}\r\n", line));
+                        }
+                    },
+                    BbsOutput::Signal(name) => client.send_line(&format!("[signal {}]", name)),
+                    BbsOutput::Disconnect(reason) => {
+                        client.send_line(&format!("NO CARRIER ({:?})", reason));
+                        client.hanging_up = true;
+                        break;
+                    },
+                }
+            }
+
+            if client.hanging_up || client.closed { break }
+            client.send(&prompt);
+        }
+    }
+
+    server.clients.retain(|client| !client.is_finished());
+}
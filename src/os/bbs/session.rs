@@ -0,0 +1,176 @@
+use crate::ui::markup::escape_markup;
+
+use super::script::{Bbs, BbsAction, BbsMenu};
+
+//==============================================================================
+//         Bbs Output
+//==============================================================================
+
+/// Why a call ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DisconnectReason {
+    /// Nothing picked up.
+    NoAnswer,
+    /// The caller hung up.
+    LocalHangup,
+    /// The board said goodbye and hung up.
+    RemoteHangup,
+    /// The line went dead.
+    CarrierLost,
+}
+
+/// What a session sends back down the line.
+#[derive(Debug, Clone, PartialEq)]
+pub enum BbsOutput {
+    /// A line of markup.
+    Line(String),
+    Download { name : String, text : String },
+    Signal(String),
+    Disconnect(DisconnectReason),
+}
+
+//==============================================================================
+//         Bbs Session
+//==============================================================================
+
+/// Where a caller is in a [`Bbs`] script. It knows nothing about terminals or
+/// sockets, so the same session runs in game and on the test server.
+#[derive(Debug, Clone)]
+pub struct BbsSession {
+    menu : String,
+    /// The board being read, if the caller is picking messages.
+    board : Option<String>,
+}
+
+impl BbsSession {
+    pub fn connect(bbs : &Bbs) -> (Self, Vec<BbsOutput>) {
+        let mut session = Self { menu : bbs.start.clone(), board : None };
+        let mut output = bbs.welcome.iter().cloned().map(BbsOutput::Line).collect();
+        session.enter_menu(bbs, &bbs.start, &mut output);
+        (session, output)
+    }
+
+    pub fn prompt(&self, bbs : &Bbs) -> String {
+        if self.board.is_some() {
+            return "Message #? ".to_string();
+        }
+
+        match bbs.menus.get(&self.menu) {
+            Some(menu) => format!("{}> ", menu.title),
+            None => "> ".to_string(),
+        }
+    }
+
+    /// Handles a line typed by the caller.
+    pub fn input(&mut self, bbs : &Bbs, line : &str) -> Vec<BbsOutput> {
+        let mut output = Vec::new();
+        let line = line.trim();
+
+        if let Some(board) = self.board.clone() {
+            self.read_message(bbs, &board, line, &mut output);
+            return output;
+        }
+
+        let Some(menu) = bbs.menus.get(&self.menu) else {
+            output.push(BbsOutput::Disconnect(DisconnectReason::CarrierLost));
+            return output;
+        };
+
+        if line.is_empty() {
+            Self::show_menu(menu, &mut output);
+            return output;
+        }
+
+        match menu.options.iter().find(|option| option.key.eq_ignore_ascii_case(line)) {
+            Some(option) => self.run(bbs, &option.action, &mut output),
+            None => output.push(BbsOutput::Line(format!("Unknown option '{}'.", escape_markup(line)))),
+        }
+
+        output
+    }
+
+    fn run(&mut self, bbs : &Bbs, action : &BbsAction, output : &mut Vec<BbsOutput>) {
+        match action {
+            BbsAction::Menu(name) => self.enter_menu(bbs, name, output),
+            BbsAction::Text(lines) => output.extend(lines.iter().cloned().map(BbsOutput::Line)),
+            BbsAction::Board(name) => self.open_board(bbs, name, output),
+            BbsAction::Download { name, text } => output.push(BbsOutput::Download { name : name.clone(), text : text.clone() }),
+            BbsAction::Signal(name) => output.push(BbsOutput::Signal(name.clone())),
+            BbsAction::Hangup => output.push(BbsOutput::Disconnect(DisconnectReason::RemoteHangup)),
+            BbsAction::DropCarrier => output.push(BbsOutput::Disconnect(DisconnectReason::CarrierLost)),
+            BbsAction::Sequence(actions) => {
+                for action in actions {
+                    self.run(bbs, action, output);
+                    if matches!(output.last(), Some(BbsOutput::Disconnect(_))) { break }
+                }
+            },
+        }
+    }
+
+    fn enter_menu(&mut self, bbs : &Bbs, name : &str, output : &mut Vec<BbsOutput>) {
+        let Some(menu) = bbs.menus.get(name) else {
+            output.push(BbsOutput::Line(format!("[c=red]Menu '{}' is out of order.[/c]", escape_markup(name))));
+            return;
+        };
+
+        self.menu = name.to_string();
+        self.board = None;
+        Self::show_menu(menu, output);
+    }
+
+    fn show_menu(menu : &BbsMenu, output : &mut Vec<BbsOutput>) {
+        output.push(BbsOutput::Line(String::new()));
+        output.push(BbsOutput::Line(format!("[b]-- {} --[/b]", escape_markup(&menu.title))));
+        output.extend(menu.text.iter().cloned().map(BbsOutput::Line));
+        for option in menu.options.iter() {
+            output.push(BbsOutput::Line(format!(" [[{}] {}", escape_markup(&option.key), escape_markup(&option.label))));
+        }
+    }
+
+    fn open_board(&mut self, bbs : &Bbs, name : &str, output : &mut Vec<BbsOutput>) {
+        let Some(board) = bbs.boards.get(name) else {
+            output.push(BbsOutput::Line(format!("[c=red]Board '{}' is out of order.[/c]", escape_markup(name))));
+            return;
+        };
+
+        self.board = Some(name.to_string());
+        output.push(BbsOutput::Line(String::new()));
+        output.push(BbsOutput::Line(format!("[b]-- {} --[/b]", escape_markup(&board.title))));
+        if board.messages.is_empty() {
+            output.push(BbsOutput::Line("No messages.".to_string()));
+        }
+        for (index, message) in board.messages.iter().enumerate() {
+            output.push(BbsOutput::Line(format!(" {:>2}. {:<12} {}", index + 1, escape_markup(&message.from), escape_markup(&message.subject))));
+        }
+        output.push(BbsOutput::Line("Enter a message number, or nothing to go back.".to_string()));
+    }
+
+    fn read_message(&mut self, bbs : &Bbs, board : &str, line : &str, output : &mut Vec<BbsOutput>) {
+        let menu = self.menu.clone();
+        let Some(board) = bbs.boards.get(board) else {
+            return self.enter_menu(bbs, &menu, output);
+        };
+
+        if line.is_empty() {
+            return self.enter_menu(bbs, &menu, output);
+        }
+
+        let message = line.parse::<usize>().ok()
+            .and_then(|number| number.checked_sub(1))
+            .and_then(|index| board.messages.get(index));
+        let Some(message) = message else {
+            output.push(BbsOutput::Line(format!("No message '{}'.", escape_markup(line))));
+            return;
+        };
+
+        output.push(BbsOutput::Line(String::new()));
+        output.push(BbsOutput::Line(format!("From: {}", escape_markup(&message.from))));
+        output.push(BbsOutput::Line(format!("Subj: {}", escape_markup(&message.subject))));
+        output.push(BbsOutput::Line(String::new()));
+        output.extend(message.body.iter().cloned().map(BbsOutput::Line));
+
+        if let Some(action) = &message.then {
+            self.run(bbs, action, output);
+        }
+    }
+}
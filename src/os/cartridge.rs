@@ -0,0 +1,287 @@
+use bevy::{asset::{io::Reader, ron, AssetLoader, AsyncReadExt, LoadContext, LoadedFolder, ParseAssetPathError, ReadAssetBytesError}, prelude::*, utils::BoxedFuture};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{loading::LoadingTracker, scene::computer_world::{ActiveComputer, Computer, ComputerNode, ComputerPoweredOffEvent, ComputerState}, ui::{commands::{RegisterTerminalCommand, TerminalCommandInfo, TerminalCommandInput, TerminalCompletionInput, TerminalOutput}, parser::TerminalArgKind, terminal::TerminalComponent}};
+
+//==============================================================================
+//         Cartridge Plugin
+//==============================================================================
+
+pub struct CartridgePlugin;
+
+impl Plugin for CartridgePlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_asset::<Cartridge>()
+            .init_asset_loader::<CartridgeLoader>()
+
+            .add_systems(Startup, load_cartridges)
+            .add_systems(PreUpdate, interrupt_cartridge)
+            .add_systems(PostUpdate, (start_cartridge, exit_cartridge, stop_cartridge_on_power_off))
+
+            .add_event::<LoadCartridgeEvent>()
+            .add_event::<CartridgeStartedEvent>()
+            .add_event::<CartridgeExitEvent>()
+
+            .register_terminal_command(
+                TerminalCommandInfo::new("load", "loads program off of cartridge")
+                    .with_optional_arg("cartridge", TerminalArgKind::Text),
+                load_command
+            )
+            .register_terminal_completer("load", complete_cartridge_name)
+        ;
+    }
+}
+
+//==============================================================================
+//         Cartridge Asset
+//==============================================================================
+
+/// What runs when a cartridge is loaded.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CartridgeProgram {
+    /// Pages through the payload as text.
+    Viewer,
+    /// Plays a game registered with [`crate::ui::arcade::RegisterAsciiGame`].
+    Game(String),
+}
+
+#[derive(Asset, TypePath, Debug, Clone)]
+pub struct Cartridge {
+    pub name : String,
+    pub title : String,
+    pub program : CartridgeProgram,
+    pub payload : String,
+}
+
+/// The `.cart.ron` file. `payload` is a path relative to the manifest.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CartridgeManifest {
+    name : String,
+    title : String,
+    program : CartridgeProgram,
+    #[serde(default)]
+    payload : Option<String>,
+}
+
+#[derive(Debug, Error)]
+pub enum CartridgeLoaderError {
+    #[error("Could not read cartridge manifest: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Could not parse cartridge manifest: {0}")]
+    Ron(#[from] ron::error::SpannedError),
+    #[error("Invalid cartridge payload path: {0}")]
+    PayloadPath(#[from] ParseAssetPathError),
+    #[error("Could not read cartridge payload: {0}")]
+    Payload(#[from] ReadAssetBytesError),
+}
+
+#[derive(Default)]
+pub struct CartridgeLoader;
+
+impl AssetLoader for CartridgeLoader {
+    type Asset = Cartridge;
+    type Settings = ();
+    type Error = CartridgeLoaderError;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _settings: &'a Self::Settings,
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<Self::Asset, Self::Error>> {
+        Box::pin(async move {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).await?;
+            let manifest = ron::de::from_bytes::<CartridgeManifest>(&bytes)?;
+
+            let payload = match &manifest.payload {
+                Some(payload) => {
+                    let path = load_context.asset_path().resolve_embed(payload)?;
+                    let bytes = load_context.read_asset_bytes(path).await?;
+                    String::from_utf8_lossy(&bytes).into_owned()
+                },
+                None => String::new(),
+            };
+
+            Ok(Cartridge {
+                name : manifest.name,
+                title : manifest.title,
+                program : manifest.program,
+                payload,
+            })
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["cart.ron"]
+    }
+}
+
+//==============================================================================
+//         Cartridge Library
+//==============================================================================
+
+/// Every cartridge found in `assets/cartridges`.
+#[derive(Resource)]
+pub struct CartridgeLibrary {
+    pub folder : Handle<LoadedFolder>,
+}
+
+impl CartridgeLibrary {
+    pub fn iter<'a>(&self, folders : &'a Assets<LoadedFolder>, cartridges : &'a Assets<Cartridge>) -> impl Iterator<Item = (Handle<Cartridge>, &'a Cartridge)> {
+        folders.get(&self.folder).into_iter()
+            .flat_map(|folder| folder.handles.iter())
+            .filter_map(|handle| handle.clone().try_typed::<Cartridge>().ok())
+            .filter_map(|handle| cartridges.get(&handle).map(|cartridge| (handle.clone(), cartridge)))
+    }
+
+    pub fn find<'a>(&self, name : &str, folders : &'a Assets<LoadedFolder>, cartridges : &'a Assets<Cartridge>) -> Option<(Handle<Cartridge>, &'a Cartridge)> {
+        self.iter(folders, cartridges).find(|(_, cartridge)| cartridge.name.eq_ignore_ascii_case(name))
+    }
+}
+
+fn load_cartridges(
+    mut commands : Commands,
+    mut loading_tracker : ResMut<LoadingTracker>,
+    asset_server : Res<AssetServer>,
+) {
+    let folder = asset_server.load_folder("cartridges");
+    loading_tracker.push(folder.clone());
+    commands.insert_resource(CartridgeLibrary { folder });
+}
+
+//==============================================================================
+//         Cartridge Events
+//==============================================================================
+
+/// Asks a computer to stop the OS and run a cartridge.
+#[derive(Event, Debug, Clone)]
+pub struct LoadCartridgeEvent {
+    pub computer : Entity,
+    pub cartridge : Handle<Cartridge>,
+}
+
+/// Sent once the computer has switched to [`ComputerState::Game`]. Whatever
+/// runs `program` should take over that computer's screen when it sees this.
+#[derive(Event, Debug, Clone)]
+pub struct CartridgeStartedEvent {
+    pub computer : Entity,
+    pub cartridge : Handle<Cartridge>,
+    pub program : CartridgeProgram,
+}
+
+/// Sent by a cartridge program when it is done, returning the computer to the OS.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct CartridgeExitEvent(pub Entity);
+
+/// The cartridge a computer is running, if any. Sits on the computer entity.
+#[derive(Component, Debug, Clone)]
+pub struct RunningCartridge(pub Handle<Cartridge>);
+
+//==============================================================================
+//         Cartridge Systems
+//==============================================================================
+
+fn load_command(
+    In(input) : In<TerminalCommandInput>,
+    mut output : ResMut<TerminalOutput>,
+    mut load_events : EventWriter<LoadCartridgeEvent>,
+    terminals : Query<&ComputerNode, With<TerminalComponent>>,
+    library : Res<CartridgeLibrary>,
+    folders : Res<Assets<LoadedFolder>>,
+    cartridges : Res<Assets<Cartridge>>,
+) {
+    let Ok(node) = terminals.get(input.terminal) else { return };
+
+    let Some(name) = input.args.str(0) else {
+        output.add_line("Cartridges in drive:");
+        for (_, cartridge) in library.iter(&folders, &cartridges) {
+            output.add_line(&format!("  {} - {}", cartridge.name, cartridge.title));
+        }
+        return;
+    };
+
+    match library.find(name, &folders, &cartridges) {
+        Some((handle, cartridge)) => {
+            output.add_line(&format!("Loading {}...", cartridge.title));
+            load_events.send(LoadCartridgeEvent { computer : node.0, cartridge : handle });
+        },
+        None => output.add_error_line(&format!("No cartridge named '{}'", name)),
+    }
+}
+
+fn complete_cartridge_name(
+    In(input) : In<TerminalCompletionInput>,
+    library : Res<CartridgeLibrary>,
+    folders : Res<Assets<LoadedFolder>>,
+    cartridges : Res<Assets<Cartridge>>,
+) -> Vec<String> {
+    library.iter(&folders, &cartridges)
+        .map(|(_, cartridge)| cartridge.name.clone())
+        .filter(|name| name.starts_with(&input.prefix))
+        .collect()
+}
+
+fn start_cartridge(
+    mut commands : Commands,
+    mut load_events : EventReader<LoadCartridgeEvent>,
+    mut started_events : EventWriter<CartridgeStartedEvent>,
+    mut computers : Query<&mut Computer>,
+    cartridges : Res<Assets<Cartridge>>,
+) {
+    for LoadCartridgeEvent { computer : entity, cartridge : handle } in load_events.read() {
+        let Ok(mut computer) = computers.get_mut(*entity) else { continue };
+        if computer.state != ComputerState::OS { continue }
+        let Some(cartridge) = cartridges.get(handle) else { continue };
+
+        computer.state = ComputerState::Game;
+        commands.entity(*entity).insert(RunningCartridge(handle.clone()));
+        started_events.send(CartridgeStartedEvent {
+            computer : *entity,
+            cartridge : handle.clone(),
+            program : cartridge.program.clone(),
+        });
+    }
+}
+
+/// Ctrl+C always gets the player out of a cartridge, whatever the program does with its input.
+fn interrupt_cartridge(
+    mut exit_events : EventWriter<CartridgeExitEvent>,
+    computers : Query<&Computer>,
+    active_computer : Res<ActiveComputer>,
+    key_input : Res<ButtonInput<KeyCode>>,
+) {
+    let Some(entity) = active_computer.0 else { return };
+    let Ok(computer) = computers.get(entity) else { return };
+
+    let ctrl = key_input.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]);
+    if computer.state == ComputerState::Game && ctrl && key_input.just_pressed(KeyCode::KeyC) {
+        exit_events.send(CartridgeExitEvent(entity));
+    }
+}
+
+fn exit_cartridge(
+    mut commands : Commands,
+    mut exit_events : EventReader<CartridgeExitEvent>,
+    mut computers : Query<&mut Computer>,
+) {
+    for CartridgeExitEvent(entity) in exit_events.read() {
+        let Ok(mut computer) = computers.get_mut(*entity) else { continue };
+
+        if computer.state == ComputerState::Game {
+            computer.state = ComputerState::OS;
+            commands.entity(*entity).remove::<RunningCartridge>();
+        }
+    }
+}
+
+fn stop_cartridge_on_power_off(
+    mut commands : Commands,
+    mut powered_off_events : EventReader<ComputerPoweredOffEvent>,
+) {
+    for ComputerPoweredOffEvent(computer) in powered_off_events.read() {
+        commands.entity(*computer).remove::<RunningCartridge>();
+    }
+}
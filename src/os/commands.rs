@@ -0,0 +1,319 @@
+use bevy::prelude::*;
+
+use crate::ui::{commands::{RegisterTerminalCommand, TerminalCommandInfo, TerminalCommandInput, TerminalCompletionInput, TerminalOutput}, markup::strip_markup, parser::TerminalArgKind, terminal::TerminalComponent};
+
+use super::fs::{FsError, FsNode, VirtualFs, HOME_DIRECTORY};
+
+//==============================================================================
+//         Filesystem Commands Plugin
+//==============================================================================
+
+pub struct FsCommandsPlugin;
+
+impl Plugin for FsCommandsPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .register_terminal_command(
+                TerminalCommandInfo::new("ls", "list the files in a directory")
+                    .with_flag(Some('a'), "all")
+                    .with_flag(Some('l'), "long")
+                    .with_optional_arg("path", TerminalArgKind::Path),
+                ls_command
+            )
+            .register_terminal_command(
+                TerminalCommandInfo::new("cd", "change the working directory")
+                    .with_optional_arg("path", TerminalArgKind::Path),
+                cd_command
+            )
+            .register_terminal_command(TerminalCommandInfo::new("pwd", "print the working directory"), pwd_command)
+            .register_terminal_command(
+                TerminalCommandInfo::new("cat", "print the contents of files")
+                    .with_variadic_arg("file", TerminalArgKind::Path, false),
+                cat_command
+            )
+            .register_terminal_command(
+                TerminalCommandInfo::new("grep", "print the lines that contain a pattern")
+                    .with_flag(Some('i'), "ignore-case")
+                    .with_flag(Some('v'), "invert")
+                    .with_arg("pattern", TerminalArgKind::Text)
+                    .with_variadic_arg("file", TerminalArgKind::Path, false),
+                grep_command
+            )
+            .register_terminal_command(
+                TerminalCommandInfo::new("echo", "print a line of text")
+                    .with_variadic_arg("text", TerminalArgKind::Text, false),
+                echo_command
+            )
+            .register_terminal_command(
+                TerminalCommandInfo::new("rm", "remove files")
+                    .with_flag(Some('r'), "recursive")
+                    .with_variadic_arg("file", TerminalArgKind::Path, true),
+                rm_command
+            )
+            .register_terminal_command(
+                TerminalCommandInfo::new("mv", "move or rename a file")
+                    .with_arg("source", TerminalArgKind::Path)
+                    .with_arg("destination", TerminalArgKind::Path),
+                mv_command
+            )
+            .register_terminal_command(
+                TerminalCommandInfo::new("cp", "copy a file")
+                    .with_flag(Some('r'), "recursive")
+                    .with_arg("source", TerminalArgKind::Path)
+                    .with_arg("destination", TerminalArgKind::Path),
+                cp_command
+            )
+
+            .register_path_completer(complete_path)
+        ;
+    }
+}
+
+//==============================================================================
+//         Filesystem Commands
+//==============================================================================
+
+fn ls_command(
+    In(input) : In<TerminalCommandInput>,
+    mut output : ResMut<TerminalOutput>,
+    terminals : Query<&TerminalComponent>,
+    filesystem : Res<VirtualFs>,
+) {
+    let Ok(terminal) = terminals.get(input.terminal) else { return };
+
+    let path = VirtualFs::resolve(&terminal.working_directory, input.args.str(0).unwrap_or("."));
+    let show_all = input.args.has_flag("all");
+
+    let entries = match filesystem.lookup(&path, terminal.user()) {
+        Ok(FsNode::Directory { .. }) => match filesystem.list(&path, terminal.user()) {
+            Ok(entries) => entries,
+            Err(error) => {
+                output.add_error_line(&format!("ls: {}", error));
+                return;
+            }
+        },
+        Ok(_) => {
+            let name = VirtualFs::split(&path).map(|(_, name)| name).unwrap_or("/");
+            output.add_line(name);
+            return;
+        },
+        Err(FsError::NotFound(_)) => {
+            output.add_error_line(&format!("ls: {}: No such file or directory", path));
+            return;
+        },
+        Err(error) => {
+            output.add_error_line(&format!("ls: {}", error));
+            return;
+        }
+    };
+
+    let names = entries.into_iter()
+        .filter(|(name, node)| show_all || !node.is_hidden(name))
+        .map(|(name, node)| {
+            let suffix = match node {
+                FsNode::Directory { .. } => "/",
+                FsNode::Program { .. } => "*",
+                FsNode::File { .. } => "",
+            };
+
+            if input.args.has_flag("long") {
+                let permissions = node.meta().permissions;
+                format!(
+                    "{}{}{}{} {:<8} {:>6} {}{}",
+                    if node.is_dir() { 'd' } else { '-' },
+                    if permissions.read { 'r' } else { '-' },
+                    if permissions.write { 'w' } else { '-' },
+                    if permissions.execute { 'x' } else { '-' },
+                    node.meta().owner(),
+                    node.size(),
+                    name,
+                    suffix,
+                )
+            } else {
+                format!("{}{}", name, suffix)
+            }
+        })
+        .collect::<Vec<_>>();
+
+    if input.args.has_flag("long") {
+        for line in names {
+            output.add_line(&line);
+        }
+    } else if !names.is_empty() {
+        output.add_line(&names.join("  "));
+    }
+}
+
+fn cd_command(
+    In(input) : In<TerminalCommandInput>,
+    mut terminals : Query<&mut TerminalComponent>,
+    mut output : ResMut<TerminalOutput>,
+    filesystem : Res<VirtualFs>,
+) {
+    let Ok(mut terminal) = terminals.get_mut(input.terminal) else { return };
+
+    let path = VirtualFs::resolve(&terminal.working_directory, input.args.str(0).unwrap_or(HOME_DIRECTORY));
+    match filesystem.list(&path, terminal.user()) {
+        Ok(_) => terminal.working_directory = path,
+        Err(error) => output.add_error_line(&format!("cd: {}", error)),
+    }
+}
+
+fn pwd_command(
+    In(input) : In<TerminalCommandInput>,
+    mut output : ResMut<TerminalOutput>,
+    terminals : Query<&TerminalComponent>,
+) {
+    let Ok(terminal) = terminals.get(input.terminal) else { return };
+    output.add_line(&terminal.working_directory);
+}
+
+fn cat_command(
+    In(input) : In<TerminalCommandInput>,
+    mut output : ResMut<TerminalOutput>,
+    terminals : Query<&TerminalComponent>,
+    filesystem : Res<VirtualFs>,
+) {
+    let Ok(terminal) = terminals.get(input.terminal) else { return };
+
+    // With no files, pass along whatever was piped in.
+    if input.args.is_empty() {
+        for line in input.stdin.iter().flatten() {
+            output.add_markup_line(line);
+        }
+        return;
+    }
+
+    for file in input.args.rest(0) {
+        let path = VirtualFs::resolve(&terminal.working_directory, &file);
+        match (filesystem.lookup(&path, terminal.user()), filesystem.read(&path, terminal.user())) {
+            (Ok(FsNode::Program { program, .. }), _) => {
+                // Programs are binaries, so show the player what dumping one to the screen looks like.
+                let garbage = program.bytes().cycle().take(48).map(|byte| char::from(33 + byte % 90)).collect::<String>();
+                output.add_line(&garbage);
+            },
+            (_, Ok(text)) => {
+                // Files are written with terminal markup, so they can change color.
+                for line in text.lines() {
+                    output.add_markup_line(line);
+                }
+            },
+            (_, Err(error)) => output.add_error_line(&format!("cat: {}", error)),
+        }
+    }
+}
+
+/// Matches against the text as it reads on screen, but prints lines with their markup.
+fn grep_command(
+    In(input) : In<TerminalCommandInput>,
+    mut output : ResMut<TerminalOutput>,
+    terminals : Query<&TerminalComponent>,
+    filesystem : Res<VirtualFs>,
+) {
+    let Ok(terminal) = terminals.get(input.terminal) else { return };
+    let Some(pattern) = input.args.str(0) else { return };
+
+    let ignore_case = input.args.has_flag("ignore-case");
+    let invert = input.args.has_flag("invert");
+    let pattern = if ignore_case { pattern.to_lowercase() } else { pattern.to_string() };
+
+    let mut sources = Vec::new();
+    if input.args.len() == 1 {
+        sources.extend(input.stdin.iter().flatten().cloned());
+    }
+    for file in input.args.rest(1) {
+        let path = VirtualFs::resolve(&terminal.working_directory, &file);
+        match filesystem.read(&path, terminal.user()) {
+            Ok(text) => sources.extend(text.lines().map(|line| line.to_string())),
+            Err(error) => output.add_error_line(&format!("grep: {}", error)),
+        }
+    }
+
+    for line in sources {
+        let text = strip_markup(&line);
+        let text = if ignore_case { text.to_lowercase() } else { text };
+        if text.contains(&pattern) != invert {
+            output.add_markup_line(&line);
+        }
+    }
+}
+
+fn echo_command(
+    In(input) : In<TerminalCommandInput>,
+    mut output : ResMut<TerminalOutput>,
+) {
+    output.add_line(&input.args.rest(0).join(" "));
+}
+
+fn rm_command(
+    In(input) : In<TerminalCommandInput>,
+    mut output : ResMut<TerminalOutput>,
+    terminals : Query<&TerminalComponent>,
+    mut filesystem : ResMut<VirtualFs>,
+) {
+    let Ok(terminal) = terminals.get(input.terminal) else { return };
+
+    for file in input.args.rest(0) {
+        let path = VirtualFs::resolve(&terminal.working_directory, &file);
+        if let Err(error) = filesystem.remove(&path, input.args.has_flag("recursive"), terminal.user()) {
+            output.add_error_line(&format!("rm: {}", error));
+        }
+    }
+}
+
+fn mv_command(
+    In(input) : In<TerminalCommandInput>,
+    mut output : ResMut<TerminalOutput>,
+    terminals : Query<&TerminalComponent>,
+    mut filesystem : ResMut<VirtualFs>,
+) {
+    let Ok(terminal) = terminals.get(input.terminal) else { return };
+    let (Some(source), Some(destination)) = (input.args.str(0), input.args.str(1)) else { return };
+
+    let source = VirtualFs::resolve(&terminal.working_directory, source);
+    let destination = VirtualFs::resolve(&terminal.working_directory, destination);
+    if let Err(error) = filesystem.rename(&source, &destination, terminal.user()) {
+        output.add_error_line(&format!("mv: {}", error));
+    }
+}
+
+fn cp_command(
+    In(input) : In<TerminalCommandInput>,
+    mut output : ResMut<TerminalOutput>,
+    terminals : Query<&TerminalComponent>,
+    mut filesystem : ResMut<VirtualFs>,
+) {
+    let Ok(terminal) = terminals.get(input.terminal) else { return };
+    let (Some(source), Some(destination)) = (input.args.str(0), input.args.str(1)) else { return };
+
+    let source = VirtualFs::resolve(&terminal.working_directory, source);
+    let destination = VirtualFs::resolve(&terminal.working_directory, destination);
+    if let Err(error) = filesystem.copy(&source, &destination, input.args.has_flag("recursive"), terminal.user()) {
+        output.add_error_line(&format!("cp: {}", error));
+    }
+}
+
+//==============================================================================
+//         Path Completion
+//==============================================================================
+
+fn complete_path(
+    In(input) : In<TerminalCompletionInput>,
+    terminals : Query<&TerminalComponent>,
+    filesystem : Res<VirtualFs>,
+) -> Vec<String> {
+    let Ok(terminal) = terminals.get(input.terminal) else { return Vec::new() };
+
+    let (directory, name_prefix) = match input.prefix.rsplit_once('/') {
+        Some((directory, name)) => (format!("{}/", directory), name),
+        None => (String::new(), input.prefix.as_str()),
+    };
+
+    let path = VirtualFs::resolve(&terminal.working_directory, if directory.is_empty() { "." } else { &directory });
+    let Ok(entries) = filesystem.list(&path, terminal.user()) else { return Vec::new() };
+
+    entries.into_iter()
+        .filter(|(name, node)| name.starts_with(name_prefix) && (name_prefix.starts_with('.') || !node.is_hidden(name)))
+        .map(|(name, node)| format!("{}{}{}", directory, name, if node.is_dir() { "/" } else { "" }))
+        .collect()
+}
@@ -0,0 +1,489 @@
+use std::collections::BTreeMap;
+
+use bevy::{asset::{io::Reader, ron, AssetLoader, AsyncReadExt, LoadContext}, prelude::*, utils::BoxedFuture};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{game::GameState, loading::LoadingTracker};
+
+use super::users::ROOT_USER;
+
+//==============================================================================
+//         VirtualFs Plugin
+//==============================================================================
+
+pub struct VirtualFsPlugin;
+
+impl Plugin for VirtualFsPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_asset::<VirtualFsManifest>()
+            .init_asset_loader::<VirtualFsManifestLoader>()
+
+            .add_systems(Startup, load_filesystem)
+            .add_systems(OnEnter(GameState::PreparingScene), populate_filesystem)
+
+            .init_resource::<VirtualFs>()
+        ;
+    }
+}
+
+//==============================================================================
+//         Filesystem Nodes
+//==============================================================================
+
+/// What everyone but the owner is allowed to do with a file or directory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FsPermissions {
+    pub read : bool,
+    pub write : bool,
+    pub execute : bool,
+}
+
+impl Default for FsPermissions {
+    fn default() -> Self {
+        Self { read : true, write : true, execute : false }
+    }
+}
+
+/// Data shared by every kind of node.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FsMeta {
+    #[serde(default)]
+    pub permissions : FsPermissions,
+    /// Who owns the node, root if nobody is named.
+    #[serde(default)]
+    pub owner : Option<String>,
+    #[serde(default)]
+    pub hidden : bool,
+}
+
+impl FsMeta {
+    pub fn owner(&self) -> &str {
+        self.owner.as_deref().unwrap_or(ROOT_USER)
+    }
+
+    /// The owner and root can always read and write, whatever the permissions say.
+    fn is_privileged(&self, user : &str) -> bool {
+        user == ROOT_USER || self.owner() == user
+    }
+
+    pub fn can_read(&self, user : &str) -> bool {
+        self.permissions.read || self.is_privileged(user)
+    }
+
+    pub fn can_write(&self, user : &str) -> bool {
+        self.permissions.write || self.is_privileged(user)
+    }
+
+    /// Whether `user` can look inside a directory on the way to something else.
+    pub fn can_enter(&self, user : &str) -> bool {
+        self.permissions.read || self.permissions.execute || self.is_privileged(user)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FsNode {
+    Directory {
+        #[serde(default)]
+        entries : BTreeMap<String, FsNode>,
+        #[serde(default)]
+        meta : FsMeta,
+    },
+    File {
+        #[serde(default)]
+        text : String,
+        #[serde(default)]
+        meta : FsMeta,
+    },
+    /// An executable. `program` names what runs when it is executed.
+    Program {
+        program : String,
+        #[serde(default)]
+        meta : FsMeta,
+    },
+}
+
+impl Default for FsNode {
+    fn default() -> Self {
+        Self::directory()
+    }
+}
+
+impl FsNode {
+    pub fn directory() -> Self {
+        Self::Directory { entries : BTreeMap::new(), meta : FsMeta::default() }
+    }
+
+    pub fn file(text : impl Into<String>) -> Self {
+        Self::File { text : text.into(), meta : FsMeta::default() }
+    }
+
+    /// Hands the node over to `user`.
+    pub fn owned_by(mut self, user : &str) -> Self {
+        self.meta_mut().owner = Some(user.to_string());
+        self
+    }
+
+    pub fn meta(&self) -> &FsMeta {
+        match self {
+            Self::Directory { meta, .. } | Self::File { meta, .. } | Self::Program { meta, .. } => meta,
+        }
+    }
+
+    pub fn meta_mut(&mut self) -> &mut FsMeta {
+        match self {
+            Self::Directory { meta, .. } | Self::File { meta, .. } | Self::Program { meta, .. } => meta,
+        }
+    }
+
+    pub fn is_dir(&self) -> bool {
+        matches!(self, Self::Directory { .. })
+    }
+
+    /// Hidden nodes are either flagged as hidden or start with a dot.
+    pub fn is_hidden(&self, name : &str) -> bool {
+        self.meta().hidden || name.starts_with('.')
+    }
+
+    /// The size in bytes shown by `ls -l`.
+    pub fn size(&self) -> usize {
+        match self {
+            Self::Directory { entries, .. } => entries.len(),
+            Self::File { text, .. } => text.len(),
+            Self::Program { program, .. } => program.len() * 64,
+        }
+    }
+}
+
+//==============================================================================
+//         Filesystem Errors
+//==============================================================================
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum FsError {
+    #[error("{0}: No such file or directory")]
+    NotFound(String),
+    #[error("{0}: Not a directory")]
+    NotADirectory(String),
+    #[error("{0}: Is a directory")]
+    IsADirectory(String),
+    #[error("{0}: Permission denied")]
+    PermissionDenied(String),
+    #[error("{0}: File exists")]
+    AlreadyExists(String),
+    #[error("{0}: Directory not empty")]
+    DirectoryNotEmpty(String),
+    #[error("{0}: Invalid path")]
+    InvalidPath(String),
+}
+
+//==============================================================================
+//         VirtualFs
+//==============================================================================
+
+pub const HOME_DIRECTORY : &str = "/home";
+
+/// The files on the G64. Paths are unix style and always resolved to absolute
+/// paths with [`VirtualFs::resolve`] before they are looked up. Anything that
+/// touches a file's contents is done as a user, see [`FsMeta::can_read`].
+#[derive(Resource, Debug, Clone, Default)]
+pub struct VirtualFs {
+    root : FsNode,
+}
+
+impl VirtualFs {
+    pub fn new(root : FsNode) -> Self {
+        Self { root }
+    }
+
+    /// Joins `path` onto `cwd`, handling `.`, `..` and `~`, and returns a normalized absolute path.
+    pub fn resolve(cwd : &str, path : &str) -> String {
+        let joined = if let Some(rest) = path.strip_prefix('~') {
+            format!("{}/{}", HOME_DIRECTORY, rest)
+        } else if path.starts_with('/') {
+            path.to_string()
+        } else {
+            format!("{}/{}", cwd, path)
+        };
+
+        let mut parts : Vec<&str> = Vec::new();
+        for part in joined.split('/') {
+            match part {
+                "" | "." => {},
+                ".." => { parts.pop(); },
+                part => parts.push(part),
+            }
+        }
+
+        format!("/{}", parts.join("/"))
+    }
+
+    fn components(path : &str) -> impl Iterator<Item = &str> {
+        path.split('/').filter(|part| !part.is_empty())
+    }
+
+    /// Splits an absolute path into its parent directory and file name.
+    pub fn split(path : &str) -> Option<(&str, &str)> {
+        let (parent, name) = path.trim_end_matches('/').rsplit_once('/')?;
+        if name.is_empty() { return None }
+        Some((if parent.is_empty() { "/" } else { parent }, name))
+    }
+
+    pub fn get(&self, path : &str) -> Option<&FsNode> {
+        Self::components(path).try_fold(&self.root, |node, name| match node {
+            FsNode::Directory { entries, .. } => entries.get(name),
+            _ => None,
+        })
+    }
+
+    pub fn get_mut(&mut self, path : &str) -> Option<&mut FsNode> {
+        Self::components(path).try_fold(&mut self.root, |node, name| match node {
+            FsNode::Directory { entries, .. } => entries.get_mut(name),
+            _ => None,
+        })
+    }
+
+    /// Finds a node as `user` sees it. Every directory along the way has to let them in.
+    pub fn lookup(&self, path : &str, user : &str) -> Result<&FsNode, FsError> {
+        let mut node = &self.root;
+        let mut walked = String::from("/");
+        for name in Self::components(path) {
+            node = match node {
+                FsNode::Directory { entries, meta } => {
+                    if !meta.can_enter(user) { return Err(FsError::PermissionDenied(walked)) }
+                    entries.get(name).ok_or_else(|| FsError::NotFound(path.to_string()))?
+                },
+                _ => return Err(FsError::NotFound(path.to_string())),
+            };
+            walked = Self::resolve(&walked, name);
+        }
+        Ok(node)
+    }
+
+    fn lookup_mut(&mut self, path : &str, user : &str) -> Result<&mut FsNode, FsError> {
+        self.lookup(path, user)?;
+        self.get_mut(path).ok_or_else(|| FsError::NotFound(path.to_string()))
+    }
+
+    pub fn exists(&self, path : &str) -> bool {
+        self.get(path).is_some()
+    }
+
+    pub fn is_dir(&self, path : &str) -> bool {
+        self.get(path).map(FsNode::is_dir).unwrap_or(false)
+    }
+
+    fn entries_mut(&mut self, path : &str, user : &str) -> Result<&mut BTreeMap<String, FsNode>, FsError> {
+        match self.lookup_mut(path, user)? {
+            FsNode::Directory { entries, meta } => {
+                if !meta.can_write(user) { return Err(FsError::PermissionDenied(path.to_string())) }
+                Ok(entries)
+            },
+            _ => Err(FsError::NotADirectory(path.to_string())),
+        }
+    }
+
+    /// Lists a directory in name order.
+    pub fn list(&self, path : &str, user : &str) -> Result<Vec<(&String, &FsNode)>, FsError> {
+        match self.lookup(path, user)? {
+            FsNode::Directory { entries, meta } => {
+                if !meta.can_read(user) { return Err(FsError::PermissionDenied(path.to_string())) }
+                Ok(entries.iter().collect())
+            },
+            _ => Err(FsError::NotADirectory(path.to_string())),
+        }
+    }
+
+    pub fn read(&self, path : &str, user : &str) -> Result<&str, FsError> {
+        match self.lookup(path, user)? {
+            FsNode::File { text, meta } => {
+                if !meta.can_read(user) { return Err(FsError::PermissionDenied(path.to_string())) }
+                Ok(text)
+            },
+            FsNode::Directory { .. } => Err(FsError::IsADirectory(path.to_string())),
+            FsNode::Program { .. } => Err(FsError::PermissionDenied(path.to_string())),
+        }
+    }
+
+    /// Creates or replaces the text of a file. New files belong to `user`.
+    pub fn write(&mut self, path : &str, contents : &str, user : &str) -> Result<(), FsError> {
+        match self.lookup_mut(path, user) {
+            Ok(FsNode::File { text, meta }) => {
+                if !meta.can_write(user) { return Err(FsError::PermissionDenied(path.to_string())) }
+                *text = contents.to_string();
+                Ok(())
+            },
+            Ok(FsNode::Directory { .. }) => Err(FsError::IsADirectory(path.to_string())),
+            Ok(FsNode::Program { .. }) => Err(FsError::PermissionDenied(path.to_string())),
+            Err(FsError::NotFound(_)) => self.insert(path, FsNode::file(contents).owned_by(user), user),
+            Err(error) => Err(error),
+        }
+    }
+
+    pub fn append(&mut self, path : &str, contents : &str, user : &str) -> Result<(), FsError> {
+        let existing = match self.read(path, user) {
+            Ok(text) => text.to_string(),
+            Err(FsError::NotFound(_)) => String::new(),
+            Err(error) => return Err(error),
+        };
+        self.write(path, &format!("{}{}", existing, contents), user)
+    }
+
+    /// Adds a new node. Fails if something already exists at `path`.
+    pub fn insert(&mut self, path : &str, node : FsNode, user : &str) -> Result<(), FsError> {
+        let Some((parent, name)) = Self::split(path) else { return Err(FsError::InvalidPath(path.to_string())) };
+        let entries = self.entries_mut(parent, user)?;
+        if entries.contains_key(name) {
+            return Err(FsError::AlreadyExists(path.to_string()));
+        }
+        entries.insert(name.to_string(), node);
+        Ok(())
+    }
+
+    pub fn remove(&mut self, path : &str, recursive : bool, user : &str) -> Result<FsNode, FsError> {
+        let Some((parent, name)) = Self::split(path) else { return Err(FsError::PermissionDenied(path.to_string())) };
+        let entries = self.entries_mut(parent, user)?;
+        match entries.get(name) {
+            None => return Err(FsError::NotFound(path.to_string())),
+            Some(node) if !node.meta().can_write(user) => return Err(FsError::PermissionDenied(path.to_string())),
+            Some(FsNode::Directory { entries : children, .. }) if !recursive => {
+                return Err(if children.is_empty() { FsError::IsADirectory(path.to_string()) } else { FsError::DirectoryNotEmpty(path.to_string()) });
+            },
+            Some(_) => {},
+        }
+        entries.remove(name).ok_or_else(|| FsError::NotFound(path.to_string()))
+    }
+
+    /// Where `source` ends up when copied or moved to `destination`. Copying into a directory keeps the name.
+    fn target_path(&self, source : &str, destination : &str) -> String {
+        match (self.is_dir(destination), Self::split(source)) {
+            (true, Some((_, name))) => Self::resolve(destination, name),
+            _ => destination.to_string(),
+        }
+    }
+
+    /// Copies a node. The copy belongs to `user`.
+    pub fn copy(&mut self, source : &str, destination : &str, recursive : bool, user : &str) -> Result<(), FsError> {
+        let node = self.lookup(source, user)?;
+        if node.is_dir() && !recursive {
+            return Err(FsError::IsADirectory(source.to_string()));
+        }
+        if !node.meta().can_read(user) {
+            return Err(FsError::PermissionDenied(source.to_string()));
+        }
+
+        let node = node.clone().owned_by(user);
+        let target = self.target_path(source, destination);
+        if target == source || target.starts_with(&format!("{}/", source)) {
+            return Err(FsError::InvalidPath(target));
+        }
+        if self.get(&target).map(FsNode::is_dir).unwrap_or(false) {
+            return Err(FsError::IsADirectory(target));
+        }
+        if self.exists(&target) {
+            self.remove(&target, false, user)?;
+        }
+        self.insert(&target, node, user)
+    }
+
+    /// Moves a node. A file already at the destination is replaced, like `mv` does.
+    pub fn rename(&mut self, source : &str, destination : &str, user : &str) -> Result<(), FsError> {
+        let target = self.target_path(source, destination);
+        if target == source { return Ok(()) }
+        if target.starts_with(&format!("{}/", source)) {
+            return Err(FsError::InvalidPath(target));
+        }
+        match self.get(&target) {
+            Some(existing) if existing.is_dir() => return Err(FsError::AlreadyExists(target)),
+            Some(_) if self.is_dir(source) => return Err(FsError::NotADirectory(target)),
+            _ => {},
+        }
+
+        let node = self.remove(source, true, user)?;
+        // Put the source back as it was if the target can't be replaced.
+        if self.exists(&target) {
+            if let Err(error) = self.remove(&target, false, user) {
+                self.insert(source, node, ROOT_USER)?;
+                return Err(error);
+            }
+        }
+        if let Err(error) = self.insert(&target, node.clone(), user) {
+            self.insert(source, node, ROOT_USER)?;
+            return Err(error);
+        }
+        Ok(())
+    }
+}
+
+//==============================================================================
+//         Filesystem Manifest
+//==============================================================================
+
+/// The initial contents of the filesystem, loaded from a `.fs.ron` file.
+#[derive(Asset, TypePath, Debug, Clone, Serialize, Deserialize)]
+pub struct VirtualFsManifest {
+    pub root : FsNode,
+}
+
+#[derive(Debug, Error)]
+pub enum VirtualFsManifestError {
+    #[error("Could not read filesystem manifest: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Could not parse filesystem manifest: {0}")]
+    Ron(#[from] ron::error::SpannedError),
+}
+
+#[derive(Default)]
+pub struct VirtualFsManifestLoader;
+
+impl AssetLoader for VirtualFsManifestLoader {
+    type Asset = VirtualFsManifest;
+    type Settings = ();
+    type Error = VirtualFsManifestError;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _settings: &'a Self::Settings,
+        _load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<Self::Asset, Self::Error>> {
+        Box::pin(async move {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).await?;
+            Ok(ron::de::from_bytes::<VirtualFsManifest>(&bytes)?)
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["fs.ron"]
+    }
+}
+
+//==============================================================================
+//         Filesystem Loading
+//==============================================================================
+
+#[derive(Resource)]
+pub struct VirtualFsAssets {
+    pub manifest : Handle<VirtualFsManifest>,
+}
+
+fn load_filesystem(
+    mut commands : Commands,
+    mut loading_tracker : ResMut<LoadingTracker>,
+    asset_server : Res<AssetServer>,
+) {
+    let manifest = asset_server.load("os/g64.fs.ron");
+    loading_tracker.push(manifest.clone());
+    commands.insert_resource(VirtualFsAssets { manifest });
+}
+
+fn populate_filesystem(
+    mut filesystem : ResMut<VirtualFs>,
+    fs_assets : Res<VirtualFsAssets>,
+    manifests : Res<Assets<VirtualFsManifest>>,
+) {
+    let Some(manifest) = manifests.get(&fs_assets.manifest) else { return };
+    *filesystem = VirtualFs::new(manifest.root.clone());
+}
@@ -0,0 +1,29 @@
+pub mod fs;
+pub mod commands;
+pub mod cartridge;
+pub mod basic;
+pub mod bbs;
+pub mod users;
+
+use bevy::prelude::*;
+
+use self::{basic::BasicPlugin, bbs::BbsPlugin, cartridge::CartridgePlugin, commands::FsCommandsPlugin, fs::VirtualFsPlugin, users::UsersPlugin};
+
+//==============================================================================
+//         Os Plugin
+//==============================================================================
+
+pub struct OsPlugin;
+
+impl Plugin for OsPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_plugins(VirtualFsPlugin)
+            .add_plugins(UsersPlugin)
+            .add_plugins(FsCommandsPlugin)
+            .add_plugins(CartridgePlugin)
+            .add_plugins(BasicPlugin)
+            .add_plugins(BbsPlugin)
+        ;
+    }
+}
@@ -0,0 +1,430 @@
+use bevy::{asset::{io::Reader, ron, AssetLoader, AsyncReadExt, LoadContext}, prelude::*, utils::BoxedFuture};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{game::GameState, loading::LoadingTracker, scene::computer_world::{Computer, ComputerNode, ComputerPoweredOffEvent, ComputerState}, ui::{commands::{RegisterTerminalCommand, TerminalCommandInfo, TerminalCommandInput, TerminalOutput}, parser::TerminalArgKind, terminal::{TerminalComponent, TerminalForeground, TerminalInterruptEvent, TerminalProgramInputEvent, DEFAULT_PROMPT}, typewriter::TerminalTypewriter}};
+
+use super::fs::{VirtualFs, HOME_DIRECTORY};
+
+/// The user every permission check lets through.
+pub const ROOT_USER : &str = "root";
+
+/// Who a terminal runs as while nobody is logged in.
+pub const NOBODY_USER : &str = "nobody";
+
+/// How long a wrong password keeps the terminal busy, to slow down guessing.
+const LOGIN_FAILURE_DELAY : f32 = 2.0;
+
+const LOGIN_PROMPT : &str = "login: ";
+const PASSWORD_PROMPT : &str = "Password: ";
+
+//==============================================================================
+//         Users Plugin
+//==============================================================================
+
+pub struct UsersPlugin;
+
+impl Plugin for UsersPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_asset::<UserManifest>()
+            .init_asset_loader::<UserManifestLoader>()
+
+            .add_systems(Startup, load_users)
+            .add_systems(OnEnter(GameState::PreparingScene), populate_users)
+            .add_systems(Update, (start_login, login_input, interrupt_login).chain())
+            .add_systems(PostUpdate, drop_login_on_power_off)
+
+            .add_event::<UserLoginEvent>()
+            .add_event::<UserLoginFailedEvent>()
+
+            .init_resource::<UserDatabase>()
+
+            .register_terminal_command(TerminalCommandInfo::new("whoami", "print the current user"), whoami_command)
+            .register_terminal_command(
+                TerminalCommandInfo::new("su", "become another user")
+                    .with_optional_arg("user", TerminalArgKind::Text),
+                su_command
+            )
+            .register_terminal_command(TerminalCommandInfo::new("logout", "log out of the current user"), logout_command)
+        ;
+    }
+}
+
+//==============================================================================
+//         User Manifest
+//==============================================================================
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UserAccount {
+    pub name : String,
+    /// Empty for accounts that don't need one.
+    #[serde(default)]
+    pub password : String,
+}
+
+/// The accounts on the G64, loaded from a `.users.ron` file.
+#[derive(Asset, TypePath, Debug, Clone, Serialize, Deserialize)]
+pub struct UserManifest {
+    /// Whether terminals ask who is there after booting. Without a login
+    /// everyone is `default_user`.
+    #[serde(default)]
+    pub login : bool,
+    pub default_user : String,
+    /// Shown above the login prompt, like `/etc/issue`.
+    #[serde(default)]
+    pub issue : Vec<String>,
+    pub users : Vec<UserAccount>,
+}
+
+#[derive(Debug, Error)]
+pub enum UserManifestError {
+    #[error("Could not read user manifest: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Could not parse user manifest: {0}")]
+    Ron(#[from] ron::error::SpannedError),
+}
+
+#[derive(Default)]
+pub struct UserManifestLoader;
+
+impl AssetLoader for UserManifestLoader {
+    type Asset = UserManifest;
+    type Settings = ();
+    type Error = UserManifestError;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _settings: &'a Self::Settings,
+        _load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<Self::Asset, Self::Error>> {
+        Box::pin(async move {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).await?;
+            Ok(ron::de::from_bytes::<UserManifest>(&bytes)?)
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["users.ron"]
+    }
+}
+
+//==============================================================================
+//         User Database
+//==============================================================================
+
+#[derive(Resource, Debug, Clone)]
+pub struct UserDatabase {
+    pub login : bool,
+    pub default_user : String,
+    pub issue : Vec<String>,
+    users : Vec<UserAccount>,
+}
+
+impl Default for UserDatabase {
+    fn default() -> Self {
+        Self {
+            login : false,
+            default_user : ROOT_USER.to_string(),
+            issue : Vec::new(),
+            users : Vec::new(),
+        }
+    }
+}
+
+impl UserDatabase {
+    pub fn get(&self, name : &str) -> Option<&UserAccount> {
+        self.users.iter().find(|user| user.name == name)
+    }
+
+    pub fn exists(&self, name : &str) -> bool {
+        self.get(name).is_some()
+    }
+
+    /// Whether `name` can get in without being asked for a password.
+    pub fn is_open(&self, name : &str) -> bool {
+        self.get(name).is_some_and(|user| user.password.is_empty())
+    }
+
+    pub fn check_password(&self, name : &str, password : &str) -> bool {
+        self.get(name).is_some_and(|user| user.password == password)
+    }
+}
+
+#[derive(Resource)]
+pub struct UserAssets {
+    pub manifest : Handle<UserManifest>,
+}
+
+fn load_users(
+    mut commands : Commands,
+    mut loading_tracker : ResMut<LoadingTracker>,
+    asset_server : Res<AssetServer>,
+) {
+    let manifest = asset_server.load("os/g64.users.ron");
+    loading_tracker.push(manifest.clone());
+    commands.insert_resource(UserAssets { manifest });
+}
+
+fn populate_users(
+    mut users : ResMut<UserDatabase>,
+    user_assets : Res<UserAssets>,
+    manifests : Res<Assets<UserManifest>>,
+) {
+    let Some(manifest) = manifests.get(&user_assets.manifest) else { return };
+    *users = UserDatabase {
+        login : manifest.login,
+        default_user : manifest.default_user.clone(),
+        issue : manifest.issue.clone(),
+        users : manifest.users.clone(),
+    };
+}
+
+//==============================================================================
+//         Login Events
+//==============================================================================
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LoginKind {
+    /// Logging in at the `login:` prompt.
+    Login,
+    /// Switching user with `su`.
+    Su,
+}
+
+#[derive(Event, Debug, Clone)]
+pub struct UserLoginEvent {
+    pub terminal : Entity,
+    pub user : String,
+    pub kind : LoginKind,
+}
+
+/// Sent for every wrong password, so the room can react to someone guessing.
+#[derive(Event, Debug, Clone)]
+pub struct UserLoginFailedEvent {
+    pub terminal : Entity,
+    pub user : String,
+    pub kind : LoginKind,
+    /// Failures in a row at this prompt, counting this one.
+    pub attempts : u32,
+}
+
+//==============================================================================
+//         Login Prompt
+//==============================================================================
+
+/// Asks for a user name and password, in place of the shell.
+#[derive(Component, Debug, Clone)]
+pub struct LoginPrompt {
+    kind : LoginKind,
+    /// `None` while asking who is logging in.
+    user : Option<String>,
+    attempts : u32,
+}
+
+fn start_login(
+    mut commands : Commands,
+    mut terminals : Query<(Entity, &mut TerminalComponent, &mut TerminalTypewriter, &ComputerNode), Without<TerminalForeground>>,
+    computers : Query<&Computer>,
+    users : Res<UserDatabase>,
+) {
+    for (entity, mut terminal, mut typewriter, node) in terminals.iter_mut() {
+        if !terminal.users.is_empty() { continue }
+        let Ok(computer) = computers.get(node.0) else { continue };
+        if computer.state != ComputerState::OS { continue }
+
+        if !users.login {
+            terminal.users.push(users.default_user.clone());
+            continue;
+        }
+
+        for line in users.issue.iter() {
+            typewriter.write_markup_line(line);
+        }
+        terminal.prompt = LOGIN_PROMPT.to_string();
+        commands.entity(entity).insert((
+            LoginPrompt { kind : LoginKind::Login, user : None, attempts : 0 },
+            TerminalForeground { program : "login".to_string() },
+        ));
+    }
+}
+
+fn login_input(
+    mut commands : Commands,
+    mut prompts : Query<(&mut LoginPrompt, &mut TerminalComponent, &mut TerminalTypewriter)>,
+    mut input_events : EventReader<TerminalProgramInputEvent>,
+    mut login_events : EventWriter<UserLoginEvent>,
+    mut failed_events : EventWriter<UserLoginFailedEvent>,
+    users : Res<UserDatabase>,
+    filesystem : Res<VirtualFs>,
+) {
+    for event in input_events.read() {
+        let Ok((mut prompt, mut terminal, mut typewriter)) = prompts.get_mut(event.terminal) else { continue };
+
+        let Some(name) = prompt.user.clone() else {
+            let name = event.line.trim();
+            if name.is_empty() { continue }
+
+            prompt.user = Some(name.to_string());
+            if users.is_open(name) {
+                log_in(&mut commands, event.terminal, &mut terminal, &mut typewriter, name, prompt.kind, &filesystem, &mut login_events);
+            } else {
+                // Unknown names are asked for a password too, so guessing them gives nothing away.
+                terminal.prompt = PASSWORD_PROMPT.to_string();
+                terminal.masked = true;
+            }
+            continue;
+        };
+
+        terminal.masked = false;
+        if users.check_password(&name, &event.line) {
+            log_in(&mut commands, event.terminal, &mut terminal, &mut typewriter, &name, prompt.kind, &filesystem, &mut login_events);
+            continue;
+        }
+
+        prompt.attempts += 1;
+        failed_events.send(UserLoginFailedEvent { terminal : event.terminal, user : name, kind : prompt.kind, attempts : prompt.attempts });
+        typewriter.pause(LOGIN_FAILURE_DELAY);
+
+        match prompt.kind {
+            LoginKind::Login => {
+                typewriter.write_line("Login incorrect");
+                prompt.user = None;
+                terminal.prompt = LOGIN_PROMPT.to_string();
+            },
+            LoginKind::Su => {
+                typewriter.write_line("su: Authentication failure");
+                end_login(&mut commands, event.terminal, &mut terminal);
+            },
+        }
+    }
+}
+
+/// Ctrl+C starts a login over, or gives up on `su`.
+fn interrupt_login(
+    mut commands : Commands,
+    mut prompts : Query<(&mut LoginPrompt, &mut TerminalComponent)>,
+    mut interrupt_events : EventReader<TerminalInterruptEvent>,
+) {
+    for event in interrupt_events.read() {
+        let Ok((mut prompt, mut terminal)) = prompts.get_mut(event.terminal) else { continue };
+
+        terminal.add_line("^C");
+        terminal.masked = false;
+        match prompt.kind {
+            LoginKind::Login => {
+                prompt.user = None;
+                terminal.prompt = LOGIN_PROMPT.to_string();
+            },
+            LoginKind::Su => end_login(&mut commands, event.terminal, &mut terminal),
+        }
+    }
+}
+
+fn drop_login_on_power_off(
+    mut commands : Commands,
+    prompts : Query<(Entity, &ComputerNode), With<LoginPrompt>>,
+    mut powered_off_events : EventReader<ComputerPoweredOffEvent>,
+) {
+    for ComputerPoweredOffEvent(computer) in powered_off_events.read() {
+        for (entity, node) in prompts.iter() {
+            if node.0 != *computer { continue }
+            commands.entity(entity).remove::<LoginPrompt>();
+        }
+    }
+}
+
+fn log_in(
+    commands : &mut Commands,
+    terminal_entity : Entity,
+    terminal : &mut TerminalComponent,
+    typewriter : &mut TerminalTypewriter,
+    name : &str,
+    kind : LoginKind,
+    filesystem : &VirtualFs,
+    login_events : &mut EventWriter<UserLoginEvent>,
+) {
+    match kind {
+        LoginKind::Login => {
+            terminal.users = vec![name.to_string()];
+            terminal.working_directory = HOME_DIRECTORY.to_string();
+            if let Ok(motd) = filesystem.read("/etc/motd", name) {
+                for line in motd.lines() {
+                    typewriter.write_markup_line(line);
+                }
+            }
+        },
+        LoginKind::Su => terminal.users.push(name.to_string()),
+    }
+
+    end_login(commands, terminal_entity, terminal);
+    login_events.send(UserLoginEvent { terminal : terminal_entity, user : name.to_string(), kind });
+}
+
+fn end_login(commands : &mut Commands, terminal_entity : Entity, terminal : &mut TerminalComponent) {
+    terminal.prompt = DEFAULT_PROMPT.to_string();
+    terminal.masked = false;
+    commands.entity(terminal_entity).remove::<(LoginPrompt, TerminalForeground)>();
+}
+
+//==============================================================================
+//         User Commands
+//==============================================================================
+
+fn whoami_command(
+    In(input) : In<TerminalCommandInput>,
+    mut output : ResMut<TerminalOutput>,
+    terminals : Query<&TerminalComponent>,
+) {
+    let Ok(terminal) = terminals.get(input.terminal) else { return };
+    output.add_line(terminal.user());
+}
+
+fn su_command(
+    In(input) : In<TerminalCommandInput>,
+    mut commands : Commands,
+    mut terminals : Query<&mut TerminalComponent>,
+    mut output : ResMut<TerminalOutput>,
+    mut login_events : EventWriter<UserLoginEvent>,
+    users : Res<UserDatabase>,
+) {
+    let Ok(mut terminal) = terminals.get_mut(input.terminal) else { return };
+    let name = input.args.str(0).unwrap_or(ROOT_USER);
+
+    if !users.exists(name) {
+        return output.add_error_line(&format!("su: user {} does not exist", name));
+    }
+
+    // Root can become anyone without being asked.
+    if terminal.user() == ROOT_USER || users.is_open(name) {
+        terminal.users.push(name.to_string());
+        login_events.send(UserLoginEvent { terminal : input.terminal, user : name.to_string(), kind : LoginKind::Su });
+        return;
+    }
+
+    terminal.prompt = PASSWORD_PROMPT.to_string();
+    terminal.masked = true;
+    commands.entity(input.terminal).insert((
+        LoginPrompt { kind : LoginKind::Su, user : Some(name.to_string()), attempts : 0 },
+        TerminalForeground { program : "su".to_string() },
+    ));
+}
+
+/// Drops back to whoever ran `su`, or logs out altogether.
+fn logout_command(
+    In(input) : In<TerminalCommandInput>,
+    mut terminals : Query<&mut TerminalComponent>,
+    mut output : ResMut<TerminalOutput>,
+) {
+    let Ok(mut terminal) = terminals.get_mut(input.terminal) else { return };
+
+    terminal.users.pop();
+    if terminal.users.is_empty() {
+        terminal.scrollback.clear();
+        terminal.working_directory = HOME_DIRECTORY.to_string();
+    } else {
+        output.add_line("logout");
+    }
+}
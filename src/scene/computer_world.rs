@@ -3,7 +3,7 @@ use std::default;
 use bevy::{pbr::CascadeShadowConfigBuilder, prelude::*, render::{camera::RenderTarget, mesh::shape::Cube, render_resource::{Extent3d, TextureDescriptor, TextureDimension, TextureFormat, TextureUsages}, view::RenderLayers}, window::WindowRef};
 use bevy_ascii::prelude::*;
 
-use crate::{game::{ActiveCamera, GameState, GameWorld, OnGameWorldChangeEvent}, ui::{boot_screen::LoadingScreenComponent, terminal::TerminalComponent}};
+use crate::{game::{ActiveCamera, CurrentGameWorld, GameState, GameWorld, OnGameWorldChangeEvent}, util::DelayedEventPlugin, ui::{arcade::GameScreenComponent, boot_screen::{LoadingScreenComponent, ShutdownScreenComponent}, boot_sequence::BootSequence, cartridge::CartridgeViewerComponent, director::TerminalGhost, editor::EditorComponent, terminal::{FocusedTerminal, TerminalComponent}, typewriter::TerminalTypewriter}};
 
 use super::{RoomCamera};
 
@@ -17,10 +17,15 @@ impl Plugin for ComputerWorldPlugin {
     fn build(&self, app: &mut App) {
         app
             .add_plugins(AsciiShaderPlugin)
+            .add_plugins(DelayedEventPlugin::<ComputerPowerEvent>::default())
         
-            .add_systems(Startup, init_computer_world)
+            .add_systems(Update, (on_enter_computer_world, power_button))
+            .add_systems(PostUpdate, update_screen_glow)
         
-            .init_resource::<ComputerState>()
+            .init_resource::<ActiveComputer>()
+            
+            .add_event::<ComputerPowerEvent>()
+            .add_event::<ComputerPoweredOffEvent>()
         
             // .add_event::<SwitchToComputerWorld>()
         ;
@@ -34,38 +39,116 @@ impl Plugin for ComputerWorldPlugin {
 #[derive(Debug, Component)]
 pub struct ComputerCamera;
 
+/// Marks an ascii node drawn on a computer's screen, pointing back at the computer.
+#[derive(Debug, Component, Clone, Copy, PartialEq, Eq)]
+pub struct ComputerNode(pub Entity);
+
+//==============================================================================
+//         Computer
+//==============================================================================
+
+/// A computer in the room. It lives on the ascii camera that draws its screen,
+/// and every screen it shows is a child node marked with [`ComputerNode`].
+#[derive(Debug, Component)]
+pub struct Computer {
+    /// The name of the glTF node its screen was found on.
+    pub name : String,
+    pub state : ComputerState,
+    pub render_surface_mat : Handle<StandardMaterial>,
+    pub render_surface_image : Handle<Image>,
+    /// What it runs the next time it powers on.
+    pub boot_sequence : Handle<BootSequence>,
+}
+
+/// The computer the player is sitting at, or last sat at. Only it gets keyboard input.
+#[derive(Resource, Debug, Default, Clone, Copy)]
+pub struct ActiveComputer(pub Option<Entity>);
+
+impl ActiveComputer {
+    pub fn is(&self, computer : Entity) -> bool {
+        self.0 == Some(computer)
+    }
+}
+
 //==============================================================================
 //         ComputerState
 //==============================================================================
 
-#[derive(Resource, Default, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub enum ComputerState {
     #[default]
     Off,
+    Booting,
     OS,
     Game,
+    ShuttingDown,
+}
+
+impl ComputerState {
+    /// Whether the screen has power, including while booting and shutting down.
+    pub fn is_powered(&self) -> bool {
+        *self != Self::Off
+    }
 }
 
 //==============================================================================
-//         Load ComputerWorld
+//         Computer Power
 //==============================================================================
 
-#[derive(Resource)]
-pub struct ComputerWorldAssets {
-    pub render_surface_mat : Handle<StandardMaterial>,
-    pub render_surface_image : Handle<Image>,
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PowerAction {
+    PowerOn,
+    /// A clean shutdown with the screen collapsing and the fans winding down.
+    Shutdown,
+    /// The power is cut at once. Nothing gets a chance to clean up.
+    PowerLoss,
+}
+
+/// Turns a computer on or off. The game can send these (or a delayed one)
+/// at any time, e.g. a lightning strike sends [`PowerAction::PowerLoss`].
+#[derive(Event, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ComputerPowerEvent {
+    pub computer : Entity,
+    pub action : PowerAction,
+}
+
+impl ComputerPowerEvent {
+    pub fn power_on(computer : Entity) -> Self {
+        Self { computer, action : PowerAction::PowerOn }
+    }
+    
+    pub fn shutdown(computer : Entity) -> Self {
+        Self { computer, action : PowerAction::Shutdown }
+    }
+    
+    pub fn power_loss(computer : Entity) -> Self {
+        Self { computer, action : PowerAction::PowerLoss }
+    }
 }
 
+/// Sent once a computer is completely off. Anything that was running on it is
+/// gone and should reset itself.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct ComputerPoweredOffEvent(pub Entity);
+
+/// How strongly a powered screen glows with whatever is drawn on it.
+pub const SCREEN_GLOW : Color = Color::WHITE;
+
 //==============================================================================
-//         Init ComputerWorld
+//         Spawn Computer
 //==============================================================================
 
-fn init_computer_world(
-    mut commands : Commands,
-    mut images : ResMut<Assets<Image>>,
-    mut materials : ResMut<Assets<StandardMaterial>>,
-    mut meshes : ResMut<Assets<Mesh>>,
-) {
+/// Spawns a computer with its own render surface, drawn on `render_layer` so
+/// its screens never show up on another computer. Returns the computer entity
+/// and the material to put on the screen mesh.
+pub fn spawn_computer(
+    commands : &mut Commands,
+    images : &mut Assets<Image>,
+    materials : &mut Assets<StandardMaterial>,
+    name : &str,
+    render_layer : u8,
+    boot_sequence : Handle<BootSequence>,
+) -> (Entity, Handle<StandardMaterial>) {
     let size = Extent3d {
         width: 1228,
         height: 1024,
@@ -92,6 +175,15 @@ fn init_computer_world(
     
     let render_surface_image = images.add(image);
     
+    let render_surface_mat = materials.add(StandardMaterial {
+        base_color_texture: Some(render_surface_image.clone()),
+        // The screen lights up with its own picture, so each character glows in its own color.
+        emissive_texture: Some(render_surface_image.clone()),
+        emissive: Color::BLACK,
+        ..Default::default()
+    });
+    
+    let layer = RenderLayers::layer(render_layer);
     let camera = commands.spawn((
         Camera3dBundle {
             transform: Transform::from_translation(Vec3::new(0.0, 1.5, 5.0)).looking_at(Vec3::ZERO, Vec3::Y),
@@ -103,53 +195,52 @@ fn init_computer_world(
         },
         AsciiCamera::default(),
         AsciiUi::default(),
-        RenderLayers::layer(1),
+        layer,
         VisibilityBundle::default(),
         ComputerCamera,
+        Computer {
+            name : name.to_string(),
+            state : ComputerState::Off,
+            render_surface_mat : render_surface_mat.clone(),
+            render_surface_image,
+            boot_sequence,
+        },
+        Name::new(format!("Computer ({})", name)),
     )).id();
-    
-    let render_surface_mat = materials.add(StandardMaterial {
-        base_color_texture: Some(render_surface_image.clone()),
-        emissive: Color::LIME_GREEN,
-        ..Default::default()
-    });
-    
-    commands.insert_resource(ComputerWorldAssets {
-        render_surface_mat,
-        render_surface_image,
-    });
+    let node = ComputerNode(camera);
     
     commands.ascii_ui_with_parent(camera)
         .fill(LoadingScreenComponent::default())
-            .hidden().insert(RenderLayers::layer(1))
+            .hidden().insert(layer).insert(node)
+        .pop()
+        .fill(TerminalComponent::new(0))
+            .hidden().insert(layer).insert(node).insert(TerminalTypewriter::default()).insert(TerminalGhost::default()).insert(FocusedTerminal)
+        .pop()
+        .fill(TerminalComponent::new(1))
+            .hidden().insert(layer).insert(node).insert(TerminalTypewriter::default()).insert(TerminalGhost::default())
+        .pop()
+        .fill(TerminalComponent::new(2))
+            .hidden().insert(layer).insert(node).insert(TerminalTypewriter::default()).insert(TerminalGhost::default())
+        .pop()
+        .fill(TerminalComponent::new(3))
+            .hidden().insert(layer).insert(node).insert(TerminalTypewriter::default()).insert(TerminalGhost::default())
+        .pop()
+        .fill(CartridgeViewerComponent::default())
+            .hidden().insert(layer).insert(node)
         .pop()
-        .fill(TerminalComponent::default())
-            .hidden().insert(RenderLayers::layer(1))
+        .fill(GameScreenComponent::default())
+            .hidden().insert(layer).insert(node)
+        .pop()
+        .fill(EditorComponent::default())
+            .hidden().insert(layer).insert(node)
+        .pop()
+        .fill(ShutdownScreenComponent::default())
+            .hidden().insert(layer).insert(node)
         .pop()
     ;
     
     
-    // light
-    // commands.spawn((
-    //     DirectionalLightBundle {
-    //         directional_light: DirectionalLight {
-    //             shadows_enabled: true,
-    //             ..default()
-    //         },
-    //         // This is a relatively small scene, so use tighter shadow
-    //         // cascade bounds than the default for better quality.
-    //         // We also adjusted the shadow map to be larger since we're
-    //         // only using a single cascade.
-    //         cascade_shadow_config: CascadeShadowConfigBuilder {
-    //             num_cascades: 1,
-    //             maximum_distance: 1.6,
-    //             ..default()
-    //         }
-    //         .into(),
-    //         ..default()
-    //     },
-    //     RenderLayers::layer(1)
-    // ));
+    (camera, render_surface_mat)
 }
 
 //==============================================================================
@@ -158,22 +249,56 @@ fn init_computer_world(
 
 fn on_enter_computer_world(
     mut gameworld_change_events : EventReader<OnGameWorldChangeEvent>,
-    computer_state : Res<ComputerState>,
+    mut power_events : EventWriter<ComputerPowerEvent>,
+    computers : Query<&Computer>,
+    active_computer : Res<ActiveComputer>,
 ) {
     for game_world_change_event in gameworld_change_events.read() {
         if game_world_change_event.0 == GameWorld::Computer {
-            match *computer_state {
-                ComputerState::Off => todo!(),
-                ComputerState::OS => {},
-                ComputerState::Game => {},
+            let Some(entity) = active_computer.0 else { continue };
+            let Ok(computer) = computers.get(entity) else { continue };
+            
+            match computer.state {
+                ComputerState::Off => { power_events.send(ComputerPowerEvent::power_on(entity)); },
+                ComputerState::Booting | ComputerState::OS | ComputerState::Game | ComputerState::ShuttingDown => {},
             }
             
-            println!("Entering Computer World");
+            println!("Entering Computer World ({})", computer.name);
         }
     }
 }
 
-//==============================================================================
-//         BootUp Computer
-//==============================================================================
+/// Pressing Enter or Space at a dead screen presses the power button.
+fn power_button(
+    mut power_events : EventWriter<ComputerPowerEvent>,
+    computers : Query<&Computer>,
+    active_computer : Res<ActiveComputer>,
+    current_game_world : Res<CurrentGameWorld>,
+    input : Res<ButtonInput<KeyCode>>,
+) {
+    if current_game_world.get() != GameWorld::Computer { return }
+    let Some(entity) = active_computer.0 else { return };
+    let Ok(computer) = computers.get(entity) else { return };
+    if computer.state != ComputerState::Off { return }
+    
+    if input.any_just_pressed([KeyCode::Enter, KeyCode::Space]) {
+        power_events.send(ComputerPowerEvent::power_on(entity));
+    }
+}
 
+/// Each screen only glows while its computer has power.
+fn update_screen_glow(
+    mut materials : ResMut<Assets<StandardMaterial>>,
+    computers : Query<&Computer>,
+) {
+    for computer in computers.iter() {
+        let glow = if computer.state.is_powered() { SCREEN_GLOW } else { Color::BLACK };
+        
+        let Some(material) = materials.get(&computer.render_surface_mat) else { continue };
+        if material.emissive == glow { continue }
+        
+        if let Some(material) = materials.get_mut(&computer.render_surface_mat) {
+            material.emissive = glow;
+        }
+    }
+}
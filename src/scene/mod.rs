@@ -1,10 +1,10 @@
 pub mod computer_world;
 
-use bevy::{audio::{PlaybackMode, Volume}, gltf::Gltf, prelude::*, render::{camera::RenderTarget, view::RenderLayers}, scene::InstanceId};
+use bevy::{audio::{PlaybackMode, Volume}, gltf::Gltf, prelude::*, render::{camera::RenderTarget, view::RenderLayers}, scene::InstanceId, utils::HashMap};
 use bevy_inspector_egui::bevy_egui::setup_new_windows_system;
-use crate::{audio::{ambient::{AmbientAudioEvent, AmbientAudioType}, sound::PlaySoundEvent, AudioAssets}, camera::blackout::BlackoutTransition, game::{ActiveCamera, GameState}, loading::{LoadingTracker, SceneTracker}};
+use crate::{audio::{ambient::{AmbientAudioEvent, AmbientAudioType}, sound::PlaySoundEvent, AudioAssets}, camera::blackout::BlackoutTransition, game::{ActiveCamera, GameState}, loading::{LoadingTracker, SceneTracker}, ui::boot_sequence::BootSequenceAssets};
 
-use self::computer_world::{ComputerCamera, ComputerWorldAssets, ComputerWorldPlugin};
+use self::computer_world::{spawn_computer, ActiveComputer, ComputerCamera, ComputerWorldPlugin};
 
 //==============================================================================
 //         Scene Plugin
@@ -34,6 +34,17 @@ impl Plugin for ScenePlugin {
 #[derive(Debug, Component)]
 pub struct RoomCamera;
 
+/// Everything spawned from the room model. Camera zones behind it can't be clicked.
+#[derive(Debug, Component)]
+pub struct RoomGeometry;
+
+/// Any glTF node whose name starts with this gets a computer of its own,
+/// e.g. `Screen`, `Screen.Basement` or `Screen.Security`.
+pub const SCREEN_NODE_PREFIX : &str = "Screen";
+
+/// Render layer 0 is the room, so computers are drawn on the layers after it.
+const FIRST_COMPUTER_LAYER : u8 = 1;
+
 //==============================================================================
 //         Scene Loading
 //==============================================================================
@@ -123,9 +134,12 @@ fn setup_room_scene_when_finished(
     mut sound_effect_event : EventWriter<PlaySoundEvent>,
     room_scene_assets : Res<RoomSceneAssets>,
     scene_spawner : ResMut<SceneSpawner>,
+    mut images : ResMut<Assets<Image>>,
+    mut materials : ResMut<Assets<StandardMaterial>>,
+    mut active_computer : ResMut<ActiveComputer>,
     named_assets : Query<(&Name, &Parent)>,
-    game_world_assets : Res<ComputerWorldAssets>,
-    sounds : Res<AudioAssets>
+    sounds : Res<AudioAssets>,
+    boot_sequence_assets : Res<BootSequenceAssets>,
 ) {
     fade_in_event.send(BlackoutTransition::fade_in(1.0));
     ambient_audio_event.send(AmbientAudioEvent::fade_in(AmbientAudioType::Storm, 1.0, 0.2));
@@ -134,14 +148,33 @@ fn setup_room_scene_when_finished(
     let Some(instance) = room_scene_assets.room_scene else { return };
     
     if scene_spawner.instance_is_ready(instance) {
+        // A screen node can hold more than one mesh, they all show the same computer.
+        let mut screens : HashMap<Entity, Handle<StandardMaterial>> = HashMap::new();
+        
         for entity in scene_spawner.iter_instance_entities(instance) {
-            commands.entity(entity).insert(RenderLayers::layer(0));
+            commands.entity(entity).insert((RenderLayers::layer(0), RoomGeometry));
             
             if let Ok((name, parent)) = named_assets.get(entity) {
                 if &**name == "Mesh" {
                     if let Ok((parrent_name, _)) = named_assets.get(**parent) {
-                        if &**parrent_name == "Screen" {
-                            commands.entity(entity).insert(game_world_assets.render_surface_mat.clone());
+                        if parrent_name.starts_with(SCREEN_NODE_PREFIX) {
+                            if !screens.contains_key(&**parent) {
+                                // Every computer needs a render layer of its own, and there are only so many.
+                                let render_layer = FIRST_COMPUTER_LAYER as usize + screens.len();
+                                if render_layer >= RenderLayers::TOTAL_LAYERS {
+                                    println!("Screen {} has no render layer left, so it stays blank", parrent_name);
+                                    continue;
+                                }
+                                let render_layer = render_layer as u8;
+                                let (computer, material) = spawn_computer(&mut commands, &mut images, &mut materials, parrent_name, render_layer, boot_sequence_assets.default.clone());
+                                screens.insert(**parent, material);
+                                
+                                // The desk computer is the one you sit at first.
+                                if active_computer.0.is_none() || &**parrent_name == SCREEN_NODE_PREFIX {
+                                    active_computer.0 = Some(computer);
+                                }
+                            }
+                            commands.entity(entity).insert(screens[&**parent].clone());
                         }
                     }
                 }
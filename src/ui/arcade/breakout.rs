@@ -0,0 +1,211 @@
+use bevy::prelude::*;
+use bevy_ascii::prelude::*;
+
+use super::{draw_centered, draw_character, AsciiGame, GameContext, GameInput, GameStatus};
+
+const PADDLE_WIDTH : i32 = 7;
+const BRICK_WIDTH : i32 = 4;
+const BRICK_ROWS : i32 = 4;
+/// The rows above the bricks, so the ball has room behind them.
+const BRICK_TOP : i32 = 2;
+const BALL_SPEED : f32 = 0.5;
+const START_LIVES : u32 = 3;
+
+//==============================================================================
+//         Breakout
+//==============================================================================
+
+/// Bat the ball into the wall until there is no wall left.
+pub struct BreakoutGame {
+    width : i32,
+    height : i32,
+    /// The left edge of the paddle.
+    paddle : i32,
+    ball : Vec2,
+    velocity : Vec2,
+    /// `true` for every brick still standing, row by row.
+    bricks : Vec<Vec<bool>>,
+    score : u32,
+    best : u32,
+    lives : u32,
+    /// The ball sits on the paddle until the player serves it.
+    serving : bool,
+    over : bool,
+}
+
+impl Default for BreakoutGame {
+    fn default() -> Self {
+        Self {
+            width : 0,
+            height : 0,
+            paddle : 0,
+            ball : Vec2::ZERO,
+            velocity : Vec2::ZERO,
+            bricks : Vec::new(),
+            score : 0,
+            best : 0,
+            lives : START_LIVES,
+            serving : true,
+            over : false,
+        }
+    }
+}
+
+impl BreakoutGame {
+    fn reset(&mut self) {
+        self.best = self.best.max(self.score);
+        self.score = 0;
+        self.lives = START_LIVES;
+        self.over = false;
+        self.build_wall();
+        self.serve();
+    }
+
+    fn build_wall(&mut self) {
+        let columns = (self.width / BRICK_WIDTH).max(1) as usize;
+        self.bricks = vec![vec![true; columns]; BRICK_ROWS as usize];
+    }
+
+    fn serve(&mut self) {
+        self.paddle = (self.width - PADDLE_WIDTH) / 2;
+        self.serving = true;
+        self.velocity = Vec2::new(BALL_SPEED, -BALL_SPEED);
+    }
+
+    fn paddle_row(&self) -> i32 {
+        self.height - 2
+    }
+
+    /// Where the wall is centered, since the width rarely divides evenly.
+    fn wall_offset(&self) -> i32 {
+        let columns = self.bricks.first().map(Vec::len).unwrap_or(0) as i32;
+        (self.width - columns * BRICK_WIDTH) / 2
+    }
+
+    /// Knocks out the brick at a cell, if there is one there.
+    fn hit_brick(&mut self, x : i32, y : i32) -> bool {
+        let row = y - BRICK_TOP;
+        let column = (x - self.wall_offset()).div_euclid(BRICK_WIDTH);
+        if row < 0 || row >= BRICK_ROWS || column < 0 { return false }
+
+        let Some(brick) = self.bricks.get_mut(row as usize).and_then(|row| row.get_mut(column as usize)) else { return false };
+        if !*brick { return false }
+
+        *brick = false;
+        // The top rows are worth more.
+        self.score += 10 * (BRICK_ROWS - row) as u32;
+        true
+    }
+
+    fn move_ball(&mut self) {
+        let mut next = self.ball + self.velocity;
+
+        if next.x < 0.0 || next.x >= self.width as f32 {
+            self.velocity.x = -self.velocity.x;
+            next.x = self.ball.x + self.velocity.x;
+        }
+        if next.y < 0.0 {
+            self.velocity.y = -self.velocity.y;
+            next.y = self.ball.y + self.velocity.y;
+        }
+
+        let (x, y) = (next.x.floor() as i32, next.y.floor() as i32);
+        if self.hit_brick(x, y) {
+            self.velocity.y = -self.velocity.y;
+            next.y = self.ball.y + self.velocity.y;
+        }
+
+        // The paddle sends the ball off at an angle depending on where it was hit.
+        if y == self.paddle_row() && self.velocity.y > 0.0 && (self.paddle..self.paddle + PADDLE_WIDTH).contains(&x) {
+            let offset = (x - self.paddle - PADDLE_WIDTH / 2) as f32 / (PADDLE_WIDTH / 2) as f32;
+            self.velocity = Vec2::new(offset * BALL_SPEED * 1.5, -BALL_SPEED);
+            if self.velocity.x.abs() < 0.1 { self.velocity.x = 0.15 }
+            next.y = self.ball.y + self.velocity.y;
+        }
+
+        self.ball = next;
+
+        if self.ball.y >= self.height as f32 {
+            self.lives = self.lives.saturating_sub(1);
+            match self.lives {
+                0 => self.over = true,
+                _ => self.serve(),
+            }
+        }
+
+        if self.bricks.iter().flatten().all(|brick| !brick) {
+            self.build_wall();
+            self.serve();
+        }
+    }
+}
+
+impl AsciiGame for BreakoutGame {
+    fn step_time(&self) -> f32 {
+        0.05
+    }
+
+    fn start(&mut self, context : &GameContext) {
+        self.width = context.width;
+        self.height = context.height;
+        self.reset();
+    }
+
+    fn step(&mut self, input : &GameInput) -> GameStatus {
+        if self.over {
+            if input.any_just_pressed(&[KeyCode::KeyQ, KeyCode::Escape]) { return GameStatus::Exit }
+            if input.any_just_pressed(&[KeyCode::Space, KeyCode::Enter]) { self.reset() }
+            return GameStatus::Running;
+        }
+
+        if input.any_held(&[KeyCode::ArrowLeft, KeyCode::KeyA]) {
+            self.paddle = (self.paddle - 1).max(0);
+        }
+        if input.any_held(&[KeyCode::ArrowRight, KeyCode::KeyD]) {
+            self.paddle = (self.paddle + 1).min(self.width - PADDLE_WIDTH);
+        }
+
+        if self.serving {
+            self.ball = Vec2::new((self.paddle + PADDLE_WIDTH / 2) as f32, (self.paddle_row() - 1) as f32);
+            if input.just_pressed(KeyCode::Space) { self.serving = false }
+            return GameStatus::Running;
+        }
+
+        self.move_ball();
+        GameStatus::Running
+    }
+
+    fn render(&self, buffer : &mut AsciiBuffer) {
+        let offset = self.wall_offset();
+        for (row, bricks) in self.bricks.iter().enumerate() {
+            for (column, standing) in bricks.iter().enumerate() {
+                if !standing { continue }
+                for (index, character) in "[==]".chars().enumerate() {
+                    draw_character(buffer, offset + column as i32 * BRICK_WIDTH + index as i32, BRICK_TOP + row as i32, character);
+                }
+            }
+        }
+
+        for x in self.paddle..self.paddle + PADDLE_WIDTH {
+            draw_character(buffer, x, self.paddle_row(), '=');
+        }
+        draw_character(buffer, self.ball.x.floor() as i32, self.ball.y.floor() as i32, 'o');
+
+        let lives = "o ".repeat(self.lives as usize);
+        buffer.relative(0, 0, 1.0, 1).text(&format!("Balls: {}", lives)).draw();
+
+        if self.serving && !self.over {
+            draw_centered(buffer, self.height / 2, "[SPACE] Serve");
+        }
+
+        if self.over {
+            draw_centered(buffer, self.height / 2, "G A M E   O V E R");
+            draw_centered(buffer, self.height / 2 + 2, "[SPACE] Again   [Q] Quit");
+        }
+    }
+
+    /// The best this sitting, so starting over doesn't throw a good game away.
+    fn score(&self) -> u32 {
+        self.best.max(self.score)
+    }
+}
@@ -0,0 +1,423 @@
+pub mod snake;
+pub mod breakout;
+
+use std::collections::BTreeMap;
+
+use bevy::{input::{keyboard::KeyboardInput, ButtonState}, prelude::*};
+use bevy_ascii::prelude::*;
+
+use crate::{os::{cartridge::{Cartridge, CartridgeExitEvent, CartridgeProgram, CartridgeStartedEvent}, fs::{FsNode, VirtualFs, HOME_DIRECTORY}, users::ROOT_USER}, scene::computer_world::{ActiveComputer, Computer, ComputerNode, ComputerPoweredOffEvent, ComputerState}};
+
+use self::{breakout::BreakoutGame, snake::SnakeGame};
+
+use super::terminal::{FocusedTerminal, TerminalComponent};
+
+/// The border around the playfield, on every side.
+const GAME_PADDING : u32 = 3;
+
+/// Steps are capped each frame, so a long hitch doesn't fast forward the game.
+const MAX_STEPS_PER_FRAME : u32 = 5;
+
+/// How many entries a high score table keeps.
+const HIGH_SCORE_COUNT : usize = 5;
+
+//==============================================================================
+//         Arcade Plugin
+//==============================================================================
+
+/// Runs [`CartridgeProgram::Game`] cartridges.
+pub struct ArcadePlugin;
+
+impl Plugin for ArcadePlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_resource::<AsciiGameRegistry>()
+
+            .register_ascii_game::<SnakeGame>("snake")
+            .register_ascii_game::<BreakoutGame>("breakout")
+        ;
+    }
+}
+
+//==============================================================================
+//         Ascii Game
+//==============================================================================
+
+/// A real-time game played on a computer screen. It is stepped at a fixed
+/// rate whatever the frame rate, and draws whatever state it is in.
+pub trait AsciiGame : Send + Sync + 'static {
+    /// Seconds between steps.
+    fn step_time(&self) -> f32;
+
+    /// Sets up a fresh game. Called once, before the first step.
+    fn start(&mut self, context : &GameContext);
+
+    fn step(&mut self, input : &GameInput) -> GameStatus;
+
+    /// Draws the playfield. `buffer` is the screen inside the border.
+    fn render(&self, buffer : &mut AsciiBuffer);
+
+    /// The score so far, saved to the high score table when the game exits.
+    fn score(&self) -> u32;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GameStatus {
+    Running,
+    /// Hands the computer back to the OS.
+    Exit,
+}
+
+/// What a game knows about the world outside it.
+#[derive(Debug, Clone)]
+pub struct GameContext {
+    /// Whoever is logged in at the computer.
+    pub player : String,
+    pub high_scores : Vec<HighScore>,
+    pub width : i32,
+    pub height : i32,
+    /// Different every time, for [`GameRng`].
+    pub seed : u64,
+}
+
+/// The keyboard since the last step, and the size of the playfield in characters.
+#[derive(Debug, Clone, Default)]
+pub struct GameInput {
+    pub pressed : Vec<KeyCode>,
+    pub held : Vec<KeyCode>,
+    pub width : i32,
+    pub height : i32,
+}
+
+impl GameInput {
+    pub fn just_pressed(&self, key : KeyCode) -> bool {
+        self.pressed.contains(&key)
+    }
+
+    pub fn any_just_pressed(&self, keys : &[KeyCode]) -> bool {
+        keys.iter().any(|key| self.just_pressed(*key))
+    }
+
+    pub fn any_held(&self, keys : &[KeyCode]) -> bool {
+        keys.iter().any(|key| self.held.contains(key))
+    }
+}
+
+/// Puts a single character at `x`, `y` in `buffer`, clipped to it.
+pub fn draw_character(buffer : &AsciiBuffer, x : i32, y : i32, character : char) {
+    if x < 0 || y < 0 || x >= buffer.bounds.width as i32 || y >= buffer.bounds.height as i32 { return }
+    buffer.relative(0, y, 1.0, 1).set_character(x, 0, character);
+}
+
+/// Writes a line of text centered on row `y`.
+pub fn draw_centered(buffer : &AsciiBuffer, y : i32, text : &str) {
+    buffer.relative(0, y, 1.0, 1).text(text).horizontal_alignment(HorizontalAlignment::Center).draw();
+}
+
+/// A tiny xorshift generator, so games don't need a crate to shuffle things.
+#[derive(Debug, Clone, Copy)]
+pub struct GameRng(u64);
+
+impl GameRng {
+    pub fn new(seed : u64) -> Self {
+        Self(seed | 1)
+    }
+
+    /// A number from `0` up to, not including, `max`.
+    pub fn below(&mut self, max : i32) -> i32 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        ((self.0 >> 33) % max.max(1) as u64) as i32
+    }
+}
+
+//==============================================================================
+//         Game Registry
+//==============================================================================
+
+type GameConstructor = fn() -> Box<dyn AsciiGame>;
+
+/// Every game a cartridge can name in [`CartridgeProgram::Game`].
+#[derive(Resource, Default)]
+pub struct AsciiGameRegistry {
+    games : BTreeMap<String, GameConstructor>,
+}
+
+impl AsciiGameRegistry {
+    pub fn create(&self, name : &str) -> Option<Box<dyn AsciiGame>> {
+        self.games.get(name).map(|constructor| constructor())
+    }
+}
+
+fn construct_game<G : AsciiGame + Default>() -> Box<dyn AsciiGame> {
+    Box::new(G::default())
+}
+
+pub trait RegisterAsciiGame {
+    fn register_ascii_game<G : AsciiGame + Default>(&mut self, name : &str) -> &mut Self;
+}
+
+impl RegisterAsciiGame for App {
+    fn register_ascii_game<G : AsciiGame + Default>(&mut self, name : &str) -> &mut Self {
+        let mut registry = self.world.get_resource_or_insert_with(AsciiGameRegistry::default);
+        registry.games.insert(name.to_string(), construct_game::<G>);
+        self
+    }
+}
+
+//==============================================================================
+//         High Scores
+//==============================================================================
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HighScore {
+    pub name : String,
+    pub score : u32,
+}
+
+/// High scores live on the G64 itself, one `score name` line each, so they
+/// survive a reboot and can be `cat`ed (or doctored) by the player.
+fn high_score_path(game : &str) -> String {
+    format!("{}/.scores/{}", HOME_DIRECTORY, game)
+}
+
+pub fn load_high_scores(filesystem : &VirtualFs, game : &str) -> Vec<HighScore> {
+    let Ok(text) = filesystem.read(&high_score_path(game), ROOT_USER) else { return Vec::new() };
+
+    let mut scores = text.lines()
+        .filter_map(|line| line.trim().split_once(' '))
+        .filter_map(|(score, name)| Some(HighScore { name : name.trim().to_string(), score : score.parse().ok()? }))
+        .collect::<Vec<_>>();
+    scores.sort_by(|a, b| b.score.cmp(&a.score));
+    scores
+}
+
+fn save_high_score(filesystem : &mut VirtualFs, game : &str, score : HighScore) {
+    let mut scores = load_high_scores(filesystem, game);
+    scores.push(score);
+    scores.sort_by(|a, b| b.score.cmp(&a.score));
+    scores.truncate(HIGH_SCORE_COUNT);
+
+    let folder = format!("{}/.scores", HOME_DIRECTORY);
+    if !filesystem.exists(&folder) {
+        let _ = filesystem.insert(&folder, FsNode::directory(), ROOT_USER);
+    }
+
+    let text = scores.iter().map(|entry| format!("{} {}\n", entry.score, entry.name)).collect::<String>();
+    if let Err(error) = filesystem.write(&high_score_path(game), &text, ROOT_USER) {
+        println!("Could not save {} high scores: {}", game, error);
+    }
+}
+
+//==============================================================================
+//         Game Screen Component
+//==============================================================================
+
+/// The screen used by [`CartridgeProgram::Game`] cartridges.
+#[derive(Component, Default)]
+pub struct GameScreenComponent {
+    name : String,
+    title : String,
+    player : String,
+    game : Option<Box<dyn AsciiGame>>,
+    /// Time not yet used up by a step.
+    carry : f32,
+    /// Keys pressed since the last step.
+    pressed : Vec<KeyCode>,
+}
+
+impl GameScreenComponent {
+    /// The playfield size in characters for a screen drawn in `node`.
+    fn playfield_size(node : &AsciiNode) -> (i32, i32) {
+        (
+            node.bounds.width.saturating_sub(GAME_PADDING * 2) as i32,
+            node.bounds.height.saturating_sub(GAME_PADDING * 2) as i32,
+        )
+    }
+}
+
+impl AsciiComponent for GameScreenComponent {
+    type UpdateQuery<'w, 's> = ();
+
+    fn render(&self, buffer: &mut AsciiBuffer) {
+        let Some(game) = &self.game else { return };
+
+        {
+            let buffer = buffer.top(3);
+            buffer.text(&format!("--- {} : {:06} ---", self.title, game.score()))
+                .vertical_alignment(VerticalAlignment::Center)
+                .horizontal_alignment(HorizontalAlignment::Center)
+                .draw();
+        }
+
+        let padding = GAME_PADDING as i32;
+        let mut buffer = buffer.padding((padding, padding, padding, padding)).clip();
+        game.render(&mut buffer);
+    }
+
+    fn set_up(app: &mut App) {
+        app
+            .add_systems(PostUpdate, (start_games, stop_games, stop_games_on_power_off))
+            .add_systems(Update, (game_input, step_games).chain())
+        ;
+    }
+}
+
+//==============================================================================
+//         Game Screen Systems
+//==============================================================================
+
+fn start_games(
+    mut screens : Query<(&mut GameScreenComponent, &mut Visibility, &AsciiNode, &ComputerNode), Without<TerminalComponent>>,
+    mut terminals : Query<(&TerminalComponent, &mut Visibility, Has<FocusedTerminal>, &ComputerNode)>,
+    mut computers : Query<&mut Computer>,
+    mut started_events : EventReader<CartridgeStartedEvent>,
+    mut exit_events : EventWriter<CartridgeExitEvent>,
+    mut mark_dirty : EventWriter<AsciiMarkDirtyEvent>,
+    registry : Res<AsciiGameRegistry>,
+    filesystem : Res<VirtualFs>,
+    cartridges : Res<Assets<Cartridge>>,
+    time : Res<Time>,
+) {
+    for event in started_events.read() {
+        let CartridgeProgram::Game(name) = &event.program else { continue };
+        let Some(cartridge) = cartridges.get(&event.cartridge) else { continue };
+
+        let Some(mut game) = registry.create(name) else {
+            println!("Cartridge {} wants a game called '{}', but there is none", cartridge.name, name);
+            exit_events.send(CartridgeExitEvent(event.computer));
+            continue;
+        };
+
+        let player = terminals.iter()
+            .find(|(_, _, focused, node)| *focused && node.0 == event.computer)
+            .map(|(terminal, _, _, _)| terminal.user().to_string())
+            .unwrap_or_default();
+
+        for (mut screen, mut visibility, node, computer) in screens.iter_mut() {
+            if computer.0 != event.computer { continue }
+
+            let (width, height) = GameScreenComponent::playfield_size(node);
+            game.start(&GameContext {
+                player : player.clone(),
+                high_scores : load_high_scores(&filesystem, name),
+                width,
+                height,
+                seed : time.elapsed().as_nanos() as u64,
+            });
+
+            *screen = GameScreenComponent {
+                name : name.clone(),
+                title : cartridge.title.clone(),
+                player : player.clone(),
+                game : Some(game),
+                ..default()
+            };
+            *visibility = Visibility::Visible;
+            break;
+        }
+
+        for (_, mut visibility, _, node) in terminals.iter_mut() {
+            if node.0 != event.computer { continue }
+            *visibility = Visibility::Hidden;
+        }
+
+        if let Ok(mut computer) = computers.get_mut(event.computer) {
+            computer.state = ComputerState::Game;
+        }
+
+        mark_dirty.send(AsciiMarkDirtyEvent);
+    }
+}
+
+/// Only the computer being looked at gets the keyboard.
+fn game_input(
+    mut screens : Query<(&mut GameScreenComponent, &InheritedVisibility, &ComputerNode)>,
+    mut key_events : EventReader<KeyboardInput>,
+    active_computer : Res<ActiveComputer>,
+) {
+    let pressed_keys = key_events.read()
+        .filter(|event| event.state == ButtonState::Pressed)
+        .map(|event| event.key_code)
+        .collect::<Vec<_>>();
+
+    for (mut screen, visibility, computer) in screens.iter_mut() {
+        if !visibility.get() || !active_computer.is(computer.0) || screen.game.is_none() { continue }
+        screen.pressed.extend(pressed_keys.iter().copied());
+    }
+}
+
+fn step_games(
+    mut screens : Query<(&mut GameScreenComponent, &AsciiNode, &ComputerNode)>,
+    mut exit_events : EventWriter<CartridgeExitEvent>,
+    mut mark_dirty : EventWriter<AsciiMarkDirtyEvent>,
+    active_computer : Res<ActiveComputer>,
+    key_input : Res<ButtonInput<KeyCode>>,
+    time : Res<Time>,
+) {
+    for (mut screen, node, computer) in screens.iter_mut() {
+        let Some(step_time) = screen.game.as_ref().map(|game| game.step_time().max(0.001)) else { continue };
+
+        let (width, height) = GameScreenComponent::playfield_size(node);
+        let held = match active_computer.is(computer.0) {
+            true => key_input.get_pressed().copied().collect(),
+            false => Vec::new(),
+        };
+
+        screen.carry += time.delta_seconds();
+        let mut steps = 0;
+        while screen.carry >= step_time && steps < MAX_STEPS_PER_FRAME {
+            screen.carry -= step_time;
+            steps += 1;
+
+            let input = GameInput { pressed : std::mem::take(&mut screen.pressed), held : held.clone(), width, height };
+            let Some(game) = screen.game.as_mut() else { break };
+            if game.step(&input) == GameStatus::Exit {
+                exit_events.send(CartridgeExitEvent(computer.0));
+                break;
+            }
+        }
+        screen.carry = screen.carry.min(step_time);
+
+        if steps > 0 {
+            mark_dirty.send(AsciiMarkDirtyEvent);
+        }
+    }
+}
+
+/// However the game ended, its score is kept and the screen handed back.
+fn stop_games(
+    mut screens : Query<(&mut GameScreenComponent, &mut Visibility, &ComputerNode)>,
+    mut computers : Query<&mut Computer>,
+    mut exit_events : EventReader<CartridgeExitEvent>,
+    mut filesystem : ResMut<VirtualFs>,
+) {
+    for CartridgeExitEvent(computer) in exit_events.read() {
+        for (mut screen, mut visibility, node) in screens.iter_mut() {
+            if node.0 != *computer { continue }
+            let Some(game) = screen.game.take() else { continue };
+
+            if game.score() > 0 {
+                save_high_score(&mut filesystem, &screen.name, HighScore { name : screen.player.clone(), score : game.score() });
+            }
+            *visibility = Visibility::Hidden;
+
+            if let Ok(mut owner) = computers.get_mut(*computer) {
+                if owner.state == ComputerState::Game { owner.state = ComputerState::OS }
+            }
+        }
+    }
+}
+
+fn stop_games_on_power_off(
+    mut screens : Query<(&mut GameScreenComponent, &ComputerNode)>,
+    mut powered_off_events : EventReader<ComputerPoweredOffEvent>,
+) {
+    for ComputerPoweredOffEvent(computer) in powered_off_events.read() {
+        for (mut screen, node) in screens.iter_mut() {
+            if node.0 != *computer { continue }
+            screen.game = None;
+        }
+    }
+}
@@ -0,0 +1,190 @@
+use std::collections::VecDeque;
+
+use bevy::prelude::*;
+use bevy_ascii::prelude::*;
+
+use super::{draw_centered, draw_character, AsciiGame, GameContext, GameInput, GameRng, GameStatus, HighScore};
+
+const POINTS_PER_FOOD : u32 = 10;
+const START_LENGTH : usize = 4;
+
+const UP : (i32, i32) = (0, -1);
+const DOWN : (i32, i32) = (0, 1);
+const LEFT : (i32, i32) = (-1, 0);
+const RIGHT : (i32, i32) = (1, 0);
+
+//==============================================================================
+//         Snake
+//==============================================================================
+
+/// The reference game. Eat, grow, don't bite yourself. The high score table
+/// has one name on it the player didn't put there.
+pub struct SnakeGame {
+    player : String,
+    high_scores : Vec<HighScore>,
+    width : i32,
+    height : i32,
+    /// Head first.
+    body : VecDeque<(i32, i32)>,
+    direction : (i32, i32),
+    food : (i32, i32),
+    score : u32,
+    best : u32,
+    deaths : u32,
+    over : bool,
+    rng : GameRng,
+}
+
+impl Default for SnakeGame {
+    fn default() -> Self {
+        Self {
+            player : String::new(),
+            high_scores : Vec::new(),
+            width : 0,
+            height : 0,
+            body : VecDeque::new(),
+            direction : RIGHT,
+            food : (0, 0),
+            score : 0,
+            best : 0,
+            deaths : 0,
+            over : false,
+            rng : GameRng::new(1),
+        }
+    }
+}
+
+impl SnakeGame {
+    fn reset(&mut self) {
+        let (x, y) = (self.width / 2, self.height / 2);
+        self.body = (0..START_LENGTH as i32).map(|offset| (x - offset, y)).collect();
+        self.direction = RIGHT;
+        self.score = 0;
+        self.over = false;
+        self.place_food();
+    }
+
+    fn place_food(&mut self) {
+        // The walls take up the outermost ring.
+        for _ in 0..100 {
+            let food = (1 + self.rng.below(self.width - 2), 1 + self.rng.below(self.height - 2));
+            if !self.body.contains(&food) {
+                self.food = food;
+                return;
+            }
+        }
+    }
+
+    fn is_wall(&self, (x, y) : (i32, i32)) -> bool {
+        x <= 0 || y <= 0 || x >= self.width - 1 || y >= self.height - 1
+    }
+
+    fn die(&mut self) {
+        self.over = true;
+        self.deaths += 1;
+        self.best = self.best.max(self.score);
+        self.high_scores.push(HighScore { name : self.player.clone(), score : self.score });
+        self.high_scores.sort_by(|a, b| b.score.cmp(&a.score));
+    }
+
+    /// The table shown after dying. From the second death on, the player is
+    /// already at the top of it, always a point ahead.
+    fn table(&self) -> Vec<HighScore> {
+        let mut table = self.high_scores.clone();
+        if self.deaths >= 2 && !self.player.is_empty() {
+            let top = table.first().map(|entry| entry.score).unwrap_or(0);
+            table.insert(0, HighScore { name : self.player.to_uppercase(), score : top + 1 });
+        }
+        table.truncate(5);
+        table
+    }
+}
+
+impl AsciiGame for SnakeGame {
+    fn step_time(&self) -> f32 {
+        0.12
+    }
+
+    fn start(&mut self, context : &GameContext) {
+        self.player = context.player.clone();
+        self.high_scores = context.high_scores.clone();
+        self.width = context.width;
+        self.height = context.height;
+        self.rng = GameRng::new(context.seed);
+        self.reset();
+    }
+
+    fn step(&mut self, input : &GameInput) -> GameStatus {
+        if self.over {
+            if input.any_just_pressed(&[KeyCode::KeyQ, KeyCode::Escape]) { return GameStatus::Exit }
+            if input.any_just_pressed(&[KeyCode::Space, KeyCode::Enter]) { self.reset() }
+            return GameStatus::Running;
+        }
+
+        self.width = input.width;
+        self.height = input.height;
+
+        for key in input.pressed.iter() {
+            let direction = match key {
+                KeyCode::ArrowUp | KeyCode::KeyW => UP,
+                KeyCode::ArrowDown | KeyCode::KeyS => DOWN,
+                KeyCode::ArrowLeft | KeyCode::KeyA => LEFT,
+                KeyCode::ArrowRight | KeyCode::KeyD => RIGHT,
+                _ => continue,
+            };
+            // Turning straight back would bite the neck.
+            if direction != (-self.direction.0, -self.direction.1) {
+                self.direction = direction;
+            }
+        }
+
+        let Some(&(x, y)) = self.body.front() else { return GameStatus::Running };
+        let head = (x + self.direction.0, y + self.direction.1);
+
+        if self.is_wall(head) || self.body.iter().take(self.body.len() - 1).any(|part| *part == head) {
+            self.die();
+            return GameStatus::Running;
+        }
+
+        self.body.push_front(head);
+        if head == self.food {
+            self.score += POINTS_PER_FOOD;
+            self.place_food();
+        } else {
+            self.body.pop_back();
+        }
+
+        GameStatus::Running
+    }
+
+    fn render(&self, buffer : &mut AsciiBuffer) {
+        for x in 0..self.width {
+            draw_character(buffer, x, 0, '#');
+            draw_character(buffer, x, self.height - 1, '#');
+        }
+        for y in 0..self.height {
+            draw_character(buffer, 0, y, '#');
+            draw_character(buffer, self.width - 1, y, '#');
+        }
+
+        draw_character(buffer, self.food.0, self.food.1, '*');
+        for (index, (x, y)) in self.body.iter().enumerate() {
+            draw_character(buffer, *x, *y, if index == 0 { '@' } else { 'o' });
+        }
+
+        if !self.over { return }
+
+        let top = self.height / 2 - 5;
+        draw_centered(buffer, top, "G A M E   O V E R");
+        draw_centered(buffer, top + 2, "HIGH SCORES");
+        for (index, entry) in self.table().iter().enumerate() {
+            draw_centered(buffer, top + 3 + index as i32, &format!("{}. {:<10} {:>6}", index + 1, entry.name, entry.score));
+        }
+        draw_centered(buffer, top + 9, "[SPACE] Again   [Q] Quit");
+    }
+
+    /// The best this sitting, so dying and walking away still counts.
+    fn score(&self) -> u32 {
+        self.best.max(self.score)
+    }
+}
@@ -1,20 +1,21 @@
-use std::time::Duration;
-
 use bevy::prelude::*;
 
 use bevy_ascii::prelude::*;
 use bevy_debug_text_overlay::screen_print;
 
-use crate::{audio::{ambient::{AmbientAudioEvent, AmbientAudioType}, sound::PlaySoundEvent, AudioAssets}, game::OnGameWorldChangeEvent, scene::computer_world::ComputerState, util::DelayedEvent};
+use crate::{audio::{ambient::{AmbientAudioEvent, AmbientAudioType}, sound::PlaySoundEvent, AudioAssets}, scene::computer_world::{Computer, ComputerNode, ComputerPowerEvent, ComputerPoweredOffEvent, ComputerState, PowerAction}};
 
-use super::terminal::TerminalComponent;
+use super::{arcade::GameScreenComponent, boot_sequence::{BootAction, BootSequence}, cartridge::CartridgeViewerComponent, editor::EditorComponent, terminal::{FocusedTerminal, TerminalComponent}};
 
 //==============================================================================
 //         loading Screen components
 //==============================================================================
 
+/// Plays a [`BootSequence`]. The sequence is copied in when the boot starts,
+/// since rendering has no access to assets.
 #[derive(Component)]
 pub struct LoadingScreenComponent {
+    sequence: BootSequence,
     progress: Timer,
     is_loading: bool,
 }
@@ -22,52 +23,61 @@ pub struct LoadingScreenComponent {
 impl Default for LoadingScreenComponent {
     fn default() -> Self {
         Self {
-            progress: Timer::from_seconds(8.0, TimerMode::Once),
+            sequence: BootSequence::default(),
+            progress: Timer::from_seconds(0.0, TimerMode::Once),
             is_loading: false,
         }
     }
 }
 
+impl LoadingScreenComponent {
+    fn start(&mut self, sequence : BootSequence) {
+        self.progress = Timer::from_seconds(sequence.handoff, TimerMode::Once);
+        self.sequence = sequence;
+        self.is_loading = true;
+    }
+}
+
 impl AsciiComponent for LoadingScreenComponent {
     type UpdateQuery<'w, 's> = ();
     
     fn render(&self, buffer: &mut AsciiBuffer) {
         let elapsed = self.progress.elapsed_secs();
         
-        if elapsed <= 2.5 {
-            let text = match elapsed {
-                ..=0.8 => "Initializing boot...",
-                0.8..=1.5 => "Initializing boot...\nLoading G64 unified Os...",
-                1.5..=1.8 => "Initializing boot...\nLoading G64 unified Os...\n64MB RAM detected...",
-                1.8..=2.0 => "Initializing boot...\nLoading G64 unified Os...\n64MB RAM detected...\nG64 Power+ APU detected...",
-                2.0.. => "Initializing boot...\nLoading G64 unified Os...\n64MB RAM detected...\nG64 Power+ APU detected...\nLoading Interface...",
-                _ => ""
-            };
-            
-            buffer.padding((1, 1, 1, 1)).text(text).draw();
-        } else {
-            let progress = ((elapsed - 2.5) / 0.5).min(1.0);
-            
-            let width = (progress * 22.0).round() as i32;
-            let height = 10;
-            
-            let inner = buffer.center(width, height).square().border(BorderType::Full).draw();
-            
-            if progress >= 1.0 {
-               if let Some(inner) = inner {
-                   inner.text("G64+ Pro").horizontal_alignment(HorizontalAlignment::Center).vertical_alignment(VerticalAlignment::Center).draw();
-               }
+        let mut log = Vec::new();
+        let mut progress = None;
+        let mut logo = None;
+        for step in self.sequence.steps.iter().filter(|step| step.at <= elapsed) {
+            match &step.action {
+                BootAction::Log(line) => log.push(line.as_str()),
+                BootAction::ClearLog => log.clear(),
+                BootAction::Progress { duration, width, height } => progress = Some((step.at, *duration, *width, *height)),
+                BootAction::Logo(art) => logo = Some(art.as_str()),
+                BootAction::Sound { .. } => {},
             }
         }
         
-      //   ____  __   _  _   
-      //  / ___|/ /_ | || |  
-      // | |  _| '_ \| || |_ 
-      // | |_| | (_) |__   _|
-      //  \____|\___/   |_| 
+        let Some((start, duration, width, height)) = progress else {
+            buffer.padding((1, 1, 1, 1)).text(&log.join("\n")).draw();
+            if let Some(logo) = logo {
+                buffer.text(logo).horizontal_alignment(HorizontalAlignment::Center).vertical_alignment(VerticalAlignment::Center).draw();
+            }
+            return;
+        };
         
+        let progress = match duration > 0.0 {
+            true => ((elapsed - start) / duration).min(1.0),
+            false => 1.0,
+        };
         
+        let width = (progress * width as f32).round() as i32;
+        let inner = buffer.center(width, height as i32).square().border(BorderType::Full).draw();
         
+        if progress >= 1.0 {
+            if let (Some(inner), Some(logo)) = (inner, logo) {
+                inner.text(logo).horizontal_alignment(HorizontalAlignment::Center).vertical_alignment(VerticalAlignment::Center).draw();
+            }
+        }
     }
 
     fn set_up(app: &mut App) {
@@ -78,49 +88,233 @@ impl AsciiComponent for LoadingScreenComponent {
     }
 }
 
-fn begin_startup(
-    mut loading_screens: Query<(&mut LoadingScreenComponent, &mut Visibility)>,
-    mut events : EventReader<OnGameWorldChangeEvent>,
+//==============================================================================
+//         Shutdown Screen components
+//==============================================================================
+
+/// The picture collapsing into a line and then a dot, like an old CRT losing power.
+#[derive(Component)]
+pub struct ShutdownScreenComponent {
+    progress : Timer,
+    is_shutting_down : bool,
+}
+
+impl Default for ShutdownScreenComponent {
+    fn default() -> Self {
+        Self {
+            progress : Timer::from_seconds(1.5, TimerMode::Once),
+            is_shutting_down : false,
+        }
+    }
+}
+
+impl AsciiComponent for ShutdownScreenComponent {
+    type UpdateQuery<'w, 's> = ();
+    
+    fn render(&self, buffer: &mut AsciiBuffer) {
+        let elapsed = self.progress.elapsed_secs();
+        let full_width = buffer.bounds.width as f32;
+        let full_height = buffer.bounds.height as f32;
+        
+        match elapsed {
+            ..=0.4 => {
+                let height = (full_height * (1.0 - elapsed / 0.4)).round().max(3.0) as i32;
+                buffer.center(full_width as i32, height).square().border(BorderType::Full).draw();
+            },
+            0.4..=0.8 => {
+                let width = (full_width * (1.0 - (elapsed - 0.4) / 0.4)).round().max(1.0) as usize;
+                buffer.text(&"-".repeat(width))
+                    .horizontal_alignment(HorizontalAlignment::Center)
+                    .vertical_alignment(VerticalAlignment::Center)
+                    .draw();
+            },
+            0.8..=1.2 => {
+                buffer.text("*")
+                    .horizontal_alignment(HorizontalAlignment::Center)
+                    .vertical_alignment(VerticalAlignment::Center)
+                    .draw();
+            },
+            _ => {},
+        }
+    }
+
+    fn set_up(app: &mut App) {
+        app
+            .add_systems(PostUpdate, (begin_shutdown, cut_power))
+            .add_systems(Update, update_shutdown_screen)
+        ;
+    }
+}
+
+/// The computer hum keeps playing while any other computer is still running.
+fn is_another_computer_running(computers : &Query<(Entity, &mut Computer)>, computer : Entity) -> bool {
+    computers.iter().any(|(entity, other)| entity != computer && matches!(other.state, ComputerState::OS | ComputerState::Game))
+}
+
+fn begin_shutdown(
+    mut computers : Query<(Entity, &mut Computer)>,
+    mut shutdown_screens : Query<(&mut ShutdownScreenComponent, &mut Visibility, &ComputerNode)>,
+    mut screens : Query<(&mut Visibility, &ComputerNode), (Or<(With<TerminalComponent>, With<CartridgeViewerComponent>, With<GameScreenComponent>, With<EditorComponent>, With<LoadingScreenComponent>)>, Without<ShutdownScreenComponent>)>,
+    mut power_events : EventReader<ComputerPowerEvent>,
     mut sound_effect_events : EventWriter<PlaySoundEvent>,
-    mut ambient_sound_events : EventWriter<DelayedEvent<AmbientAudioEvent>>,
+    mut ambient_sound_events : EventWriter<AmbientAudioEvent>,
+    mut mark_dirty : EventWriter<AsciiMarkDirtyEvent>,
     audio_assets : Res<AudioAssets>,
-    computer_state : Res<ComputerState>,
 ) {
-    
-    if !events.is_empty() && *computer_state == ComputerState::Off {
-        println!("begin_startup");
-        for mut loading_screen in loading_screens.iter_mut() {
-            sound_effect_events.send(PlaySoundEvent::new(audio_assets.computer_rev.clone(), 0.1, None));
-            ambient_sound_events.send(DelayedEvent::new(AmbientAudioEvent::play(AmbientAudioType::Computer, 0.1), 7.99));
-            loading_screen.0.is_loading = true;
-            *loading_screen.1 = Visibility::Visible;
+    for event in power_events.read() {
+        if event.action != PowerAction::Shutdown { continue }
+        let Ok((_, mut computer)) = computers.get_mut(event.computer) else { continue };
+        if !computer.state.is_powered() || computer.state == ComputerState::ShuttingDown { continue }
+        
+        computer.state = ComputerState::ShuttingDown;
+        sound_effect_events.send(PlaySoundEvent::new(audio_assets.computer_medium_write.clone(), 0.1, None));
+        if !is_another_computer_running(&computers, event.computer) {
+            ambient_sound_events.send(AmbientAudioEvent::fade_out(AmbientAudioType::Computer, 1.5));
+        }
+        
+        for (mut visibility, node) in screens.iter_mut() {
+            if node.0 != event.computer { continue }
+            *visibility = Visibility::Hidden;
+        }
+        
+        for (mut shutdown_screen, mut visibility, node) in shutdown_screens.iter_mut() {
+            if node.0 != event.computer { continue }
+            shutdown_screen.is_shutting_down = true;
+            shutdown_screen.progress.reset();
+            *visibility = Visibility::Visible;
+        }
+        
+        mark_dirty.send(AsciiMarkDirtyEvent);
+    }
+}
+
+fn update_shutdown_screen(
+    mut shutdown_screens : Query<(&mut ShutdownScreenComponent, &mut Visibility, &ComputerNode)>,
+    mut computers : Query<&mut Computer>,
+    mut powered_off_events : EventWriter<ComputerPoweredOffEvent>,
+    mut mark_dirty : EventWriter<AsciiMarkDirtyEvent>,
+    time : Res<Time>,
+) {
+    for (mut shutdown_screen, mut visibility, node) in shutdown_screens.iter_mut() {
+        if !shutdown_screen.is_shutting_down { continue }
+        
+        mark_dirty.send(AsciiMarkDirtyEvent);
+        if shutdown_screen.progress.tick(time.delta()).finished() {
+            shutdown_screen.is_shutting_down = false;
+            *visibility = Visibility::Hidden;
+            
+            let Ok(mut computer) = computers.get_mut(node.0) else { continue };
+            if computer.state == ComputerState::ShuttingDown {
+                computer.state = ComputerState::Off;
+                powered_off_events.send(ComputerPoweredOffEvent(node.0));
+            }
+        }
+    }
+}
+
+/// A power loss skips the shutdown animation: everything goes dark at once.
+fn cut_power(
+    mut computers : Query<(Entity, &mut Computer)>,
+    mut screens : Query<(&mut Visibility, Option<&mut ShutdownScreenComponent>, &ComputerNode), Or<(With<TerminalComponent>, With<CartridgeViewerComponent>, With<GameScreenComponent>, With<EditorComponent>, With<LoadingScreenComponent>, With<ShutdownScreenComponent>)>>,
+    mut power_events : EventReader<ComputerPowerEvent>,
+    mut ambient_sound_events : EventWriter<AmbientAudioEvent>,
+    mut powered_off_events : EventWriter<ComputerPoweredOffEvent>,
+    mut mark_dirty : EventWriter<AsciiMarkDirtyEvent>,
+) {
+    for event in power_events.read() {
+        if event.action != PowerAction::PowerLoss { continue }
+        let Ok((_, mut computer)) = computers.get_mut(event.computer) else { continue };
+        if !computer.state.is_powered() { continue }
+        
+        computer.state = ComputerState::Off;
+        if !is_another_computer_running(&computers, event.computer) {
+            ambient_sound_events.send(AmbientAudioEvent::fade_out(AmbientAudioType::Computer, 0.1));
+        }
+        
+        for (mut visibility, shutdown_screen, node) in screens.iter_mut() {
+            if node.0 != event.computer { continue }
+            *visibility = Visibility::Hidden;
+            if let Some(mut shutdown_screen) = shutdown_screen {
+                shutdown_screen.is_shutting_down = false;
+            }
+        }
+        
+        powered_off_events.send(ComputerPoweredOffEvent(event.computer));
+        mark_dirty.send(AsciiMarkDirtyEvent);
+    }
+}
+
+fn begin_startup(
+    mut loading_screens: Query<(&mut LoadingScreenComponent, &mut Visibility, &ComputerNode)>,
+    mut computers : Query<&mut Computer>,
+    mut power_events : EventReader<ComputerPowerEvent>,
+    boot_sequences : Res<Assets<BootSequence>>,
+) {
+    for event in power_events.read() {
+        if event.action != PowerAction::PowerOn { continue }
+        let Ok(mut computer) = computers.get_mut(event.computer) else { continue };
+        if computer.state != ComputerState::Off { continue }
+        
+        println!("begin_startup ({})", computer.name);
+        computer.state = ComputerState::Booting;
+        let sequence = boot_sequences.get(&computer.boot_sequence).cloned().unwrap_or_default();
+        for (mut loading_screen, mut visibility, node) in loading_screens.iter_mut() {
+            if node.0 != event.computer { continue }
+            loading_screen.start(sequence.clone());
+            *visibility = Visibility::Visible;
         }
     }
-    
-    events.clear();
 }
 
 fn update_loading_screen (
-    mut loading_screens: Query<(&mut LoadingScreenComponent, &AsciiNode, &mut Visibility), Without<TerminalComponent>>,
-    mut terminals: Query<(&mut TerminalComponent, &AsciiNode, &mut Visibility), Without<LoadingScreenComponent>>,
-    mut computer_state : ResMut<ComputerState>,
+    mut loading_screens: Query<(&mut LoadingScreenComponent, &mut Visibility, &ComputerNode), Without<TerminalComponent>>,
+    mut terminals: Query<(&mut Visibility, &ComputerNode), (With<FocusedTerminal>, Without<LoadingScreenComponent>)>,
+    mut computers : Query<(Entity, &mut Computer)>,
     mut mark_dirty : EventWriter<AsciiMarkDirtyEvent>,
+    mut ambient_sound_events : EventWriter<AmbientAudioEvent>,
+    mut sound_effect_events : EventWriter<PlaySoundEvent>,
     time : Res<Time>,
 ) { 
-    for (mut loading_screen, node, mut vis) in loading_screens.iter_mut() {
+    for (mut loading_screen, mut vis, node) in loading_screens.iter_mut() {
+        let Ok((_, computer)) = computers.get(node.0) else { continue };
+        
+        // The power went out part way through the boot.
+        if loading_screen.is_loading && computer.state != ComputerState::Booting {
+            loading_screen.is_loading = false;
+            loading_screen.progress.reset();
+            continue;
+        }
+        
         if loading_screen.is_loading {
             mark_dirty.send(AsciiMarkDirtyEvent);
+            let before = loading_screen.progress.elapsed_secs();
             loading_screen.progress.tick(time.delta());
             
+            // The last frame also plays anything scheduled right at the handoff.
+            let after = match loading_screen.progress.finished() {
+                true => f32::INFINITY,
+                false => loading_screen.progress.elapsed_secs(),
+            };
+            for (sound, volume) in loading_screen.sequence.sounds_between(before, after) {
+                sound_effect_events.send(PlaySoundEvent::new(sound.clone(), volume, None));
+            }
+            
             if loading_screen.progress.finished() {
                 loading_screen.is_loading = false;
                 loading_screen.progress.reset();
                 *vis = Visibility::Hidden;
-                *computer_state = ComputerState::OS;
-                for (_, _, mut visability) in terminals.iter_mut() {
+                
+                if !is_another_computer_running(&computers, node.0) {
+                    ambient_sound_events.send(AmbientAudioEvent::play(AmbientAudioType::Computer, 0.1));
+                }
+                if let Ok((_, mut computer)) = computers.get_mut(node.0) {
+                    computer.state = ComputerState::OS;
+                }
+                for (mut visability, terminal_node) in terminals.iter_mut() {
+                    if terminal_node != node { continue }
                     *visability = Visibility::Visible;
                 }
             }
         }
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,143 @@
+use bevy::{asset::{io::Reader, ron, AssetLoader, AsyncReadExt, LoadContext}, prelude::*, utils::BoxedFuture};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::loading::LoadingTracker;
+
+//==============================================================================
+//         Boot Sequence Plugin
+//==============================================================================
+
+pub struct BootSequencePlugin;
+
+impl Plugin for BootSequencePlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .init_asset::<BootSequence>()
+            .init_asset_loader::<BootSequenceLoader>()
+
+            .add_systems(Startup, load_boot_sequences)
+        ;
+    }
+}
+
+//==============================================================================
+//         Boot Sequence Asset
+//==============================================================================
+
+/// A boot script for the loading screen, read from a `.boot.ron` file. Every
+/// step happens `at` seconds into the boot, and the OS takes over at `handoff`.
+#[derive(Asset, TypePath, Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BootSequence {
+    pub handoff : f32,
+    pub steps : Vec<BootStep>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BootStep {
+    pub at : f32,
+    pub action : BootAction,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum BootAction {
+    /// Adds a line to the boot log.
+    Log(String),
+    /// Wipes the boot log.
+    ClearLog,
+    /// Replaces the log with a box that grows to `width` over `duration` seconds.
+    Progress { duration : f32, width : u32, height : u32 },
+    /// Art drawn in the middle of the last progress box once it is full, or
+    /// in the middle of the screen when there is none.
+    Logo(String),
+    /// Plays an audio file from the assets folder.
+    Sound {
+        path : String,
+        volume : f32,
+        #[serde(skip)]
+        handle : Handle<AudioSource>,
+    },
+}
+
+impl BootSequence {
+    /// The sounds that start somewhere in `from..to`.
+    pub fn sounds_between(&self, from : f32, to : f32) -> impl Iterator<Item = (&Handle<AudioSource>, f32)> {
+        self.steps.iter()
+            .filter(move |step| step.at >= from && step.at < to)
+            .filter_map(|step| match &step.action {
+                BootAction::Sound { handle, volume, .. } => Some((handle, *volume)),
+                _ => None,
+            })
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum BootSequenceLoaderError {
+    #[error("Could not read boot sequence: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Could not parse boot sequence: {0}")]
+    Ron(#[from] ron::error::SpannedError),
+}
+
+#[derive(Default)]
+pub struct BootSequenceLoader;
+
+impl AssetLoader for BootSequenceLoader {
+    type Asset = BootSequence;
+    type Settings = ();
+    type Error = BootSequenceLoaderError;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _settings: &'a Self::Settings,
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<Self::Asset, Self::Error>> {
+        Box::pin(async move {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).await?;
+            let mut sequence = ron::de::from_bytes::<BootSequence>(&bytes)?;
+
+            for step in sequence.steps.iter_mut() {
+                if let BootAction::Sound { path, handle, .. } = &mut step.action {
+                    *handle = load_context.load(path.clone());
+                }
+            }
+
+            Ok(sequence)
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["boot.ron"]
+    }
+}
+
+//==============================================================================
+//         Boot Sequence Assets
+//==============================================================================
+
+/// The sequences a computer can boot with. Each computer starts with
+/// `default`; set [`Computer::boot_sequence`] to give one of them a different
+/// boot, e.g. `corrupted` the next time it powers on.
+///
+/// [`Computer::boot_sequence`]: crate::scene::computer_world::Computer::boot_sequence
+#[derive(Resource)]
+pub struct BootSequenceAssets {
+    pub default : Handle<BootSequence>,
+    pub corrupted : Handle<BootSequence>,
+}
+
+fn load_boot_sequences(
+    mut commands : Commands,
+    mut loading_tracker : ResMut<LoadingTracker>,
+    asset_server : Res<AssetServer>,
+) {
+    let default = asset_server.load("os/g64.boot.ron");
+    let corrupted = asset_server.load("os/g64_corrupted.boot.ron");
+
+    loading_tracker.push(default.clone());
+    loading_tracker.push(corrupted.clone());
+
+    commands.insert_resource(BootSequenceAssets { default, corrupted });
+}
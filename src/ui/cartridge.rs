@@ -0,0 +1,153 @@
+use bevy::{input::{keyboard::KeyboardInput, ButtonState}, prelude::*};
+use bevy_ascii::prelude::*;
+
+use crate::{os::cartridge::{Cartridge, CartridgeExitEvent, CartridgeProgram, CartridgeStartedEvent}, scene::computer_world::{ActiveComputer, ComputerNode}};
+
+use super::terminal::{FocusedTerminal, TerminalComponent};
+
+//==============================================================================
+//         Cartridge Viewer Component
+//==============================================================================
+
+/// The screen used by [`CartridgeProgram::Viewer`] cartridges. It pages through
+/// the cartridge payload until the player quits.
+#[derive(Component, Default)]
+pub struct CartridgeViewerComponent {
+    pub title : String,
+    pub text : String,
+    pub scroll : usize,
+}
+
+impl AsciiComponent for CartridgeViewerComponent {
+    type UpdateQuery<'w, 's> = ();
+
+    fn render(&self, buffer: &mut AsciiBuffer) {
+        {
+            let buffer = buffer.top(3);
+            buffer.text(&format!("--- {} ---", self.title))
+                .vertical_alignment(VerticalAlignment::Center)
+                .horizontal_alignment(HorizontalAlignment::Center)
+                .draw();
+        }
+
+        {
+            let footer = buffer.relative(0, buffer.bounds.height as i32 - 2, 1.0, 1);
+            footer.text("[SPACE] Next page   [Q] Quit")
+                .horizontal_alignment(HorizontalAlignment::Center)
+                .draw();
+        }
+
+        let buffer = buffer.padding((3, 3, 3, 3)).clip();
+        let lines = viewer_lines(&self.text, buffer.bounds.width);
+
+        let page = lines.iter()
+            .skip(self.scroll)
+            .take(buffer.bounds.height as usize)
+            .cloned()
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        buffer.text(&page).draw();
+    }
+
+    fn set_up(app: &mut App) {
+        app
+            .add_systems(PostUpdate, (show_cartridge_viewer, return_to_terminal))
+            .add_systems(Update, cartridge_viewer_input)
+        ;
+    }
+}
+
+fn viewer_lines(text : &str, width : u32) -> Vec<String> {
+    text.lines()
+        .flat_map(|line| break_string_into_lines(line, width as usize))
+        .collect()
+}
+
+//==============================================================================
+//         Cartridge Viewer Systems
+//==============================================================================
+
+fn show_cartridge_viewer(
+    mut viewers : Query<(&mut CartridgeViewerComponent, &mut Visibility, &ComputerNode), Without<TerminalComponent>>,
+    mut terminals : Query<(&mut Visibility, &ComputerNode), With<TerminalComponent>>,
+    mut started_events : EventReader<CartridgeStartedEvent>,
+    mut mark_dirty : EventWriter<AsciiMarkDirtyEvent>,
+    cartridges : Res<Assets<Cartridge>>,
+) {
+    for event in started_events.read() {
+        if event.program != CartridgeProgram::Viewer { continue }
+        let Some(cartridge) = cartridges.get(&event.cartridge) else { continue };
+
+        for (mut viewer, mut visibility, node) in viewers.iter_mut() {
+            if node.0 != event.computer { continue }
+            viewer.title = cartridge.title.clone();
+            viewer.text = cartridge.payload.clone();
+            viewer.scroll = 0;
+            *visibility = Visibility::Visible;
+        }
+
+        for (mut visibility, node) in terminals.iter_mut() {
+            if node.0 != event.computer { continue }
+            *visibility = Visibility::Hidden;
+        }
+
+        mark_dirty.send(AsciiMarkDirtyEvent);
+    }
+}
+
+fn cartridge_viewer_input(
+    mut viewers : Query<(&mut CartridgeViewerComponent, &AsciiNode, &InheritedVisibility, &ComputerNode)>,
+    mut key_events : EventReader<KeyboardInput>,
+    mut exit_events : EventWriter<CartridgeExitEvent>,
+    mut mark_dirty : EventWriter<AsciiMarkDirtyEvent>,
+    active_computer : Res<ActiveComputer>,
+) {
+    let pressed_keys = key_events.read()
+        .filter(|event| event.state == ButtonState::Pressed)
+        .map(|event| event.key_code)
+        .collect::<Vec<_>>();
+
+    for (mut viewer, node, visibility, computer) in viewers.iter_mut() {
+        if !visibility.get() || !active_computer.is(computer.0) { continue }
+
+        let page_height = node.bounds.height.saturating_sub(6) as usize;
+        let line_count = viewer_lines(&viewer.text, node.bounds.width.saturating_sub(6)).len();
+        let max_scroll = line_count.saturating_sub(page_height);
+
+        for key in pressed_keys.iter() {
+            match key {
+                KeyCode::Space | KeyCode::PageDown => viewer.scroll = (viewer.scroll + page_height).min(max_scroll),
+                KeyCode::PageUp => viewer.scroll = viewer.scroll.saturating_sub(page_height),
+                KeyCode::ArrowDown => viewer.scroll = (viewer.scroll + 1).min(max_scroll),
+                KeyCode::ArrowUp => viewer.scroll = viewer.scroll.saturating_sub(1),
+                KeyCode::KeyQ => { exit_events.send(CartridgeExitEvent(computer.0)); },
+                _ => continue,
+            }
+            mark_dirty.send(AsciiMarkDirtyEvent);
+        }
+    }
+}
+
+/// Whatever program was running, the OS gets the screen back when a cartridge exits.
+fn return_to_terminal(
+    mut viewers : Query<(&mut Visibility, &ComputerNode), (With<CartridgeViewerComponent>, Without<TerminalComponent>)>,
+    mut terminals : Query<(&mut TerminalComponent, &mut Visibility, &ComputerNode), (With<FocusedTerminal>, Without<CartridgeViewerComponent>)>,
+    mut exit_events : EventReader<CartridgeExitEvent>,
+    mut mark_dirty : EventWriter<AsciiMarkDirtyEvent>,
+) {
+    for CartridgeExitEvent(computer) in exit_events.read() {
+        for (mut visibility, node) in viewers.iter_mut() {
+            if node.0 != *computer { continue }
+            *visibility = Visibility::Hidden;
+        }
+
+        for (mut terminal, mut visibility, node) in terminals.iter_mut() {
+            if node.0 != *computer { continue }
+            terminal.add_line("Cartridge ejected.");
+            *visibility = Visibility::Visible;
+        }
+
+        mark_dirty.send(AsciiMarkDirtyEvent);
+    }
+}
@@ -1,9 +1,10 @@
-use bevy::prelude::*;
-use bevy_ascii::prelude::AsciiNode;
+use std::collections::BTreeMap;
 
-use crate::{camera::{blackout::BlackoutTransition, path::CameraPathFollower, zone::{CameraZone, CameraZoneAction, CurrentZone}}, game::{ActiveCamera, ToggleGameWorldEvent}};
+use bevy::{ecs::system::SystemId, prelude::*};
 
-use super::terminal::TerminalComponent;
+use crate::{camera::{blackout::BlackoutTransition, path::CameraPathFollower, zone::{CameraZone, CurrentZone, Inspecting}}, game::ToggleGameWorldEvent, os::fs::{FsError, VirtualFs}, scene::computer_world::{ComputerNode, ComputerPowerEvent}};
+
+use super::{director::{OverrideResult, TerminalDirector}, markup::escape_markup, parser::{parse_args, parse_command_line, tokenize, Pipeline, Redirect, Sequence, TerminalArgError, TerminalArgKind, TerminalArgs, Token}, terminal::{TerminalComponent, TerminalForeground}};
 
 //==============================================================================
 //         Commands Plungin
@@ -15,8 +16,16 @@ impl Plugin for TerminalCommandPlugin {
     fn build(&self, app: &mut App) {
         app
             .add_systems(PostUpdate, on_terminal_command)
-        
+
+            .init_resource::<TerminalCommandRegistry>()
+            .init_resource::<TerminalOutput>()
+
             .add_event::<TerminalCommandEvent>()
+
+            .register_terminal_command(TerminalCommandInfo::new("help", "display this message"), help_command)
+            .register_terminal_command(TerminalCommandInfo::new("clear", "clear the terminal"), clear_command)
+            .register_terminal_command(TerminalCommandInfo::new("exit", "leave the computer"), exit_command)
+            .register_terminal_command(TerminalCommandInfo::new("off", "shut down the computer"), off_command)
         ;
     }
 }
@@ -25,53 +34,457 @@ impl Plugin for TerminalCommandPlugin {
 //         Terminal Command Event
 //==============================================================================
 
+/// A line entered at the shell prompt of `terminal`.
 #[derive(Event)]
-pub struct TerminalCommandEvent(pub String);
+pub struct TerminalCommandEvent {
+    pub terminal : Entity,
+    pub line : String,
+}
 
-pub fn on_terminal_command(
-    mut commands: Commands,
-    mut room_camera : Query<(Entity, &Camera, &GlobalTransform, &mut CurrentZone)>,
-    mut terminal : Query<(&mut TerminalComponent, &AsciiNode, &InheritedVisibility)>,
-    mut reader: EventReader<TerminalCommandEvent>,
-    mut world_event : EventWriter<ToggleGameWorldEvent>,
-    mut fade_event : EventWriter<BlackoutTransition>,
-    comp_camera : Query<Entity, With<ActiveCamera>>,
-    camera_zones : Query<(Entity, &CameraZone, &Transform, Option<&Children>)>,
-) {
-    let Ok((mut terminal, node, visibility)) = terminal.get_single_mut() else { return };
+//==============================================================================
+//         Terminal Command Info
+//==============================================================================
+
+/// A single positional argument that a terminal command accepts. A variadic
+/// argument swallows every remaining word and must be the last one.
+#[derive(Debug, Clone)]
+pub struct TerminalArgSpec {
+    pub name : &'static str,
+    pub kind : TerminalArgKind,
+    pub required : bool,
+    pub variadic : bool,
+    pub choices : Vec<&'static str>,
+}
+
+/// An option such as `-v` / `--verbose`, or `--name=x` when it takes a value.
+#[derive(Debug, Clone)]
+pub struct TerminalFlagSpec {
+    pub short : Option<char>,
+    pub long : &'static str,
+    pub takes_value : bool,
+}
+
+/// Describes a command for the registry: its name, the text shown by `help`
+/// and the arguments it expects.
+#[derive(Debug, Clone)]
+pub struct TerminalCommandInfo {
+    pub name : &'static str,
+    pub description : &'static str,
+    pub args : Vec<TerminalArgSpec>,
+    pub flags : Vec<TerminalFlagSpec>,
+}
+
+impl TerminalCommandInfo {
+    pub fn new(name : &'static str, description : &'static str) -> Self {
+        Self { name, description, args : Vec::new(), flags : Vec::new() }
+    }
+
+    pub fn with_arg(mut self, name : &'static str, kind : TerminalArgKind) -> Self {
+        self.args.push(TerminalArgSpec { name, kind, required : true, variadic : false, choices : Vec::new() });
+        self
+    }
+
+    pub fn with_optional_arg(mut self, name : &'static str, kind : TerminalArgKind) -> Self {
+        self.args.push(TerminalArgSpec { name, kind, required : false, variadic : false, choices : Vec::new() });
+        self
+    }
+
+    pub fn with_variadic_arg(mut self, name : &'static str, kind : TerminalArgKind, required : bool) -> Self {
+        self.args.push(TerminalArgSpec { name, kind, required, variadic : true, choices : Vec::new() });
+        self
+    }
+
+    /// Offers `choices` (usually subcommands) when tab completing the last argument added.
+    pub fn with_choices(mut self, choices : &[&'static str]) -> Self {
+        if let Some(arg) = self.args.last_mut() {
+            arg.choices.extend_from_slice(choices);
+        }
+        self
+    }
+
+    pub fn with_flag(mut self, short : Option<char>, long : &'static str) -> Self {
+        self.flags.push(TerminalFlagSpec { short, long, takes_value : false });
+        self
+    }
+
+    pub fn with_value_flag(mut self, short : Option<char>, long : &'static str) -> Self {
+        self.flags.push(TerminalFlagSpec { short, long, takes_value : true });
+        self
+    }
+
+    pub fn usage(&self) -> String {
+        let usage = self.flags.iter().fold(self.name.to_string(), |accum, flag| {
+            let value = if flag.takes_value { format!("={}", flag.long) } else { String::new() };
+            match flag.short {
+                Some(short) => format!("{} [-{}|--{}{}]", accum, short, flag.long, value),
+                None => format!("{} [--{}{}]", accum, flag.long, value),
+            }
+        });
+
+        self.args.iter().fold(usage, |accum, arg| {
+            let dots = if arg.variadic { "..." } else { "" };
+            if arg.required {
+                format!("{} <{}>{}", accum, arg.name, dots)
+            } else {
+                format!("{} [{}]{}", accum, arg.name, dots)
+            }
+        })
+    }
+}
+
+//==============================================================================
+//         Terminal Command Registry
+//==============================================================================
+
+/// What a command handler system receives when its command is run.
+#[derive(Debug, Clone)]
+pub struct TerminalCommandInput {
+    pub terminal : Entity,
+    pub name : String,
+    pub args : TerminalArgs,
+    /// What the command before it in a pipeline printed, as markup lines.
+    pub stdin : Option<Vec<String>>,
+}
+
+/// What a completer system receives when the player presses tab inside one of
+/// a command's arguments. `prefix` is the part of the word before the caret.
+#[derive(Debug, Clone)]
+pub struct TerminalCompletionInput {
+    pub terminal : Entity,
+    pub command : String,
+    pub arg_index : usize,
+    pub kind : Option<TerminalArgKind>,
+    pub prefix : String,
+}
+
+pub type TerminalCompleter = SystemId<TerminalCompletionInput, Vec<String>>;
+
+pub struct RegisteredTerminalCommand {
+    pub info : TerminalCommandInfo,
+    pub handler : SystemId<TerminalCommandInput>,
+    pub completer : Option<TerminalCompleter>,
+}
+
+#[derive(Resource, Default)]
+pub struct TerminalCommandRegistry {
+    commands : BTreeMap<String, RegisteredTerminalCommand>,
+    path_completer : Option<TerminalCompleter>,
+}
+
+impl TerminalCommandRegistry {
+    pub fn path_completer(&self) -> Option<TerminalCompleter> {
+        self.path_completer
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.commands.keys().map(|name| name.as_str())
+    }
+
+    pub fn get(&self, name : &str) -> Option<&RegisteredTerminalCommand> {
+        self.commands.get(name)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &TerminalCommandInfo> {
+        self.commands.values().map(|command| &command.info)
+    }
+}
+
+pub trait RegisterTerminalCommand {
+    /// Registers `handler` as a one shot system that is run whenever `info.name` is typed into a terminal.
+    fn register_terminal_command<M>(&mut self, info : TerminalCommandInfo, handler : impl IntoSystem<TerminalCommandInput, (), M> + 'static) -> &mut Self;
+    
+    /// Registers a system that suggests completions for the arguments of an already registered command.
+    fn register_terminal_completer<M>(&mut self, name : &'static str, completer : impl IntoSystem<TerminalCompletionInput, Vec<String>, M> + 'static) -> &mut Self;
+    
+    /// Registers the system used to complete every argument of kind [`TerminalArgKind::Path`].
+    fn register_path_completer<M>(&mut self, completer : impl IntoSystem<TerminalCompletionInput, Vec<String>, M> + 'static) -> &mut Self;
+}
+
+impl RegisterTerminalCommand for App {
+    fn register_terminal_command<M>(&mut self, info : TerminalCommandInfo, handler : impl IntoSystem<TerminalCommandInput, (), M> + 'static) -> &mut Self {
+        let handler = self.world.register_system(handler);
+        let mut registry = self.world.get_resource_or_insert_with(TerminalCommandRegistry::default);
+        registry.commands.insert(info.name.to_string(), RegisteredTerminalCommand { info, handler, completer : None });
+        self
+    }
     
-    for command in reader.read() {
-        match command.0.as_str() {
-            "help" => {
-                terminal.add_line("", node.bounds.width - 6);
-                terminal.add_line("Available commands:", node.bounds.width - 6);
-                terminal.add_line("  help - display this message", node.bounds.width - 6);
-                terminal.add_line("  clear - clear the terminal", node.bounds.width - 6);
-                terminal.add_line("  exit - leave the computer", node.bounds.width - 6);
-                terminal.add_line("  off - turn the computer off", node.bounds.width - 6);
-                terminal.add_line("  load - loads program off of cartridge.", node.bounds.width - 6);
-                terminal.add_line("", node.bounds.width - 6);
-            },
-            "clear" => {
-                terminal.lines.clear();
-            },
-            "exit" => {
-                let Ok((cam_entity, camera, transform, mut current_zone)) = room_camera.get_single_mut() else {return};
-                let Ok((zone_entity, zone, zone_transform, children)) = camera_zones.get(current_zone.0) else {return};
-                
-                let CameraZoneAction::Move(target_transform) = zone.event else {return};
-                
-                world_event.send(ToggleGameWorldEvent);
-                fade_event.send(BlackoutTransition::fade_in(0.5));
-                
-                commands.entity(cam_entity).insert(CameraPathFollower::to_transform(
-                    target_transform, 
-                    0.5
-                ));
+    fn register_terminal_completer<M>(&mut self, name : &'static str, completer : impl IntoSystem<TerminalCompletionInput, Vec<String>, M> + 'static) -> &mut Self {
+        let completer = self.world.register_system(completer);
+        let mut registry = self.world.get_resource_or_insert_with(TerminalCommandRegistry::default);
+        match registry.commands.get_mut(name) {
+            Some(command) => command.completer = Some(completer),
+            None => warn!("Tried to register a completer for unknown terminal command '{}'", name),
+        }
+        self
+    }
+    
+    fn register_path_completer<M>(&mut self, completer : impl IntoSystem<TerminalCompletionInput, Vec<String>, M> + 'static) -> &mut Self {
+        let completer = self.world.register_system(completer);
+        self.world.get_resource_or_insert_with(TerminalCommandRegistry::default).path_completer = Some(completer);
+        self
+    }
+}
+
+//==============================================================================
+//         Terminal Output
+//==============================================================================
+
+/// Where the command being run prints. Handlers write here rather than to the
+/// terminal, so the shell can pipe what they say into the next command or
+/// redirect it into a file. Lines are kept as markup, the same as file contents.
+#[derive(Resource, Debug, Default)]
+pub struct TerminalOutput {
+    /// Everything printed, in the order it was printed.
+    lines : Vec<OutputLine>,
+}
+
+#[derive(Debug, Clone)]
+enum OutputLine {
+    Text(String),
+    Error(String),
+}
+
+impl TerminalOutput {
+    /// Prints text exactly as written. Anything that looks like markup is escaped.
+    pub fn add_line(&mut self, line : &str) {
+        self.add_markup_line(&escape_markup(line));
+    }
+
+    /// Prints text with color and attribute markup, see [`super::markup::parse_markup`].
+    pub fn add_markup_line(&mut self, line : &str) {
+        self.lines.push(OutputLine::Text(line.to_string()));
+    }
+
+    /// Prints text in red. Errors always go to the screen, never down a pipe,
+    /// and a command that prints one counts as having failed for `&&`.
+    pub fn add_error_line(&mut self, line : &str) {
+        self.lines.push(OutputLine::Error(format!("[c=red]{}[/c]", escape_markup(line))));
+    }
+
+    pub fn failed(&self) -> bool {
+        self.lines.iter().any(|line| matches!(line, OutputLine::Error(_)))
+    }
+
+    /// What gets piped into the next command or redirected into a file.
+    fn text(&self) -> Vec<String> {
+        self.lines.iter().filter_map(|line| match line {
+            OutputLine::Text(text) => Some(text.clone()),
+            OutputLine::Error(_) => None,
+        }).collect()
+    }
+
+    fn errors(&self) -> Vec<String> {
+        self.lines.iter().filter_map(|line| match line {
+            OutputLine::Error(error) => Some(error.clone()),
+            OutputLine::Text(_) => None,
+        }).collect()
+    }
+
+    /// Everything, as it would read on screen.
+    fn screen(&self) -> Vec<String> {
+        self.lines.iter().map(|line| match line {
+            OutputLine::Text(text) | OutputLine::Error(text) => text.clone(),
+        }).collect()
+    }
+}
+
+//==============================================================================
+//         Terminal Command Dispatch
+//==============================================================================
+
+/// Runs every command line entered this frame. The commands of a line run one
+/// after the other, straight away, so each sees what the one before it did.
+pub fn on_terminal_command(world : &mut World) {
+    let events = world.resource_mut::<Events<TerminalCommandEvent>>().drain().collect::<Vec<_>>();
+
+    for event in events {
+        run_command_line(world, event.terminal, &event.line);
+    }
+}
+
+fn run_command_line(world : &mut World, terminal : Entity, line : &str) {
+    let pipelines = match tokenize(line).and_then(parse_command_line) {
+        Ok(pipelines) => pipelines,
+        Err(error) => {
+            let mut output = TerminalOutput::default();
+            output.add_error_line(&format!("?Syntax error: {}", error));
+            print_output(world, terminal, &output.errors());
+            return;
+        }
+    };
+
+    let mut succeeded = true;
+    for (sequence, pipeline) in pipelines {
+        if sequence == Sequence::IfSucceeded && !succeeded { continue }
+        succeeded = run_pipeline(world, terminal, pipeline);
+
+        // A program has taken over the terminal, so the rest of the line is dropped.
+        if world.get::<TerminalForeground>(terminal).is_some() { break }
+    }
+}
+
+/// Feeds the output of each command into the next. Returns whether the last one succeeded.
+fn run_pipeline(world : &mut World, terminal : Entity, pipeline : Pipeline) -> bool {
+    let mut stdin = None;
+    let mut succeeded = true;
+
+    let mut stages = pipeline.stages.into_iter().peekable();
+    while let Some(tokens) = stages.next() {
+        let output = run_command(world, terminal, tokens, stdin.take());
+        succeeded = !output.failed();
+
+        // The last command prints straight to the screen, so its errors stay between the lines around them.
+        if stages.peek().is_none() && pipeline.redirect.is_none() {
+            print_output(world, terminal, &output.screen());
+            return succeeded;
+        }
+        print_output(world, terminal, &output.errors());
+        stdin = Some(output.text());
+    }
+
+    let lines = stdin.unwrap_or_default();
+    let Some(redirect) = pipeline.redirect else { return succeeded };
+
+    if let Err(error) = redirect_output(world, terminal, &redirect, &lines) {
+        let mut output = TerminalOutput::default();
+        output.add_error_line(&format!("?Redirect: {}", error));
+        print_output(world, terminal, &output.errors());
+        return false;
+    }
+    succeeded
+}
+
+fn run_command(world : &mut World, terminal : Entity, tokens : Vec<Token>, stdin : Option<Vec<String>>) -> TerminalOutput {
+    let mut output = TerminalOutput::default();
+    let Some(name) = tokens.first().map(|token| token.text.to_lowercase()) else { return output };
+
+    // The story gets first say over what a command does.
+    let tokens = match world.resource_mut::<TerminalDirector>().take_override(terminal, &name) {
+        Some(OverrideResult::Refuse(message)) => { output.add_error_line(&message); return output },
+        Some(OverrideResult::Respond(lines)) => {
+            for line in lines.iter() {
+                output.add_markup_line(line);
+            }
+            return output;
+        },
+        Some(OverrideResult::Replace(line)) => {
+            // Stands in for one command, so it can't be a whole pipeline.
+            let tokens = tokenize(&line).and_then(|tokens| match tokens.iter().find(|token| token.operator) {
+                Some(operator) => Err(TerminalArgError::UnexpectedOperator(operator.text.clone())),
+                None => Ok(tokens),
+            });
+            match tokens {
+                Ok(tokens) => tokens,
+                Err(error) => { output.add_error_line(&format!("?Syntax error: {}", error)); return output },
             }
-            _ => {
-                terminal.add_line(&format!("Unknown command: {}", command.0), node.bounds.width - 6);
+        },
+        None => tokens,
+    };
+
+    let Some((name, tokens)) = tokens.split_first() else { return output };
+    let name = name.text.to_lowercase();
+
+    let (handler, args) = {
+        let registry = world.resource::<TerminalCommandRegistry>();
+        let Some(registered) = registry.get(&name) else {
+            output.add_error_line(&format!("Unknown command: {}", name));
+            return output;
+        };
+
+        match parse_args(&registered.info, tokens) {
+            Ok(args) => (registered.handler, args),
+            Err(error) => {
+                output.add_error_line(&format!("?{}: {}", registered.info.name, error));
+                output.lines.push(OutputLine::Error(escape_markup(&format!("Usage: {}", registered.info.usage()))));
+                return output;
             }
         }
+    };
+
+    *world.resource_mut::<TerminalOutput>() = TerminalOutput::default();
+    if let Err(error) = world.run_system_with_input(handler, TerminalCommandInput { terminal, name, args, stdin }) {
+        warn!("Terminal command failed: {:?}", error);
+    }
+    std::mem::take(&mut *world.resource_mut::<TerminalOutput>())
+}
+
+fn print_output(world : &mut World, terminal : Entity, lines : &[String]) {
+    let Some(mut terminal) = world.get_mut::<TerminalComponent>(terminal) else { return };
+    for line in lines {
+        terminal.add_markup_line(line);
     }
-}
\ No newline at end of file
+}
+
+fn redirect_output(world : &mut World, terminal : Entity, redirect : &Redirect, lines : &[String]) -> Result<(), FsError> {
+    let Some(terminal) = world.get::<TerminalComponent>(terminal) else { return Ok(()) };
+    let user = terminal.user().to_string();
+    let (path, append) = match redirect {
+        Redirect::Overwrite(path) => (path, false),
+        Redirect::Append(path) => (path, true),
+    };
+    let path = VirtualFs::resolve(&terminal.working_directory, path);
+
+    let text = lines.iter().map(|line| format!("{}\n", line)).collect::<String>();
+    let mut filesystem = world.resource_mut::<VirtualFs>();
+    match append {
+        true => filesystem.append(&path, &text, &user),
+        false => filesystem.write(&path, &text, &user),
+    }
+}
+
+//==============================================================================
+//         Built-in Commands
+//==============================================================================
+
+fn help_command(
+    In(_) : In<TerminalCommandInput>,
+    mut output : ResMut<TerminalOutput>,
+    registry : Res<TerminalCommandRegistry>,
+) {
+    output.add_line("");
+    output.add_line("Available commands:");
+    for info in registry.iter() {
+        output.add_line(&format!("  {} - {}", info.usage(), info.description));
+    }
+    output.add_line("");
+}
+
+fn clear_command(
+    In(input) : In<TerminalCommandInput>,
+    mut terminals : Query<&mut TerminalComponent>,
+) {
+    let Ok(mut terminal) = terminals.get_mut(input.terminal) else { return };
+    terminal.scrollback.clear();
+}
+
+fn off_command(
+    In(input) : In<TerminalCommandInput>,
+    mut power_events : EventWriter<ComputerPowerEvent>,
+    terminals : Query<&ComputerNode>,
+) {
+    let Ok(node) = terminals.get(input.terminal) else { return };
+    power_events.send(ComputerPowerEvent::shutdown(node.0));
+}
+
+fn exit_command(
+    In(_) : In<TerminalCommandInput>,
+    mut commands: Commands,
+    room_camera : Query<(Entity, &CurrentZone)>,
+    mut world_event : EventWriter<ToggleGameWorldEvent>,
+    mut fade_event : EventWriter<BlackoutTransition>,
+    camera_zones : Query<&CameraZone>,
+) {
+    let Ok((cam_entity, current_zone)) = room_camera.get_single() else {return};
+    let Ok(zone) = camera_zones.get(current_zone.0) else {return};
+
+    let Some(target_transform) = zone.event.camera_pose() else {return};
+
+    world_event.send(ToggleGameWorldEvent);
+    fade_event.send(BlackoutTransition::fade_in(0.5));
+
+    // Sitting down is a closer look at the screen, so standing up ends it.
+    commands.entity(cam_entity).insert(CameraPathFollower::to_transform(
+        target_transform,
+        0.5
+    )).remove::<Inspecting>();
+}
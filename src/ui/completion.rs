@@ -0,0 +1,183 @@
+use bevy::prelude::*;
+use bevy_ascii::prelude::*;
+
+use super::{commands::{TerminalCommandRegistry, TerminalCompletionInput, TerminalFlagSpec}, parser::TerminalArgKind, terminal::TerminalComponent};
+
+//==============================================================================
+//         Completion Plugin
+//==============================================================================
+
+pub struct TerminalCompletionPlugin;
+
+impl Plugin for TerminalCompletionPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_systems(PostUpdate, complete_terminal_input)
+
+            .add_event::<TerminalCompletionEvent>()
+        ;
+    }
+}
+
+//==============================================================================
+//         Completion Event
+//==============================================================================
+
+/// Sent when tab is pressed in a terminal. The word under the caret is completed
+/// inline, or the candidates are printed when there is more than one.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct TerminalCompletionEvent {
+    pub terminal : Entity,
+}
+
+//==============================================================================
+//         Completion Systems
+//==============================================================================
+
+/// The word being completed and the words typed before it.
+struct CompletionContext {
+    words : Vec<String>,
+    start : usize,
+    prefix : String,
+}
+
+impl CompletionContext {
+    fn new(line : &str, caret : usize) -> Self {
+        let before_caret = line.chars().take(caret).collect::<String>();
+        let start = before_caret.rfind(char::is_whitespace).map(|index| before_caret[..index].chars().count() + 1).unwrap_or(0);
+        let prefix = before_caret.chars().skip(start).collect::<String>();
+        let mut words = before_caret.chars().take(start).collect::<String>().split_whitespace().map(|word| word.to_string()).collect::<Vec<_>>();
+
+        // After a pipe or sequence operator a new command starts.
+        if let Some(operator) = words.iter().rposition(|word| matches!(word.as_str(), "|" | ";" | "&&")) {
+            words.drain(..=operator);
+        }
+
+        Self { words, start, prefix }
+    }
+}
+
+fn complete_terminal_input(world : &mut World) {
+    let events = world.resource_mut::<Events<TerminalCompletionEvent>>().drain().collect::<Vec<_>>();
+
+    for event in events {
+        let Some(terminal) = world.get::<TerminalComponent>(event.terminal) else { continue };
+        let context = CompletionContext::new(terminal.input.text(), terminal.input.caret());
+
+        let candidates = completion_candidates(world, event.terminal, &context);
+        apply_completion(world, event.terminal, &context, candidates);
+    }
+}
+
+fn completion_candidates(world : &mut World, terminal : Entity, context : &CompletionContext) -> Vec<String> {
+    let (mut candidates, input, completers) = {
+        let registry = world.resource::<TerminalCommandRegistry>();
+        
+        let Some(command_name) = context.words.first() else {
+            return registry.names().filter(|name| name.starts_with(&context.prefix)).map(|name| name.to_string()).collect();
+        };
+        
+        let command_name = command_name.to_lowercase();
+        let Some(command) = registry.get(&command_name) else { return Vec::new() };
+        
+        if context.prefix.starts_with('-') {
+            return command.info.flags.iter()
+                .map(|flag| format!("--{}", flag.long))
+                .filter(|flag| flag.starts_with(&context.prefix))
+                .collect();
+        }
+        
+        // The word after a flag like `--user` is its value, which can't be completed.
+        let Some(arg_index) = arg_index(&context.words[1..], &command.info.flags) else { return Vec::new() };
+        let spec = command.info.args.get(arg_index).or(command.info.args.last().filter(|arg| arg.variadic));
+        let kind = spec.map(|spec| spec.kind);
+        
+        let choices = spec.map(|spec| spec.choices.iter()
+            .filter(|choice| choice.starts_with(&context.prefix))
+            .map(|choice| choice.to_string())
+            .collect::<Vec<_>>()
+        ).unwrap_or_default();
+        
+        let path_completer = if kind == Some(TerminalArgKind::Path) { registry.path_completer() } else { None };
+        
+        let input = TerminalCompletionInput {
+            terminal,
+            command : command_name,
+            arg_index,
+            kind,
+            prefix : context.prefix.clone(),
+        };
+        
+        (choices, input, [command.completer, path_completer])
+    };
+    
+    for completer in completers.into_iter().flatten() {
+        match world.run_system_with_input(completer, input.clone()) {
+            Ok(found) => candidates.extend(found),
+            Err(error) => warn!("Terminal completer failed: {:?}", error),
+        }
+    }
+    
+    candidates.sort();
+    candidates.dedup();
+    candidates
+}
+
+/// How many positional arguments come before the word being completed, the
+/// same way [`super::parser::parse_args`] counts them. `None` when that word
+/// is the value of the flag before it.
+fn arg_index(words : &[String], flags : &[TerminalFlagSpec]) -> Option<usize> {
+    let mut count = 0;
+    let mut only_positional = false;
+    let mut words = words.iter();
+
+    while let Some(word) = words.next() {
+        if only_positional || !word.starts_with('-') || word == "-" || word.parse::<f64>().is_ok() {
+            count += 1;
+        } else if word == "--" {
+            only_positional = true;
+        } else if let Some(long) = word.strip_prefix("--") {
+            let takes_value = flags.iter().any(|flag| flag.long == long && flag.takes_value);
+            if takes_value && words.next().is_none() { return None }
+        } else {
+            // A value flag in a cluster takes the rest of it, or the next word when it is last.
+            let shorts = word[1..].chars().collect::<Vec<_>>();
+            let value_at = shorts.iter().position(|short| flags.iter().any(|flag| flag.short == Some(*short) && flag.takes_value));
+            if value_at == Some(shorts.len() - 1) && words.next().is_none() { return None }
+        }
+    }
+
+    Some(count)
+}
+
+fn apply_completion(world : &mut World, terminal_entity : Entity, context : &CompletionContext, candidates : Vec<String>) {
+    let Some(mut terminal) = world.get_mut::<TerminalComponent>(terminal_entity) else { return };
+
+    match candidates.as_slice() {
+        [] => return,
+        [single] => {
+            // Directories end in a slash so the player can keep typing the rest of the path.
+            let suffix = if single.ends_with('/') { "" } else { " " };
+            terminal.input.replace_before_caret(context.start, &format!("{}{}", single, suffix));
+        },
+        [first, rest @ ..] => {
+            let common = rest.iter().fold(first.clone(), |common, candidate| {
+                common.chars().zip(candidate.chars())
+                    .take_while(|(a, b)| a == b)
+                    .map(|(a, _)| a)
+                    .collect()
+            });
+
+            if common.chars().count() > context.prefix.chars().count() {
+                terminal.input.replace_before_caret(context.start, &common);
+            } else {
+                let prompt = format!("{}{}", terminal.prompt, terminal.input.text());
+                terminal.add_line(&prompt);
+                terminal.add_line(&candidates.join("  "));
+            }
+        },
+    }
+
+    terminal.scrollback.scroll_to_bottom();
+    world.send_event(AsciiMarkDirtyEvent);
+}
@@ -0,0 +1,307 @@
+use std::collections::VecDeque;
+
+use bevy::prelude::*;
+use bevy_ascii::prelude::*;
+
+use crate::{audio::{sound::PlaySoundEvent, AudioAssets}, scene::computer_world::{ComputerNode, ComputerPoweredOffEvent}, util::DelayedEventPlugin};
+
+use super::{commands::TerminalCommandEvent, terminal::{submit_input, TerminalComponent, TerminalForeground, TerminalProgramInputEvent}, typewriter::TerminalTypewriter};
+
+/// Seconds between keys typed by the ghost. A little slower than the player, so it reads as deliberate.
+const GHOST_KEY_INTERVAL : f32 = 0.12;
+
+/// Matches every command in a [`CommandOverride`].
+pub const ANY_COMMAND : &str = "*";
+
+//==============================================================================
+//         Terminal Director Plugin
+//==============================================================================
+
+/// Lets story scripts take over a terminal: type into it, erase what the
+/// player typed, print to it, lock the keyboard and answer commands for it.
+/// Send [`TerminalDirectorEvent`]s, or [`crate::util::DelayedEvent`]s of
+/// them to schedule a scene.
+pub struct TerminalDirectorPlugin;
+
+impl Plugin for TerminalDirectorPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_plugins(DelayedEventPlugin::<TerminalDirectorEvent>::default())
+
+            .add_systems(Update, (direct_terminals, type_ghost_keys).chain())
+            .add_systems(PostUpdate, reset_ghosts)
+
+            .init_resource::<TerminalDirector>()
+
+            .add_event::<TerminalDirectorEvent>()
+        ;
+    }
+}
+
+//==============================================================================
+//         Director Events
+//==============================================================================
+
+#[derive(Debug, Clone)]
+pub enum DirectorAction {
+    /// Types into the input line a key at a time. A `\n` presses enter.
+    Type(String),
+    /// Backspaces away whatever is in the input line, a key at a time.
+    Erase,
+    /// Prints a line of markup through the typewriter.
+    Print(String),
+    /// Takes the keyboard away from the player. They can still scroll.
+    Lock,
+    Unlock,
+    /// Answers a command differently, see [`CommandOverride`].
+    Override(CommandOverride),
+    /// Puts a command back to normal.
+    ClearOverride(String),
+}
+
+#[derive(Event, Debug, Clone)]
+pub struct TerminalDirectorEvent {
+    pub terminal : Entity,
+    pub action : DirectorAction,
+}
+
+impl TerminalDirectorEvent {
+    pub fn type_text(terminal : Entity, text : impl Into<String>) -> Self {
+        Self { terminal, action : DirectorAction::Type(text.into()) }
+    }
+
+    /// Types `line` and presses enter.
+    pub fn run(terminal : Entity, line : &str) -> Self {
+        Self::type_text(terminal, format!("{}\n", line))
+    }
+
+    pub fn erase(terminal : Entity) -> Self {
+        Self { terminal, action : DirectorAction::Erase }
+    }
+
+    pub fn print(terminal : Entity, markup : impl Into<String>) -> Self {
+        Self { terminal, action : DirectorAction::Print(markup.into()) }
+    }
+
+    pub fn lock(terminal : Entity) -> Self {
+        Self { terminal, action : DirectorAction::Lock }
+    }
+
+    pub fn unlock(terminal : Entity) -> Self {
+        Self { terminal, action : DirectorAction::Unlock }
+    }
+
+    pub fn override_command(terminal : Entity, command_override : CommandOverride) -> Self {
+        Self { terminal, action : DirectorAction::Override(command_override) }
+    }
+
+    pub fn clear_override(terminal : Entity, command : impl Into<String>) -> Self {
+        Self { terminal, action : DirectorAction::ClearOverride(command.into()) }
+    }
+}
+
+//==============================================================================
+//         Command Overrides
+//==============================================================================
+
+#[derive(Debug, Clone)]
+pub enum OverrideResult {
+    /// Prints an error instead of running the command.
+    Refuse(String),
+    /// Prints these lines of markup instead of running the command.
+    Respond(Vec<String>),
+    /// Runs this line instead.
+    Replace(String),
+}
+
+/// Changes what a command does on one terminal.
+#[derive(Debug, Clone)]
+pub struct CommandOverride {
+    /// The command name, or [`ANY_COMMAND`].
+    pub command : String,
+    pub result : OverrideResult,
+    /// How many more times it applies. `None` lasts until cleared.
+    pub uses : Option<u32>,
+}
+
+impl CommandOverride {
+    pub fn new(command : impl Into<String>, result : OverrideResult) -> Self {
+        Self { command : command.into(), result, uses : None }
+    }
+
+    pub fn refuse(command : impl Into<String>, message : impl Into<String>) -> Self {
+        Self::new(command, OverrideResult::Refuse(message.into()))
+    }
+
+    pub fn respond(command : impl Into<String>, lines : &[&str]) -> Self {
+        Self::new(command, OverrideResult::Respond(lines.iter().map(|line| line.to_string()).collect()))
+    }
+
+    pub fn replace(command : impl Into<String>, line : impl Into<String>) -> Self {
+        Self::new(command, OverrideResult::Replace(line.into()))
+    }
+
+    pub fn times(mut self, uses : u32) -> Self {
+        self.uses = Some(uses);
+        self
+    }
+
+    fn matches(&self, command : &str) -> bool {
+        self.command == ANY_COMMAND || self.command.eq_ignore_ascii_case(command)
+    }
+}
+
+/// The command overrides in effect, checked by the shell before it runs anything.
+#[derive(Resource, Debug, Default)]
+pub struct TerminalDirector {
+    overrides : Vec<(Entity, CommandOverride)>,
+}
+
+impl TerminalDirector {
+    /// Overrides for the same command on the same terminal replace each other.
+    pub fn add_override(&mut self, terminal : Entity, command_override : CommandOverride) {
+        self.clear_override(terminal, &command_override.command);
+        self.overrides.push((terminal, command_override));
+    }
+
+    pub fn clear_override(&mut self, terminal : Entity, command : &str) {
+        self.overrides.retain(|(entity, other)| *entity != terminal || !other.command.eq_ignore_ascii_case(command));
+    }
+
+    /// What should happen instead of running `command`, using up one use of the override.
+    /// A specific override wins over [`ANY_COMMAND`].
+    pub fn take_override(&mut self, terminal : Entity, command : &str) -> Option<OverrideResult> {
+        let index = self.overrides.iter()
+            .enumerate()
+            .filter(|(_, (entity, other))| *entity == terminal && other.matches(command))
+            .min_by_key(|(_, (_, other))| other.command == ANY_COMMAND)
+            .map(|(index, _)| index)?;
+
+        let (_, command_override) = &mut self.overrides[index];
+        let result = command_override.result.clone();
+        if let Some(uses) = &mut command_override.uses {
+            *uses = uses.saturating_sub(1);
+            if *uses == 0 {
+                self.overrides.remove(index);
+            }
+        }
+        Some(result)
+    }
+}
+
+//==============================================================================
+//         Terminal Ghost
+//==============================================================================
+
+#[derive(Debug, Clone, Copy)]
+enum GhostKey {
+    Character(char),
+    Enter,
+    /// Stays at the front of the queue, backspacing, until the input line is empty.
+    Erase,
+}
+
+/// The unseen typist at a terminal. While it has keys left to press, or has
+/// locked the keyboard, the player can't type.
+#[derive(Component, Debug, Default)]
+pub struct TerminalGhost {
+    keys : VecDeque<GhostKey>,
+    timer : f32,
+    locked : bool,
+}
+
+impl TerminalGhost {
+    pub fn is_blocking(&self) -> bool {
+        self.locked || !self.keys.is_empty()
+    }
+}
+
+//==============================================================================
+//         Director Systems
+//==============================================================================
+
+fn direct_terminals(
+    mut ghosts : Query<(&mut TerminalGhost, &mut TerminalTypewriter)>,
+    mut director_events : EventReader<TerminalDirectorEvent>,
+    mut director : ResMut<TerminalDirector>,
+) {
+    for event in director_events.read() {
+        match &event.action {
+            DirectorAction::Override(command_override) => { director.add_override(event.terminal, command_override.clone()); continue },
+            DirectorAction::ClearOverride(command) => { director.clear_override(event.terminal, command); continue },
+            _ => {},
+        }
+
+        let Ok((mut ghost, mut typewriter)) = ghosts.get_mut(event.terminal) else { continue };
+        match &event.action {
+            DirectorAction::Type(text) => ghost.keys.extend(text.chars().map(|character| match character {
+                '\n' => GhostKey::Enter,
+                character => GhostKey::Character(character),
+            })),
+            DirectorAction::Erase => ghost.keys.push_back(GhostKey::Erase),
+            DirectorAction::Print(markup) => { typewriter.write_markup_line(markup); },
+            DirectorAction::Lock => ghost.locked = true,
+            DirectorAction::Unlock => ghost.locked = false,
+            DirectorAction::Override(_) | DirectorAction::ClearOverride(_) => {},
+        }
+    }
+}
+
+/// Presses the ghost's keys one at a time, through the same path as the player's.
+fn type_ghost_keys(
+    mut ghosts : Query<(Entity, &mut TerminalGhost, &mut TerminalComponent, &TerminalTypewriter, Has<TerminalForeground>)>,
+    mut command_events : EventWriter<TerminalCommandEvent>,
+    mut program_input_events : EventWriter<TerminalProgramInputEvent>,
+    mut sound_effect_events : EventWriter<PlaySoundEvent>,
+    mut mark_dirty : EventWriter<AsciiMarkDirtyEvent>,
+    audio_assets : Res<AudioAssets>,
+    time : Res<Time>,
+) {
+    for (entity, mut ghost, mut terminal, typewriter, has_foreground) in ghosts.iter_mut() {
+        if ghost.keys.is_empty() { continue }
+
+        // Let the computer finish what it is saying before typing over it.
+        if typewriter.is_busy() { continue }
+
+        ghost.timer += time.delta_seconds();
+        if ghost.timer < GHOST_KEY_INTERVAL { continue }
+        ghost.timer = 0.0;
+
+        let Some(key) = ghost.keys.front().copied() else { continue };
+        match key {
+            GhostKey::Character(character) => {
+                ghost.keys.pop_front();
+                terminal.input.insert_str(&character.to_string());
+            },
+            GhostKey::Enter => {
+                ghost.keys.pop_front();
+                submit_input(&mut terminal, entity, has_foreground, &mut command_events, &mut program_input_events);
+            },
+            GhostKey::Erase => {
+                if terminal.input.is_empty() {
+                    ghost.keys.pop_front();
+                    continue;
+                }
+                terminal.input.move_end();
+                terminal.input.backspace();
+            },
+        }
+
+        sound_effect_events.send(PlaySoundEvent::new(audio_assets.computer_light_write.clone(), 0.05, None));
+        terminal.scrollback.scroll_to_bottom();
+        mark_dirty.send(AsciiMarkDirtyEvent);
+    }
+}
+
+/// The ghost loses its grip when the power goes.
+fn reset_ghosts(
+    mut ghosts : Query<(&mut TerminalGhost, &ComputerNode)>,
+    mut powered_off_events : EventReader<ComputerPoweredOffEvent>,
+) {
+    for ComputerPoweredOffEvent(computer) in powered_off_events.read() {
+        for (mut ghost, node) in ghosts.iter_mut() {
+            if node.0 != *computer { continue }
+            *ghost = TerminalGhost::default();
+        }
+    }
+}
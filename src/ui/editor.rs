@@ -0,0 +1,500 @@
+use std::time::Duration;
+
+use bevy::{input::{keyboard::KeyboardInput, ButtonState}, prelude::*};
+use bevy_ascii::prelude::*;
+
+use crate::{os::fs::{FsError, VirtualFs}, scene::computer_world::{ActiveComputer, ComputerNode, ComputerPoweredOffEvent}};
+
+use super::{commands::{RegisterTerminalCommand, TerminalCommandInfo, TerminalCommandInput, TerminalOutput}, parser::TerminalArgKind, terminal::{FocusedTerminal, TerminalComponent, TerminalForeground}};
+
+/// The border around the text, on every side.
+const EDITOR_PADDING : u32 = 3;
+
+//==============================================================================
+//         Editor Component
+//==============================================================================
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+enum EditorMode {
+    #[default]
+    Editing,
+    /// Typing a search query into the status bar.
+    Search(String),
+}
+
+/// The full screen `edit` program. It works on a copy of the file and only
+/// touches the filesystem when the player saves.
+#[derive(Component)]
+pub struct EditorComponent {
+    path : String,
+    /// Who opened the file. Saving is done as them.
+    user : String,
+    lines : Vec<String>,
+    row : usize,
+    column : usize,
+    top : usize,
+    left : usize,
+    overwrite : bool,
+    modified : bool,
+    confirm_quit : bool,
+    mode : EditorMode,
+    last_search : String,
+    status : String,
+    blink_timer : Timer,
+    blink : bool,
+}
+
+impl Default for EditorComponent {
+    fn default() -> Self {
+        Self {
+            path : String::new(),
+            user : String::new(),
+            lines : vec![String::new()],
+            row : 0,
+            column : 0,
+            top : 0,
+            left : 0,
+            overwrite : false,
+            modified : false,
+            confirm_quit : false,
+            mode : EditorMode::Editing,
+            last_search : String::new(),
+            status : String::new(),
+            blink_timer : Timer::new(Duration::from_millis(500), TimerMode::Repeating),
+            blink : true,
+        }
+    }
+}
+
+impl EditorComponent {
+    pub fn open(&mut self, path : &str, user : &str, text : &str, status : &str) {
+        *self = Self {
+            path : path.to_string(),
+            user : user.to_string(),
+            lines : text.lines().map(str::to_string).collect(),
+            status : status.to_string(),
+            ..Default::default()
+        };
+
+        if self.lines.is_empty() {
+            self.lines.push(String::new());
+        }
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// The file as it would be saved. Files always end in a newline unless they are empty.
+    pub fn text(&self) -> String {
+        match self.lines.as_slice() {
+            [line] if line.is_empty() => String::new(),
+            lines => format!("{}\n", lines.join("\n")),
+        }
+    }
+
+    fn line_length(&self, row : usize) -> usize {
+        self.lines[row].chars().count()
+    }
+
+    fn byte_index(line : &str, column : usize) -> usize {
+        line.char_indices().nth(column).map(|(index, _)| index).unwrap_or(line.len())
+    }
+
+    fn edited(&mut self) {
+        self.modified = true;
+        self.confirm_quit = false;
+    }
+
+    fn insert_char(&mut self, character : char) {
+        let line = &mut self.lines[self.row];
+        let index = Self::byte_index(line, self.column);
+
+        if self.overwrite && index < line.len() {
+            let end = Self::byte_index(line, self.column + 1);
+            line.replace_range(index..end, &character.to_string());
+        } else {
+            line.insert(index, character);
+        }
+
+        self.column += 1;
+        self.edited();
+    }
+
+    fn newline(&mut self) {
+        let line = &mut self.lines[self.row];
+        let rest = line.split_off(Self::byte_index(line, self.column));
+        self.lines.insert(self.row + 1, rest);
+        self.row += 1;
+        self.column = 0;
+        self.edited();
+    }
+
+    fn backspace(&mut self) {
+        if self.column > 0 {
+            self.column -= 1;
+            self.delete();
+        } else if self.row > 0 {
+            let line = self.lines.remove(self.row);
+            self.row -= 1;
+            self.column = self.line_length(self.row);
+            self.lines[self.row].push_str(&line);
+            self.edited();
+        }
+    }
+
+    fn delete(&mut self) {
+        if self.column < self.line_length(self.row) {
+            let line = &mut self.lines[self.row];
+            let start = Self::byte_index(line, self.column);
+            let end = Self::byte_index(line, self.column + 1);
+            line.replace_range(start..end, "");
+            self.edited();
+        } else if self.row + 1 < self.lines.len() {
+            let next = self.lines.remove(self.row + 1);
+            self.lines[self.row].push_str(&next);
+            self.edited();
+        }
+    }
+
+    fn move_left(&mut self) {
+        if self.column > 0 {
+            self.column -= 1;
+        } else if self.row > 0 {
+            self.row -= 1;
+            self.column = self.line_length(self.row);
+        }
+    }
+
+    fn move_right(&mut self) {
+        if self.column < self.line_length(self.row) {
+            self.column += 1;
+        } else if self.row + 1 < self.lines.len() {
+            self.row += 1;
+            self.column = 0;
+        }
+    }
+
+    fn move_rows(&mut self, amount : i32) {
+        let row = (self.row as i64 + amount as i64).clamp(0, self.lines.len() as i64 - 1);
+        self.row = row as usize;
+        self.column = self.column.min(self.line_length(self.row));
+    }
+
+    /// Finds `query` after the cursor, wrapping around to the top. Case is ignored.
+    fn find_next(&mut self, query : &str) -> bool {
+        if query.is_empty() { return false }
+        let query = query.to_ascii_lowercase();
+
+        for offset in 0..=self.lines.len() {
+            let row = (self.row + offset) % self.lines.len();
+            let line = self.lines[row].to_ascii_lowercase();
+            let from = if offset == 0 { Self::byte_index(&line, self.column + 1) } else { 0 };
+
+            let found = line.get(from..).and_then(|rest| rest.find(&query)).map(|index| index + from);
+            if let Some(index) = found {
+                self.row = row;
+                self.column = line[..index].chars().count();
+                return true;
+            }
+        }
+
+        false
+    }
+
+    /// Scrolls just enough to keep the cursor on screen.
+    fn scroll_to_cursor(&mut self, width : usize, height : usize) {
+        let (width, height) = (width.max(1), height.max(1));
+
+        if self.row < self.top { self.top = self.row }
+        if self.row >= self.top + height { self.top = self.row + 1 - height }
+        if self.column < self.left { self.left = self.column }
+        if self.column >= self.left + width { self.left = self.column + 1 - width }
+    }
+
+    fn status_line(&self) -> String {
+        if let EditorMode::Search(query) = &self.mode {
+            return format!("Find: {}", query);
+        }
+
+        if !self.status.is_empty() {
+            return self.status.clone();
+        }
+
+        format!(
+            "Ln {}, Col {}  {}  ^S Save  ^Q Quit  ^F Find  F3 Next",
+            self.row + 1,
+            self.column + 1,
+            if self.overwrite { "OVR" } else { "INS" },
+        )
+    }
+}
+
+impl AsciiComponent for EditorComponent {
+    type UpdateQuery<'w, 's> = ();
+
+    fn render(&self, buffer: &mut AsciiBuffer) {
+        {
+            let buffer = buffer.top(3);
+            let modified = if self.modified { " [modified]" } else { "" };
+            buffer.text(&format!("--- EDIT: {}{} ---", self.path, modified))
+                .vertical_alignment(VerticalAlignment::Center)
+                .horizontal_alignment(HorizontalAlignment::Center)
+                .draw();
+        }
+
+        {
+            let footer = buffer.relative(EDITOR_PADDING as i32, buffer.bounds.height as i32 - 2, 1.0, 1);
+            footer.text(&self.status_line())
+                .horizontal_alignment(HorizontalAlignment::Left)
+                .draw();
+        }
+
+        let padding = EDITOR_PADDING as i32;
+        let buffer = buffer.padding((padding, padding, padding, padding)).clip();
+        let height = buffer.bounds.height as usize;
+        let width = buffer.bounds.width as usize;
+
+        for (y, line) in self.lines.iter().skip(self.top).take(height).enumerate() {
+            let row_buffer = buffer.relative(0, y as i32, 1.0, 1);
+            for (x, character) in line.chars().skip(self.left).take(width).enumerate() {
+                row_buffer.set_character(x as i32, 0, character);
+            }
+        }
+
+        if self.blink && self.mode == EditorMode::Editing {
+            let cursor = if self.overwrite { '█' } else { '_' };
+            let x = self.column as i32 - self.left as i32;
+            let y = self.row as i32 - self.top as i32;
+            buffer.relative(0, y, 1.0, 1).set_character(x, 0, cursor);
+        }
+    }
+
+    fn set_up(app: &mut App) {
+        app
+            .add_systems(Update, editor_input)
+            .add_systems(PostUpdate, (close_editor, close_editor_on_power_off))
+
+            .add_event::<CloseEditorEvent>()
+
+            .register_terminal_command(
+                TerminalCommandInfo::new("edit", "edit a text file")
+                    .with_arg("file", TerminalArgKind::Path),
+                edit_command
+            )
+        ;
+    }
+}
+
+/// Sent when the player quits the editor on a computer, handing its screen back to the terminal.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct CloseEditorEvent(pub Entity);
+
+//==============================================================================
+//         Editor Systems
+//==============================================================================
+
+fn edit_command(
+    In(input) : In<TerminalCommandInput>,
+    mut commands : Commands,
+    mut terminals : Query<(&mut TerminalComponent, &mut Visibility, &ComputerNode), Without<EditorComponent>>,
+    mut editors : Query<(&mut EditorComponent, &mut Visibility, &ComputerNode), Without<TerminalComponent>>,
+    mut output : ResMut<TerminalOutput>,
+    mut mark_dirty : EventWriter<AsciiMarkDirtyEvent>,
+    filesystem : Res<VirtualFs>,
+) {
+    let Ok((terminal, _, &computer)) = terminals.get(input.terminal) else { return };
+    let Some(file) = input.args.str(0) else { return };
+    let path = VirtualFs::resolve(&terminal.working_directory, file);
+
+    let (text, status) = match filesystem.read(&path, terminal.user()) {
+        Ok(text) => (text.to_string(), String::new()),
+        Err(FsError::NotFound(_)) => (String::new(), "[ New File ]".to_string()),
+        Err(error) => return output.add_error_line(&format!("edit: {}", error)),
+    };
+
+    for (mut editor, mut visibility, node) in editors.iter_mut() {
+        if *node != computer { continue }
+        editor.open(&path, terminal.user(), &text, &status);
+        *visibility = Visibility::Visible;
+    }
+
+    for (_, mut visibility, node) in terminals.iter_mut() {
+        if *node != computer { continue }
+        *visibility = Visibility::Hidden;
+    }
+
+    // Holds back the rest of the command line until the editor is closed.
+    commands.entity(input.terminal).insert(TerminalForeground { program : "edit".to_string() });
+    mark_dirty.send(AsciiMarkDirtyEvent);
+}
+
+fn editor_input(
+    mut editors : Query<(&mut EditorComponent, &AsciiNode, &InheritedVisibility, &ComputerNode)>,
+    mut char_input : EventReader<ReceivedCharacter>,
+    mut key_events : EventReader<KeyboardInput>,
+    mut close_events : EventWriter<CloseEditorEvent>,
+    mut mark_dirty : EventWriter<AsciiMarkDirtyEvent>,
+    mut filesystem : ResMut<VirtualFs>,
+    active_computer : Res<ActiveComputer>,
+    key_input : Res<ButtonInput<KeyCode>>,
+    time : Res<Time>,
+) {
+    let typed = char_input.read()
+        .flat_map(|event| event.char.as_str().chars().collect::<Vec<_>>())
+        .filter(|character| !character.is_control())
+        .collect::<Vec<_>>();
+
+    // Key repeats arrive as extra pressed events, so holding a key keeps moving.
+    let pressed_keys = key_events.read()
+        .filter(|event| event.state == ButtonState::Pressed)
+        .map(|event| event.key_code)
+        .collect::<Vec<_>>();
+    let ctrl = key_input.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]);
+
+    for (mut editor, node, visibility, computer) in editors.iter_mut() {
+        if !visibility.get() || !active_computer.is(computer.0) { continue }
+
+        editor.blink_timer.tick(time.delta());
+        if editor.blink_timer.finished() {
+            editor.blink = !editor.blink;
+            mark_dirty.send(AsciiMarkDirtyEvent);
+        }
+
+        if typed.is_empty() && pressed_keys.is_empty() { continue }
+
+        let width = node.bounds.width.saturating_sub(EDITOR_PADDING * 2) as usize;
+        let height = node.bounds.height.saturating_sub(EDITOR_PADDING * 2) as usize;
+
+        if let EditorMode::Search(mut query) = editor.mode.clone() {
+            query.extend(typed.iter());
+            for key in pressed_keys.iter() {
+                match key {
+                    KeyCode::Backspace => { query.pop(); },
+                    KeyCode::Enter => {
+                        editor.mode = EditorMode::Editing;
+                        editor.last_search = query.clone();
+                        if !editor.find_next(&query) {
+                            editor.status = format!("\"{}\" not found", query);
+                        }
+                        break;
+                    },
+                    KeyCode::KeyF if ctrl => {
+                        editor.mode = EditorMode::Editing;
+                        break;
+                    },
+                    _ => {},
+                }
+            }
+
+            if let EditorMode::Search(current) = &mut editor.mode {
+                *current = query;
+            }
+        } else {
+            if !typed.is_empty() {
+                editor.status.clear();
+            }
+
+            for character in typed.iter() {
+                editor.insert_char(*character);
+            }
+
+            for key in pressed_keys.iter() {
+                editor.status.clear();
+                match key {
+                    KeyCode::KeyS if ctrl => {
+                        let (path, text) = (editor.path.clone(), editor.text());
+                        editor.status = match filesystem.write(&path, &text, &editor.user) {
+                            Ok(()) => {
+                                editor.modified = false;
+                                format!("Wrote {} lines to {}", editor.lines.len(), path)
+                            },
+                            Err(error) => format!("Can't save: {}", error),
+                        };
+                    },
+                    KeyCode::KeyQ if ctrl => {
+                        if editor.modified && !editor.confirm_quit {
+                            editor.confirm_quit = true;
+                            editor.status = "Unsaved changes! ^Q again to quit without saving.".to_string();
+                        } else {
+                            close_events.send(CloseEditorEvent(computer.0));
+                        }
+                    },
+                    KeyCode::KeyF if ctrl => editor.mode = EditorMode::Search(String::new()),
+                    KeyCode::F3 => {
+                        let query = editor.last_search.clone();
+                        if !editor.find_next(&query) {
+                            editor.status = format!("\"{}\" not found", query);
+                        }
+                    },
+                    KeyCode::Insert => editor.overwrite = !editor.overwrite,
+                    KeyCode::Enter => editor.newline(),
+                    KeyCode::Backspace => editor.backspace(),
+                    KeyCode::Delete => editor.delete(),
+                    KeyCode::ArrowLeft => editor.move_left(),
+                    KeyCode::ArrowRight => editor.move_right(),
+                    KeyCode::ArrowUp => editor.move_rows(-1),
+                    KeyCode::ArrowDown => editor.move_rows(1),
+                    KeyCode::PageUp => editor.move_rows(-(height as i32)),
+                    KeyCode::PageDown => editor.move_rows(height as i32),
+                    KeyCode::Home if ctrl => { editor.row = 0; editor.column = 0; },
+                    KeyCode::End if ctrl => {
+                        editor.row = editor.lines.len() - 1;
+                        editor.column = editor.line_length(editor.row);
+                    },
+                    KeyCode::Home => editor.column = 0,
+                    KeyCode::End => editor.column = editor.line_length(editor.row),
+                    _ => {},
+                }
+            }
+        }
+
+        editor.scroll_to_cursor(width, height);
+        editor.blink = true;
+        editor.blink_timer.reset();
+        mark_dirty.send(AsciiMarkDirtyEvent);
+    }
+}
+
+fn close_editor(
+    mut commands : Commands,
+    mut editors : Query<(&mut Visibility, &ComputerNode), (With<EditorComponent>, Without<TerminalComponent>)>,
+    mut terminals : Query<(Entity, &mut Visibility, &ComputerNode, Option<&TerminalForeground>), (With<FocusedTerminal>, Without<EditorComponent>)>,
+    mut close_events : EventReader<CloseEditorEvent>,
+    mut mark_dirty : EventWriter<AsciiMarkDirtyEvent>,
+) {
+    for CloseEditorEvent(computer) in close_events.read() {
+        for (mut visibility, node) in editors.iter_mut() {
+            if node.0 != *computer { continue }
+            *visibility = Visibility::Hidden;
+        }
+
+        for (entity, mut visibility, node, foreground) in terminals.iter_mut() {
+            if node.0 != *computer { continue }
+            *visibility = Visibility::Visible;
+            if foreground.is_some_and(is_editor) {
+                commands.entity(entity).remove::<TerminalForeground>();
+            }
+        }
+
+        mark_dirty.send(AsciiMarkDirtyEvent);
+    }
+}
+
+/// The screen goes dark with the editor still open, so the terminal gets its shell back.
+fn close_editor_on_power_off(
+    mut commands : Commands,
+    mut powered_off_events : EventReader<ComputerPoweredOffEvent>,
+    terminals : Query<(Entity, &ComputerNode, &TerminalForeground)>,
+) {
+    for ComputerPoweredOffEvent(computer) in powered_off_events.read() {
+        for (entity, node, foreground) in terminals.iter() {
+            if node.0 != *computer || !is_editor(foreground) { continue }
+            commands.entity(entity).remove::<TerminalForeground>();
+        }
+    }
+}
+
+fn is_editor(foreground : &TerminalForeground) -> bool {
+    foreground.program == "edit"
+}
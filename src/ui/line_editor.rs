@@ -0,0 +1,189 @@
+use std::collections::VecDeque;
+
+//==============================================================================
+//         Line Editor
+//==============================================================================
+
+/// The line the player is currently typing, with a movable caret and a
+/// history of previously submitted lines that can be recalled.
+#[derive(Debug, Clone)]
+pub struct LineEditor {
+    text : String,
+    caret : usize,
+    history : VecDeque<String>,
+    history_index : Option<usize>,
+    draft : String,
+    pub history_limit : usize,
+}
+
+impl Default for LineEditor {
+    fn default() -> Self {
+        Self {
+            text : String::new(),
+            caret : 0,
+            history : VecDeque::new(),
+            history_index : None,
+            draft : String::new(),
+            history_limit : 100,
+        }
+    }
+}
+
+impl LineEditor {
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// The caret position in characters from the start of the line.
+    pub fn caret(&self) -> usize {
+        self.caret
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.text.is_empty()
+    }
+
+    fn len(&self) -> usize {
+        self.text.chars().count()
+    }
+
+    fn byte_index(&self, char_index : usize) -> usize {
+        self.text.char_indices().nth(char_index).map(|(index, _)| index).unwrap_or(self.text.len())
+    }
+
+    pub fn set_text(&mut self, text : &str) {
+        self.text = text.to_string();
+        self.caret = self.len();
+    }
+
+    pub fn clear(&mut self) {
+        self.text.clear();
+        self.caret = 0;
+        self.history_index = None;
+    }
+
+    /// Replaces the characters between `start` and the caret with `string`, leaving the caret after it.
+    pub fn replace_before_caret(&mut self, start : usize, string : &str) {
+        let start = start.min(self.caret);
+        let (from, to) = (self.byte_index(start), self.byte_index(self.caret));
+        self.text.replace_range(from..to, string);
+        self.caret = start + string.chars().count();
+    }
+
+    pub fn insert_str(&mut self, string : &str) {
+        let index = self.byte_index(self.caret);
+        self.text.insert_str(index, string);
+        self.caret += string.chars().count();
+    }
+
+    pub fn backspace(&mut self) {
+        if self.caret == 0 { return }
+        self.caret -= 1;
+        let index = self.byte_index(self.caret);
+        self.text.remove(index);
+    }
+
+    pub fn delete(&mut self) {
+        if self.caret >= self.len() { return }
+        let index = self.byte_index(self.caret);
+        self.text.remove(index);
+    }
+
+    pub fn move_left(&mut self) {
+        self.caret = self.caret.saturating_sub(1);
+    }
+
+    pub fn move_right(&mut self) {
+        self.caret = (self.caret + 1).min(self.len());
+    }
+
+    pub fn move_home(&mut self) {
+        self.caret = 0;
+    }
+
+    pub fn move_end(&mut self) {
+        self.caret = self.len();
+    }
+
+    /// Moves the caret to the start of the current or previous word.
+    pub fn move_word_left(&mut self) {
+        let chars = self.text.chars().collect::<Vec<_>>();
+        let mut caret = self.caret;
+        while caret > 0 && chars[caret - 1].is_whitespace() {
+            caret -= 1;
+        }
+        while caret > 0 && !chars[caret - 1].is_whitespace() {
+            caret -= 1;
+        }
+        self.caret = caret;
+    }
+
+    /// Moves the caret to the end of the current or next word.
+    pub fn move_word_right(&mut self) {
+        let chars = self.text.chars().collect::<Vec<_>>();
+        let mut caret = self.caret;
+        while caret < chars.len() && chars[caret].is_whitespace() {
+            caret += 1;
+        }
+        while caret < chars.len() && !chars[caret].is_whitespace() {
+            caret += 1;
+        }
+        self.caret = caret;
+    }
+
+    /// Recalls the previous line from history. The line being typed is kept
+    /// as a draft so it can be returned to with [`LineEditor::history_next`].
+    pub fn history_previous(&mut self) {
+        if self.history.is_empty() { return }
+
+        let index = match self.history_index {
+            None => {
+                self.draft = self.text.clone();
+                self.history.len() - 1
+            },
+            Some(index) => index.saturating_sub(1),
+        };
+
+        self.history_index = Some(index);
+        let line = self.history[index].clone();
+        self.set_text(&line);
+    }
+
+    pub fn history_next(&mut self) {
+        let Some(index) = self.history_index else { return };
+
+        if index + 1 < self.history.len() {
+            self.history_index = Some(index + 1);
+            let line = self.history[index + 1].clone();
+            self.set_text(&line);
+        } else {
+            self.history_index = None;
+            let draft = std::mem::take(&mut self.draft);
+            self.set_text(&draft);
+        }
+    }
+
+    /// Takes the current line and clears the editor, leaving no trace in the history. For passwords.
+    pub fn take(&mut self) -> String {
+        self.caret = 0;
+        self.history_index = None;
+        std::mem::take(&mut self.text)
+    }
+
+    /// Takes the current line, records it in the history and clears the editor.
+    pub fn submit(&mut self) -> String {
+        let line = std::mem::take(&mut self.text);
+        self.caret = 0;
+        self.history_index = None;
+        self.draft.clear();
+
+        if !line.trim().is_empty() && self.history.back() != Some(&line) {
+            self.history.push_back(line.clone());
+            while self.history.len() > self.history_limit {
+                self.history.pop_front();
+            }
+        }
+
+        line
+    }
+}
@@ -0,0 +1,243 @@
+use bevy::prelude::*;
+
+//==============================================================================
+//         Terminal Colors
+//==============================================================================
+
+/// The eight colors a markup tag can name, e.g. `[c=red]`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TerminalColor {
+    Black,
+    Red,
+    Green,
+    Yellow,
+    Blue,
+    Magenta,
+    Cyan,
+    White,
+}
+
+impl TerminalColor {
+    pub fn from_name(name : &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "black" => Some(Self::Black),
+            "red" => Some(Self::Red),
+            "green" => Some(Self::Green),
+            "yellow" => Some(Self::Yellow),
+            "blue" => Some(Self::Blue),
+            "magenta" => Some(Self::Magenta),
+            "cyan" => Some(Self::Cyan),
+            "white" => Some(Self::White),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Black => "black",
+            Self::Red => "red",
+            Self::Green => "green",
+            Self::Yellow => "yellow",
+            Self::Blue => "blue",
+            Self::Magenta => "magenta",
+            Self::Cyan => "cyan",
+            Self::White => "white",
+        }
+    }
+
+    /// Bold text uses the bright variant, like most ANSI terminals do.
+    pub fn to_color(&self, bright : bool) -> Color {
+        let (low, high) = if bright { (0.33, 1.0) } else { (0.0, 0.66) };
+        match self {
+            Self::Black => if bright { Color::rgb(low, low, low) } else { Color::BLACK },
+            Self::Red => Color::rgb(high, low, low),
+            Self::Green => Color::rgb(low, high, low),
+            Self::Yellow => Color::rgb(high, high, low),
+            Self::Blue => Color::rgb(low, low, high),
+            Self::Magenta => Color::rgb(high, low, high),
+            Self::Cyan => Color::rgb(low, high, high),
+            Self::White => Color::rgb(high, high, high),
+        }
+    }
+}
+
+/// The phosphor green of text with no color of its own.
+pub const DEFAULT_FOREGROUND : Color = Color::rgb(0.2, 0.8, 0.2);
+
+/// Bold text with no color of its own.
+pub const BRIGHT_FOREGROUND : Color = Color::rgb(0.5, 1.0, 0.5);
+
+//==============================================================================
+//         Text Attributes
+//==============================================================================
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TextAttributes {
+    pub foreground : Option<TerminalColor>,
+    pub background : Option<TerminalColor>,
+    pub bold : bool,
+    pub inverse : bool,
+    pub blink : bool,
+}
+
+impl TextAttributes {
+    /// The glyph and background colors of a cell, with bold and inverse applied.
+    pub fn cell_colors(&self) -> (Color, Color) {
+        let foreground = match (self.foreground, self.bold) {
+            (Some(color), bold) => color.to_color(bold),
+            (None, true) => BRIGHT_FOREGROUND,
+            (None, false) => DEFAULT_FOREGROUND,
+        };
+        let background = self.background.map(|color| color.to_color(false)).unwrap_or(Color::BLACK);
+
+        match self.inverse {
+            true => (background, foreground),
+            false => (foreground, background),
+        }
+    }
+
+    /// The tags that switch plain text to these attributes.
+    fn open_tags(&self) -> String {
+        let mut tags = String::new();
+        if let Some(color) = self.foreground { tags.push_str(&format!("[c={}]", color.name())) }
+        if let Some(color) = self.background { tags.push_str(&format!("[bg={}]", color.name())) }
+        if self.bold { tags.push_str("[b]") }
+        if self.inverse { tags.push_str("[inv]") }
+        if self.blink { tags.push_str("[blink]") }
+        tags
+    }
+
+    /// Applies a tag such as `c=red` or `/blink`. Returns false for anything
+    /// that is not a tag, so it can be printed as written.
+    fn apply(&mut self, tag : &str) -> bool {
+        match tag.split_once('=') {
+            Some(("c", name)) => match TerminalColor::from_name(name) {
+                Some(color) => self.foreground = Some(color),
+                None => return false,
+            },
+            Some(("bg", name)) => match TerminalColor::from_name(name) {
+                Some(color) => self.background = Some(color),
+                None => return false,
+            },
+            Some(_) => return false,
+            None => match tag {
+                "/" => *self = Self::default(),
+                "/c" => self.foreground = None,
+                "/bg" => self.background = None,
+                "b" => self.bold = true,
+                "/b" => self.bold = false,
+                "inv" => self.inverse = true,
+                "/inv" => self.inverse = false,
+                "blink" => self.blink = true,
+                "/blink" => self.blink = false,
+                _ => return false,
+            },
+        }
+        true
+    }
+}
+
+//==============================================================================
+//         Markup
+//==============================================================================
+
+/// A single character on screen with the attributes it is drawn with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StyledChar {
+    pub character : char,
+    pub attributes : TextAttributes,
+}
+
+/// Parses terminal markup. Tags are `[c=color]`, `[bg=color]`, `[b]`, `[inv]`
+/// and `[blink]`, each closed by the same tag with a slash (`[/c]`), and `[/]`
+/// closes everything. `[[` is a literal `[`.
+pub fn parse_markup(text : &str) -> Vec<StyledChar> {
+    let mut result = Vec::new();
+    let mut attributes = TextAttributes::default();
+    let mut rest = text;
+
+    while let Some(character) = rest.chars().next() {
+        if character == '[' {
+            if rest.starts_with("[[") {
+                result.push(StyledChar { character : '[', attributes });
+                rest = &rest[2..];
+                continue;
+            }
+
+            if let Some(end) = rest.find(']') {
+                if attributes.apply(&rest[1..end]) {
+                    rest = &rest[end + 1..];
+                    continue;
+                }
+            }
+        }
+
+        result.push(StyledChar { character, attributes });
+        rest = &rest[character.len_utf8()..];
+    }
+
+    result
+}
+
+/// Turns styled characters back into markup.
+pub fn to_markup(characters : &[StyledChar]) -> String {
+    let mut result = String::new();
+    let mut attributes = TextAttributes::default();
+
+    for styled in characters {
+        if styled.attributes != attributes {
+            if attributes != TextAttributes::default() {
+                result.push_str("[/]");
+            }
+            result.push_str(&styled.attributes.open_tags());
+            attributes = styled.attributes;
+        }
+
+        match styled.character {
+            '[' => result.push_str("[["),
+            character => result.push(character),
+        }
+    }
+
+    if attributes != TextAttributes::default() {
+        result.push_str("[/]");
+    }
+
+    result
+}
+
+/// Makes text print exactly as written, whatever brackets it contains.
+pub fn escape_markup(text : &str) -> String {
+    text.replace('[', "[[")
+}
+
+/// The text without any of its markup.
+pub fn strip_markup(text : &str) -> String {
+    parse_markup(text).iter().map(|styled| styled.character).collect()
+}
+
+/// Word wraps markup to `width` visible characters. Every line comes back as
+/// complete markup, so a color carries on over a wrapped line.
+pub fn wrap_markup(text : &str, width : usize) -> Vec<String> {
+    let characters = parse_markup(text);
+    let width = width.max(1);
+
+    let mut lines = Vec::new();
+    let mut start = 0;
+    while characters.len() - start > width {
+        let window = &characters[start..=start + width];
+        let split = match window.iter().rposition(|styled| styled.character == ' ') {
+            Some(0) | None => start + width,
+            Some(space) => start + space,
+        };
+
+        lines.push(to_markup(&characters[start..split]));
+        start = split;
+        while characters.get(start).is_some_and(|styled| styled.character == ' ') {
+            start += 1;
+        }
+    }
+    lines.push(to_markup(&characters[start..]));
+
+    lines
+}
@@ -1,11 +1,22 @@
 pub mod boot_screen;
+pub mod boot_sequence;
 pub mod terminal;
 pub mod commands;
+pub mod parser;
+pub mod line_editor;
+pub mod completion;
+pub mod cartridge;
+pub mod editor;
+pub mod markup;
+pub mod scrollback;
+pub mod typewriter;
+pub mod director;
+pub mod arcade;
 
 use bevy::prelude::*;
 use bevy_ascii::prelude::*;
 
-use self::{boot_screen::LoadingScreenComponent, commands::TerminalCommandPlugin, terminal::TerminalComponent};
+use self::{arcade::{ArcadePlugin, GameScreenComponent}, boot_screen::{LoadingScreenComponent, ShutdownScreenComponent}, boot_sequence::BootSequencePlugin, cartridge::CartridgeViewerComponent, editor::EditorComponent, commands::TerminalCommandPlugin, completion::TerminalCompletionPlugin, director::TerminalDirectorPlugin, terminal::TerminalComponent, typewriter::TypewriterPlugin};
 
 //==============================================================================
 //         Ui Plugin
@@ -17,9 +28,18 @@ impl Plugin for UiPlugin {
     fn build(&self, app: &mut App) {
         app
             .add_plugins(TerminalCommandPlugin)
+            .add_plugins(BootSequencePlugin)
+            .add_plugins(TypewriterPlugin)
+            .add_plugins(TerminalCompletionPlugin)
+            .add_plugins(TerminalDirectorPlugin)
+            .add_plugins(ArcadePlugin)
             
             .add_plugins(AsciiComponentPlugin::<LoadingScreenComponent>::default())
             .add_plugins(AsciiComponentPlugin::<TerminalComponent>::default())
+            .add_plugins(AsciiComponentPlugin::<CartridgeViewerComponent>::default())
+            .add_plugins(AsciiComponentPlugin::<GameScreenComponent>::default())
+            .add_plugins(AsciiComponentPlugin::<EditorComponent>::default())
+            .add_plugins(AsciiComponentPlugin::<ShutdownScreenComponent>::default())
         ;
     }
 }
\ No newline at end of file
@@ -0,0 +1,419 @@
+use std::{collections::HashMap, fmt::Display};
+
+use super::commands::TerminalCommandInfo;
+
+//==============================================================================
+//         Tokenizer
+//==============================================================================
+
+/// A single word of a command line. Quoted words are never treated as flags.
+/// Operators (`|`, `>`, `>>`, `;` and `&&`) come out as words of their own,
+/// marked with `operator`, even when typed without spaces around them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Token {
+    pub text : String,
+    pub quoted : bool,
+    pub operator : bool,
+}
+
+/// Splits a command line into words. Single and double quotes group words
+/// together and a backslash escapes the next character.
+pub fn tokenize(line : &str) -> Result<Vec<Token>, TerminalArgError> {
+    let mut tokens = Vec::new();
+    let mut current : Option<Token> = None;
+    let mut quote : Option<char> = None;
+    let mut chars = line.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match (c, quote) {
+            ('\\', Some('\'')) => current.get_or_insert_with(Token::empty).text.push(c),
+            ('\\', _) => {
+                let Some(escaped) = chars.next() else { return Err(TerminalArgError::TrailingEscape) };
+                let token = current.get_or_insert_with(Token::empty);
+                token.text.push(match escaped {
+                    'n' => '\n',
+                    't' => '\t',
+                    other => other,
+                });
+            },
+            (c, Some(open)) if c == open => quote = None,
+            (c, Some(_)) => current.get_or_insert_with(Token::empty).text.push(c),
+            ('"' | '\'', None) => {
+                quote = Some(c);
+                current.get_or_insert_with(Token::empty).quoted = true;
+            },
+            (c, None) if c.is_whitespace() => {
+                if let Some(token) = current.take() {
+                    tokens.push(token);
+                }
+            },
+            ('|' | ';' | '>' | '&', None) => {
+                let operator = match (c, chars.peek()) {
+                    ('>', Some('>')) => { chars.next(); ">>" },
+                    ('&', Some('&')) => { chars.next(); "&&" },
+                    // A lone `&` is just a character.
+                    ('&', _) => { current.get_or_insert_with(Token::empty).text.push(c); continue },
+                    ('|', _) => "|",
+                    (';', _) => ";",
+                    _ => ">",
+                };
+                if let Some(token) = current.take() {
+                    tokens.push(token);
+                }
+                tokens.push(Token { text : operator.to_string(), quoted : false, operator : true });
+            },
+            (c, None) => current.get_or_insert_with(Token::empty).text.push(c),
+        }
+    }
+
+    if let Some(open) = quote {
+        return Err(TerminalArgError::UnterminatedQuote(open));
+    }
+
+    if let Some(token) = current {
+        tokens.push(token);
+    }
+
+    Ok(tokens)
+}
+
+impl Token {
+    fn empty() -> Self {
+        Self { text : String::new(), quoted : false, operator : false }
+    }
+}
+
+//==============================================================================
+//         Command Lines
+//==============================================================================
+
+/// Where the output of a pipeline goes, when not to the screen.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Redirect {
+    /// `> file`
+    Overwrite(String),
+    /// `>> file`
+    Append(String),
+}
+
+/// Commands joined by `|`, each reading what the one before it wrote.
+#[derive(Debug, Clone, Default)]
+pub struct Pipeline {
+    pub stages : Vec<Vec<Token>>,
+    pub redirect : Option<Redirect>,
+}
+
+/// Whether a pipeline runs regardless of how the one before it went (`;`),
+/// or only if it succeeded (`&&`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Sequence {
+    Always,
+    IfSucceeded,
+}
+
+/// Splits tokens into the pipelines of a command line. The first pipeline is
+/// always [`Sequence::Always`].
+pub fn parse_command_line(tokens : Vec<Token>) -> Result<Vec<(Sequence, Pipeline)>, TerminalArgError> {
+    let mut pipelines = Vec::new();
+    let mut sequence = Sequence::Always;
+    let mut pipeline = Pipeline::default();
+    let mut stage = Vec::new();
+    let mut dangling = false;
+    let mut tokens = tokens.into_iter();
+
+    while let Some(token) = tokens.next() {
+        if !token.operator {
+            // Nothing can follow a redirect but the end of the pipeline.
+            if pipeline.redirect.is_some() { return Err(TerminalArgError::UnexpectedOperator(token.text)) }
+            stage.push(token);
+            dangling = false;
+            continue;
+        }
+
+        dangling = token.text == "|" || token.text == "&&";
+
+        match token.text.as_str() {
+            ">" | ">>" => {
+                let Some(target) = tokens.next().filter(|target| !target.operator) else { return Err(TerminalArgError::MissingRedirectTarget) };
+                if stage.is_empty() { return Err(TerminalArgError::UnexpectedOperator(token.text)) }
+                pipeline.stages.push(std::mem::take(&mut stage));
+                pipeline.redirect = Some(match token.text.as_str() {
+                    ">" => Redirect::Overwrite(target.text),
+                    _ => Redirect::Append(target.text),
+                });
+            },
+            "|" => {
+                if stage.is_empty() { return Err(TerminalArgError::UnexpectedOperator(token.text)) }
+                pipeline.stages.push(std::mem::take(&mut stage));
+            },
+            _ => {
+                if !stage.is_empty() {
+                    pipeline.stages.push(std::mem::take(&mut stage));
+                } else if pipeline.redirect.is_none() {
+                    return Err(TerminalArgError::UnexpectedOperator(token.text));
+                }
+                pipelines.push((sequence, std::mem::take(&mut pipeline)));
+                sequence = match token.text.as_str() {
+                    "&&" => Sequence::IfSucceeded,
+                    _ => Sequence::Always,
+                };
+            },
+        }
+    }
+
+    // A trailing `;` is fine, but `|` and `&&` need something after them.
+    if dangling {
+        return Err(TerminalArgError::UnexpectedEnd);
+    }
+
+    if !stage.is_empty() {
+        pipeline.stages.push(stage);
+    }
+    if !pipeline.stages.is_empty() {
+        pipelines.push((sequence, pipeline));
+    }
+
+
+    Ok(pipelines)
+}
+
+//==============================================================================
+//         Argument Types
+//==============================================================================
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TerminalArgKind {
+    Text,
+    Integer,
+    Number,
+    Path,
+}
+
+impl Display for TerminalArgKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TerminalArgKind::Text => write!(f, "text"),
+            TerminalArgKind::Integer => write!(f, "integer"),
+            TerminalArgKind::Number => write!(f, "number"),
+            TerminalArgKind::Path => write!(f, "path"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TerminalArgValue {
+    Text(String),
+    Integer(i64),
+    Number(f64),
+    Path(String),
+}
+
+impl std::fmt::Display for TerminalArgValue {
+    fn fmt(&self, f : &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Text(text) | Self::Path(text) => write!(f, "{}", text),
+            Self::Integer(value) => write!(f, "{}", value),
+            Self::Number(value) => write!(f, "{}", value),
+        }
+    }
+}
+
+impl TerminalArgValue {
+    fn parse(kind : TerminalArgKind, name : &'static str, text : &str) -> Result<Self, TerminalArgError> {
+        let invalid = || TerminalArgError::InvalidValue { name, kind, value : text.to_string() };
+        match kind {
+            TerminalArgKind::Text => Ok(Self::Text(text.to_string())),
+            TerminalArgKind::Integer => text.parse().map(Self::Integer).map_err(|_| invalid()),
+            TerminalArgKind::Number => text.parse().map(Self::Number).map_err(|_| invalid()),
+            TerminalArgKind::Path => Ok(Self::Path(text.to_string())),
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Self::Text(text) | Self::Path(text) => Some(text),
+            _ => None,
+        }
+    }
+
+    pub fn as_integer(&self) -> Option<i64> {
+        match self {
+            Self::Integer(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn as_number(&self) -> Option<f64> {
+        match self {
+            Self::Number(value) => Some(*value),
+            Self::Integer(value) => Some(*value as f64),
+            _ => None,
+        }
+    }
+}
+
+//==============================================================================
+//         Parsed Arguments
+//==============================================================================
+
+/// The arguments of a command after they have been checked against its spec.
+#[derive(Debug, Clone, Default)]
+pub struct TerminalArgs {
+    pub positional : Vec<TerminalArgValue>,
+    pub flags : HashMap<&'static str, Option<String>>,
+}
+
+impl TerminalArgs {
+    pub fn len(&self) -> usize {
+        self.positional.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.positional.is_empty()
+    }
+
+    pub fn get(&self, index : usize) -> Option<&TerminalArgValue> {
+        self.positional.get(index)
+    }
+
+    pub fn str(&self, index : usize) -> Option<&str> {
+        self.get(index).and_then(TerminalArgValue::as_str)
+    }
+
+    pub fn integer(&self, index : usize) -> Option<i64> {
+        self.get(index).and_then(TerminalArgValue::as_integer)
+    }
+
+    pub fn number(&self, index : usize) -> Option<f64> {
+        self.get(index).and_then(TerminalArgValue::as_number)
+    }
+
+    /// Every positional argument from `index` onwards as text, used for variadic arguments.
+    pub fn rest(&self, index : usize) -> Vec<String> {
+        self.positional.iter().skip(index).map(TerminalArgValue::to_string).collect()
+    }
+
+    pub fn has_flag(&self, long : &str) -> bool {
+        self.flags.contains_key(long)
+    }
+
+    pub fn flag_value(&self, long : &str) -> Option<&str> {
+        self.flags.get(long).and_then(|value| value.as_deref())
+    }
+}
+
+//==============================================================================
+//         Argument Errors
+//==============================================================================
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TerminalArgError {
+    UnterminatedQuote(char),
+    TrailingEscape,
+    UnexpectedOperator(String),
+    UnexpectedEnd,
+    MissingRedirectTarget,
+    UnknownFlag(String),
+    MissingFlagValue(&'static str),
+    UnexpectedFlagValue(&'static str),
+    MissingArgument(&'static str),
+    TooManyArguments,
+    InvalidValue { name : &'static str, kind : TerminalArgKind, value : String },
+}
+
+impl Display for TerminalArgError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UnterminatedQuote(quote) => write!(f, "missing closing {}", quote),
+            Self::TrailingEscape => write!(f, "nothing to escape at end of line"),
+            Self::UnexpectedOperator(operator) => write!(f, "unexpected '{}'", operator),
+            Self::UnexpectedEnd => write!(f, "unexpected end of line"),
+            Self::MissingRedirectTarget => write!(f, "missing file to redirect to"),
+            Self::UnknownFlag(flag) => write!(f, "unknown option {}", flag),
+            Self::MissingFlagValue(flag) => write!(f, "option --{} needs a value", flag),
+            Self::UnexpectedFlagValue(flag) => write!(f, "option --{} does not take a value", flag),
+            Self::MissingArgument(name) => write!(f, "missing <{}>", name),
+            Self::TooManyArguments => write!(f, "too many arguments"),
+            Self::InvalidValue { name, kind, value } => write!(f, "<{}> must be a {}, got '{}'", name, kind, value),
+        }
+    }
+}
+
+//==============================================================================
+//         Argument Parsing
+//==============================================================================
+
+/// Matches the tokens after the command name against the command's argument and flag spec.
+pub fn parse_args(info : &TerminalCommandInfo, tokens : &[Token]) -> Result<TerminalArgs, TerminalArgError> {
+    let mut args = TerminalArgs::default();
+    let mut words = Vec::new();
+    let mut only_positional = false;
+    let mut tokens = tokens.iter();
+
+    while let Some(token) = tokens.next() {
+        if only_positional || token.quoted || !token.text.starts_with('-') || token.text == "-" || token.text.parse::<f64>().is_ok() {
+            words.push(token.text.as_str());
+        } else if token.text == "--" {
+            only_positional = true;
+        } else if let Some(long) = token.text.strip_prefix("--") {
+            let (long, value) = match long.split_once('=') {
+                Some((long, value)) => (long, Some(value.to_string())),
+                None => (long, None),
+            };
+            let Some(flag) = info.flags.iter().find(|flag| flag.long == long) else {
+                return Err(TerminalArgError::UnknownFlag(token.text.clone()));
+            };
+            let value = match (flag.takes_value, value) {
+                (true, Some(value)) => Some(value),
+                (true, None) => Some(tokens.next().ok_or(TerminalArgError::MissingFlagValue(flag.long))?.text.clone()),
+                (false, Some(_)) => return Err(TerminalArgError::UnexpectedFlagValue(flag.long)),
+                (false, None) => None,
+            };
+            args.flags.insert(flag.long, value);
+        } else {
+            let shorts = token.text[1..].chars().collect::<Vec<_>>();
+            for (index, short) in shorts.iter().enumerate() {
+                let Some(flag) = info.flags.iter().find(|flag| flag.short == Some(*short)) else {
+                    return Err(TerminalArgError::UnknownFlag(format!("-{}", short)));
+                };
+                if flag.takes_value {
+                    let attached = shorts[index + 1..].iter().collect::<String>();
+                    let value = if attached.is_empty() {
+                        tokens.next().ok_or(TerminalArgError::MissingFlagValue(flag.long))?.text.clone()
+                    } else {
+                        attached
+                    };
+                    args.flags.insert(flag.long, Some(value));
+                    break;
+                }
+                args.flags.insert(flag.long, None);
+            }
+        }
+    }
+
+    let mut words = words.into_iter();
+    for spec in info.args.iter() {
+        if spec.variadic {
+            let before = args.positional.len();
+            for word in words.by_ref() {
+                args.positional.push(TerminalArgValue::parse(spec.kind, spec.name, word)?);
+            }
+            if spec.required && args.positional.len() == before {
+                return Err(TerminalArgError::MissingArgument(spec.name));
+            }
+            break;
+        }
+
+        match words.next() {
+            Some(word) => args.positional.push(TerminalArgValue::parse(spec.kind, spec.name, word)?),
+            None if spec.required => return Err(TerminalArgError::MissingArgument(spec.name)),
+            None => break,
+        }
+    }
+
+    if words.next().is_some() {
+        return Err(TerminalArgError::TooManyArguments);
+    }
+
+    Ok(args)
+}
@@ -0,0 +1,204 @@
+use std::collections::VecDeque;
+
+use super::markup::wrap_markup;
+
+/// How many lines a terminal remembers unless told otherwise.
+pub const DEFAULT_HISTORY_LENGTH : usize = 2000;
+
+//==============================================================================
+//         Scrollback
+//==============================================================================
+
+/// A terminal transcript. Lines are kept as they were written and wrapped to
+/// the current width on demand, so resizing the screen never mangles them.
+/// Scrolling is counted in wrapped rows up from the bottom.
+#[derive(Debug, Clone)]
+pub struct Scrollback {
+    lines : VecDeque<String>,
+    /// How many rows each line wraps to, parallel to `lines`.
+    row_counts : VecDeque<usize>,
+    rows : VecDeque<String>,
+    history_length : Option<usize>,
+    width : usize,
+    /// How many rows are on screen at once, which limits how far back it can scroll.
+    page_height : usize,
+    scroll : usize,
+    /// How many lines have ever been pushed, so a line can be told apart from one that replaced it.
+    pushed : usize,
+}
+
+impl Default for Scrollback {
+    fn default() -> Self {
+        Self::new(Some(DEFAULT_HISTORY_LENGTH))
+    }
+}
+
+impl Scrollback {
+    /// `None` keeps every line ever written.
+    pub fn new(history_length : Option<usize>) -> Self {
+        Self {
+            lines : VecDeque::new(),
+            row_counts : VecDeque::new(),
+            rows : VecDeque::new(),
+            history_length,
+            width : 0,
+            page_height : 0,
+            scroll : 0,
+            pushed : 0,
+        }
+    }
+
+    pub fn history_length(&self) -> Option<usize> {
+        self.history_length
+    }
+
+    pub fn set_history_length(&mut self, history_length : Option<usize>) {
+        self.history_length = history_length;
+        self.trim();
+    }
+
+    /// The lines as they were written, oldest first.
+    pub fn lines(&self) -> impl DoubleEndedIterator<Item = &String> {
+        self.lines.iter()
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Re-wraps every line. A width of 0 leaves lines unwrapped.
+    pub fn set_width(&mut self, width : usize) {
+        if self.width == width { return }
+        self.width = width;
+
+        self.rows.clear();
+        self.row_counts.clear();
+        for index in 0..self.lines.len() {
+            let rows = self.wrap(&self.lines[index]);
+            self.row_counts.push_back(rows.len());
+            self.rows.extend(rows);
+        }
+        self.clamp_scroll();
+    }
+
+    pub fn page_height(&self) -> usize {
+        self.page_height
+    }
+
+    pub fn set_page_height(&mut self, page_height : usize) {
+        self.page_height = page_height;
+        self.clamp_scroll();
+    }
+
+    pub fn push(&mut self, line : String) {
+        let rows = self.wrap(&line);
+
+        // Keep the view where it was if the player is reading back through history.
+        if self.scroll > 0 {
+            self.scroll += rows.len();
+        }
+
+        self.row_counts.push_back(rows.len());
+        self.rows.extend(rows);
+        self.lines.push_back(line);
+        self.pushed += 1;
+        self.trim();
+    }
+
+    /// Identifies the newest line until another one is pushed after it.
+    pub fn last_line_id(&self) -> Option<usize> {
+        (!self.lines.is_empty()).then(|| self.pushed - 1)
+    }
+
+    /// Rewrites the newest line, e.g. while it is still being typed out.
+    pub fn replace_last(&mut self, line : String) {
+        if self.lines.pop_back().is_none() {
+            return self.push(line);
+        }
+
+        let old_count = self.row_counts.pop_back().unwrap_or(0);
+        self.rows.truncate(self.rows.len() - old_count);
+
+        let rows = self.wrap(&line);
+        if self.scroll > 0 {
+            self.scroll = (self.scroll + rows.len()).saturating_sub(old_count);
+        }
+
+        self.row_counts.push_back(rows.len());
+        self.rows.extend(rows);
+        self.lines.push_back(line);
+    }
+
+    pub fn clear(&mut self) {
+        self.lines.clear();
+        self.row_counts.clear();
+        self.rows.clear();
+        self.scroll = 0;
+    }
+
+    pub fn row_count(&self) -> usize {
+        self.rows.len()
+    }
+
+    /// The rows that fit in `height`, oldest first.
+    pub fn visible_rows(&self, height : usize) -> impl DoubleEndedIterator<Item = &String> {
+        let end = self.rows.len() - self.scroll.min(self.rows.len());
+        let start = end.saturating_sub(height);
+        self.rows.range(start..end)
+    }
+
+    /// How many rows up from the bottom the view is.
+    pub fn scroll(&self) -> usize {
+        self.scroll
+    }
+
+    pub fn is_scrolled(&self) -> bool {
+        self.scroll > 0
+    }
+
+    pub fn max_scroll(&self, height : usize) -> usize {
+        self.rows.len().saturating_sub(height)
+    }
+
+    /// Positive amounts scroll back towards older rows.
+    pub fn scroll_by(&mut self, amount : i32, height : usize) {
+        self.page_height = height;
+        let scroll = (self.scroll as i64 + amount as i64).max(0) as usize;
+        self.scroll = scroll.min(self.max_scroll(height));
+    }
+
+    pub fn page_up(&mut self, height : usize) {
+        self.scroll_by(height.saturating_sub(1).max(1) as i32, height);
+    }
+
+    pub fn page_down(&mut self, height : usize) {
+        self.scroll_by(-(height.saturating_sub(1).max(1) as i32), height);
+    }
+
+    pub fn scroll_to_bottom(&mut self) {
+        self.scroll = 0;
+    }
+
+    fn wrap(&self, line : &str) -> Vec<String> {
+        match self.width {
+            0 => vec![line.to_string()],
+            width => wrap_markup(line, width),
+        }
+    }
+
+    fn trim(&mut self) {
+        let Some(history_length) = self.history_length else { return };
+
+        while self.lines.len() > history_length {
+            self.lines.pop_front();
+            let count = self.row_counts.pop_front().unwrap_or(0);
+            self.rows.drain(..count);
+        }
+        self.clamp_scroll();
+    }
+
+    /// Rewrapping or forgetting lines can leave the view past the oldest row.
+    fn clamp_scroll(&mut self) {
+        self.scroll = self.scroll.min(self.max_scroll(self.page_height));
+    }
+}
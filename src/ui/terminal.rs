@@ -1,38 +1,96 @@
-use std::{collections::VecDeque, fmt::format, time::Duration};
+use std::{fmt::format, time::Duration};
 
-use bevy::{input::mouse::MouseWheel, prelude::*};
+use bevy::{input::{keyboard::KeyboardInput, mouse::MouseWheel, ButtonState}, prelude::*};
 use bevy_ascii::prelude::*;
 
-use super::commands::TerminalCommandEvent;
+use crate::{os::{fs::HOME_DIRECTORY, users::NOBODY_USER}, scene::computer_world::{ActiveComputer, ComputerNode, ComputerPoweredOffEvent}};
+
+use super::{commands::TerminalCommandEvent, completion::TerminalCompletionEvent, director::TerminalGhost, line_editor::LineEditor, markup::{escape_markup, parse_markup}, scrollback::Scrollback, typewriter::TerminalTypewriter};
+
+pub const DEFAULT_PROMPT : &str = "User:> ";
+
+/// The border around the transcript, on every side.
+const TERMINAL_PADDING : u32 = 3;
+
+/// Alt plus one of these switches to the virtual console with that index.
+pub const CONSOLE_KEYS : [KeyCode; 4] = [KeyCode::F1, KeyCode::F2, KeyCode::F3, KeyCode::F4];
 
 #[derive(Component)]
 pub struct TerminalComponent {
-    pub lines : VecDeque<String>,
-    pub current_input : String,
-    pub scroll : u32,
+    /// Which virtual console this is, counting from 0.
+    pub console : usize,
+    pub scrollback : Scrollback,
+    pub input : LineEditor,
+    pub prompt : String,
+    /// Hides what is typed, for passwords.
+    pub masked : bool,
+    pub working_directory : String,
+    /// Who is logged in, with anyone they `su`'d to on top. Empty before login.
+    pub users : Vec<String>,
     pub blink_timer : Timer,
     pub blink : bool,
 }
 
 impl TerminalComponent {
-    pub fn add_line(&mut self, line : &str, width : u32) {
-        let lines = bevy_ascii::prelude::break_string_into_lines(line, width as usize);
-        for line in lines {
-            self.lines.push_back(line);
+    pub fn new(console : usize) -> Self {
+        let mut scrollback = Scrollback::default();
+        let banner = format!("G64 Os 1.36.7 (build 10) on {}", Self::console_name(console));
+        for line in [banner.as_str(), "Welcome! Type 'help' for help.", ""] {
+            scrollback.push(line.to_string());
         }
+        
+        Self {
+            console,
+            scrollback,
+            input : LineEditor::default(),
+            prompt : DEFAULT_PROMPT.to_string(),
+            masked : false,
+            working_directory : HOME_DIRECTORY.to_string(),
+            users : Vec::new(),
+            blink_timer : Timer::new(Duration::from_millis(500), TimerMode::Repeating),
+            blink : true,
+        }
+    }
+    
+    /// The name a console goes by on screen, e.g. `tty1`.
+    pub fn console_name(console : usize) -> String {
+        format!("tty{}", console + 1)
+    }
+    
+    /// Who commands run as.
+    pub fn user(&self) -> &str {
+        self.users.last().map(String::as_str).unwrap_or(NOBODY_USER)
+    }
+    
+    /// Adds text exactly as written. Anything that looks like markup is escaped.
+    pub fn add_line(&mut self, line : &str) {
+        self.add_markup_line(&escape_markup(line));
+    }
+    
+    /// Adds text with color and attribute markup, see [`parse_markup`].
+    pub fn add_markup_line(&mut self, line : &str) {
+        self.scrollback.push(line.to_string());
+    }
+    
+    /// Adds text in red, so errors stand out.
+    pub fn add_error_line(&mut self, line : &str) {
+        self.add_markup_line(&format!("[c=red]{}[/c]", escape_markup(line)));
+    }
+    
+    /// How many characters fit on a row of a terminal drawn in `node`.
+    pub fn text_width(node : &AsciiNode) -> usize {
+        node.bounds.width.saturating_sub(TERMINAL_PADDING * 2) as usize
+    }
+    
+    /// How many transcript rows fit in `node`, leaving the blank top row and the prompt.
+    pub fn page_height(node : &AsciiNode) -> usize {
+        node.bounds.height.saturating_sub(TERMINAL_PADDING * 2 + 2) as usize
     }
 }
 
 impl Default for TerminalComponent {
     fn default() -> Self {
-        let lines = ["G64 Os 1.36.7 (build 10)".to_string(), "Welcome! Type 'help' for help.".to_string(), "".to_string()];
-        Self {
-            lines : lines.into(),
-            current_input : String::from(""),
-            scroll : 0,
-            blink_timer : Timer::new(Duration::from_millis(500), TimerMode::Repeating),
-            blink : true,
-        }
+        Self::new(0)
     }
 }
 
@@ -42,62 +100,151 @@ impl AsciiComponent for TerminalComponent {
     fn render(&self, buffer: &mut AsciiBuffer) {
         { 
             let buffer = buffer.top(3);
-            buffer.text("--- Genifore 64 Plus V4 ---")
+            buffer.text(&format!("--- Genifore 64 Plus V4 : {} ---", Self::console_name(self.console)))
                 .vertical_alignment(VerticalAlignment::Center)
                 .horizontal_alignment(HorizontalAlignment::Center)
                 .draw();
         }
         
-        let buffer = buffer.padding((3, 3, 3, 3)).clip();
-        
-        let max_lines = (buffer.bounds.height - 1) as usize;
-        let terminal_history_height = self.lines.len();
+        let padding = TERMINAL_PADDING as i32;
+        let buffer = buffer.padding((padding, padding, padding, padding)).clip();
+        let page_height = buffer.bounds.height.saturating_sub(2) as usize;
         
-        let string = self.lines.iter()
-            .skip((terminal_history_height as i32 - max_lines as i32 - self.scroll as i32).max(0) as usize)
-            .take(max_lines.min(terminal_history_height))
-            .fold(String::new(), |accum, value| format!("{}\n{}", accum, value));
-
-        // println!("{}", (terminal_history_height as i32 - max_lines as i32 - self.scroll as i32));
+        let mut row_count = 0;
+        for (index, row) in self.scrollback.visible_rows(page_height).enumerate() {
+            let row_buffer = buffer.relative(0, index as i32 + 1, 1.0, 1);
+            for (x, styled) in parse_markup(row).iter().enumerate() {
+                // Blinking text goes dark but keeps its background.
+                let character = if styled.attributes.blink && !self.blink { ' ' } else { styled.character };
+                let (foreground, background) = styled.attributes.cell_colors();
+                row_buffer.set_character(x as i32, 0, character);
+                row_buffer.set_color(x as i32, 0, foreground, background);
+            }
+            row_count += 1;
+        }
         
-        buffer.text(&string).draw();
+        let command_line = buffer.relative(0, row_count + 1, 1.0, 1);
         
-        let command_line_y = (terminal_history_height.min(max_lines) as u32) + self.scroll + 1;
+        // Reading back through history, so say how far back instead of showing the prompt.
+        if self.scrollback.is_scrolled() {
+            let max_scroll = self.scrollback.max_scroll(page_height).max(1);
+            let percent = 100usize.saturating_sub(self.scrollback.scroll() * 100 / max_scroll);
+            command_line.text(&format!("-- {} more below ({}%) [PgDn] --", self.scrollback.scroll(), percent))
+                .horizontal_alignment(HorizontalAlignment::Center)
+                .draw();
+            return;
+        }
         
-        let command_line = buffer.relative(0, command_line_y as i32, 1.0, 1);
-        command_line.text(&format!("User:> {}", self.current_input)).horizontal_alignment(HorizontalAlignment::Left).draw();
+        let (input, caret) = match self.masked {
+            true => ("", 0),
+            false => (self.input.text(), self.input.caret()),
+        };
+        command_line.text(&format!("{}{}", self.prompt, input)).horizontal_alignment(HorizontalAlignment::Left).draw();
         if self.blink {
-            command_line.set_character(self.current_input.len() as i32 + 7, 0, '_');
+            command_line.set_character((self.prompt.chars().count() + caret) as i32, 0, '_');
         }
     }
 
     fn set_up(app: &mut App) {
         app
-            .add_systems(Update, terminal_input)
+            .add_systems(Update, (switch_virtual_console, terminal_input).chain())
+            .add_systems(PostUpdate, fit_terminals_to_screen)
+            .add_systems(PostUpdate, reset_terminals)
+            
+            .add_event::<TerminalProgramInputEvent>()
+            .add_event::<TerminalInterruptEvent>()
         ;
     }
 }
 
+//==============================================================================
+//         Terminal Programs
+//==============================================================================
+
+/// Marks a terminal whose input belongs to a running program instead of the
+/// shell. Lines typed into it are sent as [`TerminalProgramInputEvent`]s.
+#[derive(Component, Debug, Clone)]
+pub struct TerminalForeground {
+    pub program : String,
+}
+
+#[derive(Event, Debug, Clone)]
+pub struct TerminalProgramInputEvent {
+    pub terminal : Entity,
+    pub line : String,
+}
+
+/// Sent when Ctrl+C is pressed in a terminal.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct TerminalInterruptEvent {
+    pub terminal : Entity,
+}
+
+//==============================================================================
+//         Virtual Consoles
+//==============================================================================
+
+/// Marks the terminal on each computer that keyboard input goes to. The other
+/// consoles keep running their programs in the background, out of sight.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct FocusedTerminal;
+
+fn switch_virtual_console(
+    mut commands : Commands,
+    mut terminals : Query<(Entity, &TerminalComponent, &mut Visibility, Has<FocusedTerminal>, &ComputerNode)>,
+    mut mark_ui_dirty : EventWriter<AsciiMarkDirtyEvent>,
+    active_computer : Res<ActiveComputer>,
+    key_input : Res<ButtonInput<KeyCode>>,
+) {
+    if !key_input.any_pressed([KeyCode::AltLeft, KeyCode::AltRight]) { return }
+    let Some(console) = CONSOLE_KEYS.iter().position(|key| key_input.just_pressed(*key)) else { return };
+    let Some(computer) = active_computer.0 else { return };
+    
+    // Only while the terminals are on screen, not over the editor or a cartridge.
+    let on_computer = |node : &ComputerNode| node.0 == computer;
+    if !terminals.iter().any(|(_, _, visibility, focused, node)| on_computer(node) && focused && *visibility == Visibility::Visible) { return }
+    if !terminals.iter().any(|(_, terminal, _, _, node)| on_computer(node) && terminal.console == console) { return }
+    
+    for (entity, terminal, mut visibility, _, node) in terminals.iter_mut() {
+        if !on_computer(node) { continue }
+        
+        if terminal.console == console {
+            commands.entity(entity).insert(FocusedTerminal);
+            *visibility = Visibility::Visible;
+        } else {
+            commands.entity(entity).remove::<FocusedTerminal>();
+            *visibility = Visibility::Hidden;
+        }
+    }
+    
+    mark_ui_dirty.send(AsciiMarkDirtyEvent);
+}
+
 //==============================================================================
 //         General Terminal Systems
 //==============================================================================
 
 fn terminal_input (
-    mut terminals : Query<(&mut TerminalComponent, &AsciiNode, &InheritedVisibility)>,
+    mut terminals : Query<(Entity, &mut TerminalComponent, &AsciiNode, &InheritedVisibility, &ComputerNode, Option<&TerminalForeground>, Option<&TerminalTypewriter>, Option<&TerminalGhost>), With<FocusedTerminal>>,
     mut char_input : EventReader<ReceivedCharacter>,
+    mut key_events : EventReader<KeyboardInput>,
     mut mark_ui_dirty : EventWriter<AsciiMarkDirtyEvent>,
     mut terminal_command_event : EventWriter<TerminalCommandEvent>,
+    mut completion_event : EventWriter<TerminalCompletionEvent>,
+    mut program_input_event : EventWriter<TerminalProgramInputEvent>,
+    mut interrupt_event : EventWriter<TerminalInterruptEvent>,
     mut scroll_event : EventReader<MouseWheel>,
+    active_computer : Res<ActiveComputer>,
     key_input : Res<ButtonInput<KeyCode>>,
     time : Res<Time>,
 ) {
-    let Ok((mut terminal, node, visability)) = terminals.get_single_mut() else { return };
+    let Some((terminal_entity, mut terminal, node, visability, _, foreground, typewriter, ghost)) = terminals.iter_mut()
+        .find(|(_, _, _, _, computer, _, _, _)| active_computer.is(computer.0)) else { return };
     if !visability.get() { return }
     
-    let pressed_enter = key_input.just_pressed(KeyCode::Enter);
-    let pressed_backspace = key_input.just_pressed(KeyCode::Backspace);
-    let input_string = char_input.read().fold(String::new(), |mut accum, value| {
+    let mut input_string = char_input.read().fold(String::new(), |mut accum, value| {
         for c in value.char.as_str().chars() {
+            if c.is_control() { continue }
             match bevy_ascii::prelude::Character::from(c) {
                 Character::Nil | 
                 Character::ArrowUp |
@@ -107,42 +254,70 @@ fn terminal_input (
         }
         
         accum
-    }).to_lowercase();
+    });
     let mouse_delta = scroll_event.read().fold(0.0, |accum, value| accum + value.y) * 0.25;
     
+    // Key repeats arrive as extra pressed events, so holding an editing key keeps editing.
+    let mut pressed_keys = key_events.read()
+        .filter(|event| event.state == ButtonState::Pressed)
+        .map(|event| event.key_code)
+        .collect::<Vec<_>>();
+    let ctrl = key_input.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]);
+    
+    // The computer is talking, or has taken the keyboard. The player can still
+    // scroll through history and interrupt, but not type or edit.
+    if typewriter.is_some_and(|typewriter| typewriter.is_busy()) || ghost.is_some_and(TerminalGhost::is_blocking) {
+        input_string.clear();
+        pressed_keys.retain(|key| match key {
+            KeyCode::PageUp | KeyCode::PageDown => true,
+            KeyCode::KeyC => ctrl,
+            _ => false,
+        });
+    }
+    
     terminal.blink_timer.tick(time.delta());
     if terminal.blink_timer.finished() {
         terminal.blink = !terminal.blink;
         mark_ui_dirty.send(AsciiMarkDirtyEvent);
     }
     
-    if pressed_enter || pressed_backspace || !input_string.is_empty() || mouse_delta != 0.0 {
+    if !pressed_keys.is_empty() || !input_string.is_empty() || mouse_delta != 0.0 {
         
-        // println!("Input String {input_string} | Backspace {pressed_backspace} | Enter {pressed_enter}");
+        let page_height = TerminalComponent::page_height(node);
+        terminal.scrollback.scroll_by(mouse_delta.round() as i32, page_height);
         
-        terminal.scroll = (terminal.scroll as f32 + mouse_delta.round()) as u32;
-        terminal.scroll = terminal.scroll.min((terminal.lines.len() as i32 - node.bounds.height as i32 + 7).max(0) as u32);
-        
-        terminal.current_input.push_str(&input_string);
+        terminal.input.insert_str(&input_string);
         if !input_string.is_empty(){
-            terminal.scroll = 0;
-        }
-        
-        if pressed_backspace {
-            terminal.current_input.pop();
-            terminal.scroll = 0;
+            terminal.scrollback.scroll_to_bottom();
         }
         
-        if pressed_enter {
-            let input = terminal.current_input.clone();
-            terminal.add_line(format!("User:> {}", input).as_str(), node.bounds.width - 6);
-            // terminal.lines.push_back(format!("User:> {}", input));
-            if terminal.lines.len() > 400 {
-                terminal.lines.pop_front();
+        for key in pressed_keys {
+            match key {
+                KeyCode::PageUp => { terminal.scrollback.page_up(page_height); continue },
+                KeyCode::PageDown => { terminal.scrollback.page_down(page_height); continue },
+                KeyCode::Backspace => terminal.input.backspace(),
+                KeyCode::Delete => terminal.input.delete(),
+                KeyCode::ArrowLeft if ctrl => terminal.input.move_word_left(),
+                KeyCode::ArrowRight if ctrl => terminal.input.move_word_right(),
+                KeyCode::ArrowLeft => terminal.input.move_left(),
+                KeyCode::ArrowRight => terminal.input.move_right(),
+                KeyCode::Home => terminal.input.move_home(),
+                KeyCode::End => terminal.input.move_end(),
+                KeyCode::ArrowUp => terminal.input.history_previous(),
+                KeyCode::ArrowDown => terminal.input.history_next(),
+                KeyCode::KeyC if ctrl => {
+                    if foreground.is_none() {
+                        let line = format!("{}{}^C", terminal.prompt, terminal.input.text());
+                        terminal.add_line(&line);
+                        terminal.input.clear();
+                    }
+                    interrupt_event.send(TerminalInterruptEvent { terminal : terminal_entity });
+                },
+                KeyCode::Tab if foreground.is_none() => { completion_event.send(TerminalCompletionEvent { terminal : terminal_entity }); },
+                KeyCode::Enter => submit_input(&mut terminal, terminal_entity, foreground.is_some(), &mut terminal_command_event, &mut program_input_event),
+                _ => continue,
             }
-            terminal.current_input.clear();
-            terminal_command_event.send(TerminalCommandEvent(input.trim().to_string()));
-            terminal.scroll = 0;
+            terminal.scrollback.scroll_to_bottom();
         }
         
         terminal.blink = true;
@@ -151,6 +326,68 @@ fn terminal_input (
     }
 }
 
+/// Enters the input line: echoes it and hands it to the foreground program, or the shell if there is none.
+pub fn submit_input(
+    terminal : &mut TerminalComponent,
+    terminal_entity : Entity,
+    has_foreground : bool,
+    command_events : &mut EventWriter<TerminalCommandEvent>,
+    program_input_events : &mut EventWriter<TerminalProgramInputEvent>,
+) {
+    let (input, line) = match terminal.masked {
+        true => (terminal.input.take(), terminal.prompt.clone()),
+        false => {
+            let input = terminal.input.submit();
+            let line = format!("{}{}", terminal.prompt, input);
+            (input, line)
+        },
+    };
+    terminal.add_line(&line);
+    
+    if has_foreground {
+        program_input_events.send(TerminalProgramInputEvent { terminal : terminal_entity, line : input });
+    } else {
+        command_events.send(TerminalCommandEvent { terminal : terminal_entity, line : input.trim().to_string() });
+    }
+}
+
+/// Re-wraps the transcript whenever the screen it is drawn on changes size.
+fn fit_terminals_to_screen(
+    mut terminals : Query<(&mut TerminalComponent, &AsciiNode)>,
+    mut mark_ui_dirty : EventWriter<AsciiMarkDirtyEvent>,
+) {
+    for (mut terminal, node) in terminals.iter_mut() {
+        let width = TerminalComponent::text_width(node);
+        let page_height = TerminalComponent::page_height(node);
+        if terminal.scrollback.width() == width && terminal.scrollback.page_height() == page_height { continue }
+        
+        terminal.scrollback.set_page_height(page_height);
+        terminal.scrollback.set_width(width);
+        mark_ui_dirty.send(AsciiMarkDirtyEvent);
+    }
+}
+
+/// Whatever was on screen is lost with the power. The next boot starts clean,
+/// back on the first console.
+fn reset_terminals(
+    mut commands : Commands,
+    mut terminals : Query<(Entity, &mut TerminalComponent, &ComputerNode)>,
+    mut powered_off_events : EventReader<ComputerPoweredOffEvent>,
+) {
+    for ComputerPoweredOffEvent(computer) in powered_off_events.read() {
+        for (entity, mut terminal, node) in terminals.iter_mut() {
+            if node.0 != *computer { continue }
+            
+            *terminal = TerminalComponent::new(terminal.console);
+            commands.entity(entity).remove::<TerminalForeground>();
+            match terminal.console {
+                0 => commands.entity(entity).insert(FocusedTerminal),
+                _ => commands.entity(entity).remove::<FocusedTerminal>(),
+            };
+        }
+    }
+}
+
 //==============================================================================
 //         Terminal Commands
 //==============================================================================
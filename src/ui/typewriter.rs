@@ -0,0 +1,227 @@
+use std::collections::VecDeque;
+
+use bevy::prelude::*;
+use bevy_ascii::prelude::*;
+
+use crate::{audio::{sound::PlaySoundEvent, AudioAssets}, scene::computer_world::{ComputerNode, ComputerPoweredOffEvent}};
+
+use super::{markup::{escape_markup, parse_markup, to_markup, StyledChar}, terminal::TerminalComponent};
+
+/// 1200 baud, or 120 characters a second with a start and stop bit.
+pub const DEFAULT_BAUD : f32 = 1200.0;
+
+//==============================================================================
+//         Typewriter Plugin
+//==============================================================================
+
+pub struct TypewriterPlugin;
+
+impl Plugin for TypewriterPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_systems(Update, update_typewriters)
+            .add_systems(PostUpdate, reset_typewriters)
+
+            .add_event::<TypewriterSignalEvent>()
+        ;
+    }
+}
+
+//==============================================================================
+//         Terminal Typewriter
+//==============================================================================
+
+#[derive(Debug, Clone)]
+pub enum TypewriterItem {
+    /// A line of markup, typed out one character at a time.
+    Line(String),
+    /// Waits this many seconds before typing on.
+    Pause(f32),
+    /// Sends a [`TypewriterSignalEvent`] once everything before it is typed.
+    Signal(String),
+}
+
+/// Streams output into the terminal it sits on at `baud`, instead of it all
+/// appearing at once. The player can't type while it is busy.
+#[derive(Component, Debug)]
+pub struct TerminalTypewriter {
+    pub baud : f32,
+    queue : VecDeque<TypewriterItem>,
+    /// The line being typed.
+    current : Option<Vec<StyledChar>>,
+    /// Which scrollback line it is being typed into, see [`super::scrollback::Scrollback::last_line_id`].
+    current_line : Option<usize>,
+    typed : usize,
+    pause : f32,
+    carry : f32,
+}
+
+impl Default for TerminalTypewriter {
+    fn default() -> Self {
+        Self {
+            baud : DEFAULT_BAUD,
+            queue : VecDeque::new(),
+            current : None,
+            current_line : None,
+            typed : 0,
+            pause : 0.0,
+            carry : 0.0,
+        }
+    }
+}
+
+impl TerminalTypewriter {
+    pub fn write_line(&mut self, line : &str) -> &mut Self {
+        self.write_markup_line(&escape_markup(line))
+    }
+
+    pub fn write_markup_line(&mut self, line : &str) -> &mut Self {
+        self.queue.push_back(TypewriterItem::Line(line.to_string()));
+        self
+    }
+
+    pub fn pause(&mut self, seconds : f32) -> &mut Self {
+        self.queue.push_back(TypewriterItem::Pause(seconds));
+        self
+    }
+
+    pub fn signal(&mut self, name : impl Into<String>) -> &mut Self {
+        self.queue.push_back(TypewriterItem::Signal(name.into()));
+        self
+    }
+
+    pub fn is_busy(&self) -> bool {
+        !self.queue.is_empty() || self.current.is_some() || self.pause > 0.0
+    }
+
+    /// Rewrites the newest line of `terminal`. If it is still being typed,
+    /// the rest of it is dropped first so the old line can't creep back in.
+    pub fn replace_last(&mut self, terminal : &mut TerminalComponent, line : &str) {
+        if self.current_line.is_some() && self.current_line == terminal.scrollback.last_line_id() {
+            self.current = None;
+            self.current_line = None;
+            self.typed = 0;
+        }
+        terminal.scrollback.replace_last(line.to_string());
+    }
+
+    /// Drops everything that hasn't been typed yet. Pending signals are never sent.
+    pub fn clear(&mut self) {
+        self.queue.clear();
+        self.current = None;
+        self.current_line = None;
+        self.typed = 0;
+        self.pause = 0.0;
+        self.carry = 0.0;
+    }
+
+    fn seconds_per_character(&self) -> f32 {
+        10.0 / self.baud.max(1.0)
+    }
+}
+
+//==============================================================================
+//         Typewriter Events
+//==============================================================================
+
+/// Sent when a typewriter reaches a [`TypewriterItem::Signal`], so a story
+/// script can wait for the computer to finish talking before moving on.
+#[derive(Event, Debug, Clone)]
+pub struct TypewriterSignalEvent {
+    pub terminal : Entity,
+    pub name : String,
+}
+
+//==============================================================================
+//         Typewriter Systems
+//==============================================================================
+
+fn update_typewriters(
+    mut typewriters : Query<(Entity, &mut TerminalTypewriter, &mut TerminalComponent)>,
+    mut signal_events : EventWriter<TypewriterSignalEvent>,
+    mut sound_effect_events : EventWriter<PlaySoundEvent>,
+    mut mark_dirty : EventWriter<AsciiMarkDirtyEvent>,
+    audio_assets : Res<AudioAssets>,
+    time : Res<Time>,
+) {
+    for (entity, mut typewriter, mut terminal) in typewriters.iter_mut() {
+        if !typewriter.is_busy() { continue }
+
+        let character_time = typewriter.seconds_per_character();
+        let mut time_left = typewriter.carry + time.delta_seconds();
+        let mut clicked = false;
+
+        loop {
+            if typewriter.pause > 0.0 {
+                let waited = typewriter.pause.min(time_left);
+                typewriter.pause -= waited;
+                time_left -= waited;
+                if typewriter.pause > 0.0 { break }
+                continue;
+            }
+
+            if let Some(length) = typewriter.current.as_ref().map(Vec::len) {
+                if typewriter.typed >= length {
+                    typewriter.current = None;
+                    typewriter.current_line = None;
+                    typewriter.typed = 0;
+                    continue;
+                }
+
+                if time_left < character_time { break }
+                time_left -= character_time;
+
+                typewriter.typed += 1;
+                let Some(line) = &typewriter.current else { break };
+                let character = line[typewriter.typed - 1].character;
+                let typed = to_markup(&line[..typewriter.typed]);
+
+                // Something else wrote to the terminal mid-line, so carry on below it instead of over it.
+                if typewriter.current_line != terminal.scrollback.last_line_id() {
+                    terminal.scrollback.push(typed);
+                    typewriter.current_line = terminal.scrollback.last_line_id();
+                } else {
+                    terminal.scrollback.replace_last(typed);
+                }
+
+                // One click a frame is plenty; at high baud rates they would all blur together anyway.
+                if !clicked && !character.is_whitespace() {
+                    let sound = match character.is_alphanumeric() {
+                        true => audio_assets.computer_light_write.clone(),
+                        false => audio_assets.computer_medium_write.clone(),
+                    };
+                    sound_effect_events.send(PlaySoundEvent::new(sound, 0.05, None));
+                    clicked = true;
+                }
+                continue;
+            }
+
+            match typewriter.queue.pop_front() {
+                Some(TypewriterItem::Line(line)) => {
+                    typewriter.current = Some(parse_markup(&line));
+                    terminal.scrollback.push(String::new());
+                    typewriter.current_line = terminal.scrollback.last_line_id();
+                },
+                Some(TypewriterItem::Pause(seconds)) => typewriter.pause = seconds,
+                Some(TypewriterItem::Signal(name)) => { signal_events.send(TypewriterSignalEvent { terminal : entity, name }); },
+                None => break,
+            }
+        }
+
+        // The view follows the text on its own while it is at the bottom, and stays put if the player scrolled back.
+        typewriter.carry = if typewriter.is_busy() { time_left } else { 0.0 };
+        mark_dirty.send(AsciiMarkDirtyEvent);
+    }
+}
+
+fn reset_typewriters(
+    mut typewriters : Query<(&mut TerminalTypewriter, &ComputerNode)>,
+    mut powered_off_events : EventReader<ComputerPoweredOffEvent>,
+) {
+    for ComputerPoweredOffEvent(computer) in powered_off_events.read() {
+        for (mut typewriter, node) in typewriters.iter_mut() {
+            if node.0 != *computer { continue }
+            typewriter.clear();
+        }
+    }
+}